@@ -6,24 +6,21 @@ use itertools::Itertools;
 use recorder::{
     nodes::list_available_nodes,
     pipeline_processing::{
+        control_server::serve_control,
+        node_catalogue::{node_catalogue, pipeline_schema},
         parametrizable::prelude::*,
+        pipeline_file::load_pipeline,
         processing_context::ProcessingContext,
-        processing_graph::{ProcessingGraphBuilder, ProcessingNodeConfig, SerdeNodeConfig},
+        processing_graph::{ProcessingGraphBuilder, ProcessingNodeConfig},
     },
 };
-use serde::Deserialize;
 use std::{
     collections::{BTreeMap, HashMap},
     iter::once,
+    net::SocketAddr,
     sync::{Arc, Mutex},
 };
 
-#[derive(Deserialize, Debug)]
-struct PipelineConfig {
-    #[serde(flatten)]
-    nodes: HashMap<String, SerdeNodeConfig>,
-}
-
 #[cfg(feature = "dhat-heap")]
 #[global_allocator]
 static ALLOC: dhat::Alloc = dhat::Alloc;
@@ -66,6 +63,40 @@ enum Command {
         )]
         vars: Vec<String>,
     },
+    /// check a pipeline configuration file without running it and print the
+    /// resolved graph
+    Check {
+        /// path to the configuration file
+        file: std::path::PathBuf,
+        /// variables to substitute in the config file
+        #[clap(
+            short = 's',
+            long = "set",
+            name = "key=value",
+            allow_hyphen_values(true),
+            takes_value = true
+        )]
+        vars: Vec<String>,
+        /// also create the nodes (this opens files and devices) to print the
+        /// caps of every node
+        #[clap(long)]
+        caps: bool,
+        /// write the graph in the graphviz dot format to this file
+        #[clap(long)]
+        dot: Option<std::path::PathBuf>,
+        /// write the graph with all defaults filled in as yaml to this file
+        #[clap(long)]
+        yaml: Option<std::path::PathBuf>,
+    },
+    /// list all available nodes
+    ListNodes {
+        /// print every node with its parameters, inputs and output as json
+        #[clap(long)]
+        json: bool,
+        /// print a json schema for pipeline configuration files
+        #[clap(long, conflicts_with = "json")]
+        schema: bool,
+    },
 }
 
 /// Raw Image / Video Converter
@@ -82,6 +113,11 @@ struct Args {
     /// show a progress bar
     #[clap(long, short)]
     show_progress: bool,
+    /// serve an http api to follow the progress of the pipeline, to pause,
+    /// resume or stop it and to change the live parameters of the nodes while
+    /// it runs, e.g. 127.0.0.1:9179
+    #[clap(long)]
+    control_address: Option<SocketAddr>,
 }
 
 // used to have the convenience of ? for error handling
@@ -107,27 +143,35 @@ fn work() -> Result<()> {
             processing_graph.build(&processing_context)?
         }
         Command::FromFile { file, vars } => {
-            let vars = vars.into_iter().map(|v| {
-                let mut split = v.splitn(2, '=');
-                let name = split.next().unwrap();
-                let value = split.next().ok_or_else(|| anyhow::anyhow!("expected variable name value pair `{v}` to contain atleast one equals (=) sign"))?;
-                Ok((name.to_owned(), value.to_owned()))
-            }).collect::<Result<BTreeMap<_, _>>>()?;
-            let mut handlebars = handlebars::Handlebars::new();
-            handlebars.set_strict_mode(true);
-            let config: PipelineConfig = serde_yaml::from_str(
-                &handlebars.render_template(&std::fs::read_to_string(file)?, &vars)?,
-            )?;
-
-            let mut processing_graph = ProcessingGraphBuilder::new();
-
-            for (name, node) in config.nodes {
-                processing_graph.add(name, node.into())?;
+            graph_from_file(&file, vars)?.build(&processing_context)?
+        }
+        Command::Check { file, vars, caps, dot, yaml } => {
+            let processing_graph = graph_from_file(&file, vars)?;
+            if let Some(dot) = dot {
+                std::fs::write(dot, processing_graph.to_dot()?)?;
             }
-            processing_graph.build(&processing_context)?
+            if let Some(yaml) = yaml {
+                std::fs::write(yaml, processing_graph.to_yaml()?)?;
+            }
+            let context = if caps { Some(&processing_context) } else { None };
+            return print_resolved_graph(processing_graph, context);
+        }
+        Command::ListNodes { json, schema } => {
+            if json {
+                println!("{:#}", node_catalogue(&list_available_nodes()));
+            } else if schema {
+                println!("{:#}", pipeline_schema(&list_available_nodes()));
+            } else {
+                print!("{}", nodes_usages_string());
+            }
+            return Ok(());
         }
     };
 
+    if let Some(address) = args.control_address {
+        let address = serve_control(&processing_context, address)?;
+        eprintln!("control api listening on http://{address}");
+    }
 
     if args.show_progress {
         let progressbar: Arc<Mutex<Option<ProgressBar>>> = Default::default();
@@ -156,6 +200,57 @@ fn work() -> Result<()> {
     Ok(())
 }
 
+fn graph_from_file(
+    file: &std::path::Path,
+    vars: Vec<String>,
+) -> Result<ProcessingGraphBuilder<String>> {
+    let vars = vars.into_iter().map(|v| {
+        let mut split = v.splitn(2, '=');
+        let name = split.next().unwrap();
+        let value = split.next().ok_or_else(|| anyhow::anyhow!("expected variable name value pair `{v}` to contain atleast one equals (=) sign"))?;
+        Ok((name.to_owned(), serde_yaml::Value::String(value.to_owned())))
+    }).collect::<Result<BTreeMap<_, _>>>()?;
+
+    load_pipeline(file, &vars)
+}
+
+/// Prints every node with its inputs and parameters. If a context is given, the
+/// graph is built to print the caps of the nodes too.
+fn print_resolved_graph(
+    processing_graph: ProcessingGraphBuilder<String>,
+    context: Option<&ProcessingContext>,
+) -> Result<()> {
+    let nodes = processing_graph.resolved_nodes()?;
+    let built_graph = context.map(|context| processing_graph.build(context)).transpose()?;
+
+    for node in &nodes {
+        println!("{}: {}", node.id, node.ty);
+        for (name, input) in &node.inputs {
+            println!("    {name}: <{input}");
+        }
+        for (name, value) in &node.parameters {
+            println!("    {name}: {value}");
+        }
+        if let Some(built_graph) = &built_graph {
+            match built_graph.get_node(node.node_id).assert_input_node() {
+                Ok(built_node) => {
+                    let caps = built_node.get_caps();
+                    let frame_count = match caps.frame_count {
+                        Some(frame_count) => format!("{frame_count} frames"),
+                        None => "unknown number of frames".to_string(),
+                    };
+                    let access = if caps.random_access { "random" } else { "sequential" };
+                    println!("    caps: {frame_count}, {access} access");
+                }
+                Err(_) => println!("    caps: none (sink)"),
+            }
+        }
+    }
+
+    eprintln!("\nthe pipeline with {} nodes is valid", nodes.len());
+    Ok(())
+}
+
 fn nodes_usages_string() -> String {
     list_available_nodes()
         .keys()
@@ -198,12 +293,7 @@ fn processing_node_from_commandline(
     let parameters: HashMap<_, _> = parameters_description
         .0
         .iter()
-        .filter(|(_, descriptor)| {
-            !matches!(
-                descriptor,
-                Mandatory(NodeInputParameter) | WithDefault(NodeInputParameter, _)
-            )
-        })
+        .filter(|(_, descriptor)| !descriptor.is_node_input())
         .map(|(key, parameter_type)| {
             Ok((
                 key.to_string(),
@@ -241,11 +331,11 @@ fn clap_app_from_node_name(name: &str) -> Result<clap::Command<'static>> {
     let parameters_description = leak(&node_descriptor.parameters_descriptor);
     for (key, parameter_type) in parameters_description.0.iter() {
         let parameter_type = leak(parameter_type);
-        if let Mandatory(NodeInputParameter) | WithDefault(NodeInputParameter, _) = parameter_type {
+        if parameter_type.is_node_input() {
             continue;
         };
         let parameter_type_for_closure = parameter_type.clone();
-        app = app.arg(match parameter_type {
+        let arg = match parameter_type {
             Mandatory(_) => Arg::new(leak(&key).as_str())
                 .long(key)
                 .takes_value(true)
@@ -269,6 +359,13 @@ fn clap_app_from_node_name(name: &str) -> Result<clap::Command<'static>> {
                 })
                 .default_value(Box::leak(Box::new(default.to_string())))
                 .required(false),
+        };
+        app = app.arg(match parameter_type.parameter_type() {
+            ChoiceParameter(choices) => arg
+                .value_name(leak(&choices.join("|")).as_str())
+                .possible_values(choices.iter().copied())
+                .ignore_case(true),
+            _ => arg,
         })
     }
     Ok(app)
@@ -33,13 +33,22 @@ pub fn player(context: &mut WidgetContext) -> Fragment {
                 let mut graph_builder = ProcessingGraphBuilder::new();
                 graph_builder.add(
                     "reader".to_string(),
-                    serde_yaml::from_str::<SerdeNodeConfig>(
-                            &format!("
+                    serde_yaml::from_str::<SerdeNodeConfig>(&if files.ends_with(".zst") {
+                        format!(
+                            "
+                            type: ZstdBlobReader
+                            file: {files}
+                            "
+                        )
+                    } else {
+                        format!(
+                            "
                             type: CinemaDngReader
                             file-pattern: {files}
-                            "),
-                    )?
-                    .into(),
+                            "
+                        )
+                    })?
+                    .try_into()?,
                 )?;
                 graph_builder.add(
                     "converter".to_string(),
@@ -49,7 +58,7 @@ pub fn player(context: &mut WidgetContext) -> Fragment {
                     input: <reader
                 ",
                     )?
-                    .into(),
+                    .try_into()?,
                 )?;
                 let debayer = graph_builder.add(
                     "debayer".to_string(),
@@ -59,7 +68,7 @@ pub fn player(context: &mut WidgetContext) -> Fragment {
                     input: <converter
                 ",
                     )?
-                    .into(),
+                    .try_into()?,
                 )?;
                 let graph = graph_builder.build(&processing_context)?;
 
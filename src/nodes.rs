@@ -9,11 +9,19 @@ use crate::{
         average::Average,
         benchmark_sink::BenchmarkSink,
         bitdepth_convert::BitDepthConverter,
+        color_voodoo::CpuColorVoodoo,
+        composite::Composite,
+        darkframe_subtract::CpuDarkframeSubtract,
+        debayer::{CpuDebayer, CpuDebayerResolutionLoss},
         dual_frame_raw_decoder::{DualFrameRawDecoder, ReverseDualFrameRawDecoder},
         fp_to_uint::Fp32ToUInt16,
+        lossless::{LosslessCompress, LosslessDecompress},
+        lut_3d::CpuLut3d,
+        metrics::QualityMetrics,
         row_noise_removal::RowNoiseRemoval,
-        sz3::SZ3Compress,
-        zstd::ZstdBlobReader,
+        statistics::{Statistics, StatisticsWriter},
+        sz3::{SZ3Compress, SZ3Decompress},
+        zstd::{ZstdBlobReader, ZstdBlobWriter},
     },
     nodes_gpu::{
         bitdepth_convert::GpuBitDepthConverter,
@@ -25,13 +33,25 @@ use crate::{
         lut_3d::Lut3d,
     },
     nodes_io::{
+        reader_axiom_container::AxiomContainerReader,
         reader_cinema_dng::CinemaDngReader,
         reader_raw::{RawBlobReader, RawDirectoryReader},
         reader_tcp::TcpReader,
+        sz3_blob::{SZ3BlobReader, SZ3BlobWriter},
+        writer_axiom_container::AxiomContainerWriter,
         writer_cinema_dng::CinemaDngWriter,
         writer_raw::{RawBlobWriter, RawDirectoryWriter},
+        writer_tcp::TcpWriter,
+    },
+    nodes_util::{
+        cache::Cache,
+        concat::{Concat, Interleave},
+        range::{Loop, Reverse, Stride, Trim},
+        record_gate::RecordGate,
+        retime::Retime,
+        split::Split,
+        test_pattern::TestPattern,
     },
-    nodes_util::{cache::Cache, split::Split},
     pipeline_processing::{
         node::{Node, NodeID, ProcessingNodeIntoNode, SinkNodeIntoNode},
         parametrizable::prelude::*,
@@ -42,6 +62,22 @@ use crate::{
 use crate::nodes_io::{frameserver_cinema_dng::CinemaDngFrameserver, writer_ffmpeg::FfmpegWriter};
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
+
+/// gpu nodes that have a cpu implementation with the same parameters, that is
+/// used in their place if no gpu is present
+const CPU_FALLBACKS: &[(&str, &str)] = &[
+    ("Debayer", "CpuDebayer"),
+    ("DebayerResolutionLoss", "CpuDebayerResolutionLoss"),
+    ("ColorVoodoo", "CpuColorVoodoo"),
+    ("Lut3d", "CpuLut3d"),
+    ("DarkframeSubtract", "CpuDarkframeSubtract"),
+    ("GpuBitDepthConverter", "BitDepthConverter"),
+];
+
+fn cpu_fallback_for(name: &str) -> Option<&'static str> {
+    CPU_FALLBACKS.iter().find(|(gpu, _)| *gpu == name).map(|(_, cpu)| *cpu)
+}
+
 macro_rules! generate_dynamic_node_creation_functions {
     ($($(#[$m:meta])? $x:ty),+ $(,)?) => {
         pub fn list_available_nodes() -> HashMap<String, ParameterizableDescriptor> {
@@ -53,12 +89,21 @@ macro_rules! generate_dynamic_node_creation_functions {
             to_return
         }
 
-        pub fn create_node_from_name(name: &str, node_id: NodeID, parameters: Parameters, inputs: HashMap<String, Node>, is_input_to: &[NodeID], context: &ProcessingContext) -> Result<Node> {
+        pub fn create_node_from_name(name: &str, node_id: NodeID, node_name: &str, parameters: Parameters, inputs: HashMap<String, Node>, is_input_to: &[NodeID], context: &ProcessingContext) -> Result<Node> {
+            let name = match cpu_fallback_for(name) {
+                Some(fallback) if !context.has_vulkan() => {
+                    eprintln!("no gpu present, using {fallback} instead of {name}");
+                    fallback
+                }
+                _ => name,
+            };
+
             $(
                 $(#[$m])?
                 if name == <$x>::get_name() {
+                    let descriptor = <$x>::describe_parameters();
                     let parameters = parameters.add_inputs(node_id, inputs)?;
-                    let parameters = parameters.add_defaults(<$x>::describe_parameters());
+                    let parameters = parameters.check_values(&descriptor)?.add_defaults(descriptor).for_node(node_name);
                     return Ok(<$x>::from_parameters(parameters, is_input_to, &context)?.into_processing_element())
                 };
             )+
@@ -76,6 +121,8 @@ generate_dynamic_node_creation_functions![
     GpuBitDepthConverter,
     Debayer,
     DebayerResolutionLoss,
+    CpuDebayer,
+    CpuDebayerResolutionLoss,
     #[cfg(target_os = "linux")]
     Display,
     BitDepthConverter,
@@ -83,16 +130,41 @@ generate_dynamic_node_creation_functions![
     ReverseDualFrameRawDecoder,
     BenchmarkSink,
     ColorVoodoo,
+    CpuColorVoodoo,
     RawDirectoryWriter,
     RawBlobWriter,
+    AxiomContainerReader,
+    AxiomContainerWriter,
     Lut3d,
+    CpuLut3d,
     Average,
     TcpReader,
+    TcpWriter,
     Cache,
     Split,
+    Trim,
+    Stride,
+    Reverse,
+    Loop,
+    Retime,
+    Concat,
+    Interleave,
+    TestPattern,
+    RecordGate,
+    Composite,
+    QualityMetrics,
+    Statistics,
+    StatisticsWriter,
     SZ3Compress,
+    SZ3Decompress,
+    SZ3BlobWriter,
+    SZ3BlobReader,
+    LosslessCompress,
+    LosslessDecompress,
     ZstdBlobReader,
+    ZstdBlobWriter,
     DarkframeSubtract,
+    CpuDarkframeSubtract,
     RowNoiseRemoval,
     Histogram,
     #[cfg(target_os = "linux")]
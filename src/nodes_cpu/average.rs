@@ -2,6 +2,7 @@ use crate::{
     pipeline_processing::{
         buffers::ChunkedCpuBuffer,
         frame::{Frame, Raw},
+        metadata::FrameMetadata,
         node::{Caps, InputProcessingNode, NodeID, ProcessingNode, Request},
         parametrizable::prelude::*,
         payload::Payload,
@@ -28,6 +29,9 @@ impl Parameterizable for Average {
             .with("n", Mandatory(NaturalGreaterZero()))
             .with("std", Optional(BoolParameter))
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw])
+    }
 
     fn from_parameters(
         mut parameters: Parameters,
@@ -200,8 +204,8 @@ impl ProcessingNode for Average {
 
         let mut interp = interp;
         interp.bit_depth = 32;
-        let avg_frame = Frame { storage: avg_buffer, interp };
-        let std_frame = Frame { storage: std_buffer, interp };
+        let avg_frame = Frame { storage: avg_buffer, interp, metadata: FrameMetadata::default() };
+        let std_frame = Frame { storage: std_buffer, interp, metadata: FrameMetadata::default() };
 
         if self.produce_std {
             Ok(Payload::from(vec![Payload::from(avg_frame), Payload::from(std_frame)]))
@@ -23,6 +23,7 @@ impl Parameterizable for BenchmarkSink {
             .with("priority", Optional(U8()))
             .with("unordered", Optional(Bool()))
     }
+    fn describe_port_types() -> PortTypes { PortTypes::new().sink() }
 
     fn from_parameters(
         mut parameters: Parameters,
@@ -105,10 +106,11 @@ impl SinkNode for BenchmarkSink {
                 0,
             );
             let reporter = FPSReporter::new("pipeline");
-            loop {
-                rx.recv_async().await?;
+            // the input ends when the run is stopped
+            while rx.recv_async().await.is_ok() {
                 reporter.frame();
             }
+            Ok(())
         }
     }
 }
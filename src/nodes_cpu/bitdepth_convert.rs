@@ -25,6 +25,9 @@ impl Parameterizable for BitDepthConverter {
             .with("input", Mandatory(NodeInputParameter))
             .with("to", WithDefault(U8(), IntRangeValue(8)))
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).with_output(&[FrameType::Raw])
+    }
 
     fn from_parameters(
         mut parameters: Parameters,
@@ -47,7 +50,15 @@ impl ProcessingNode for BitDepthConverter {
             .context
             .ensure_cpu_buffer::<Raw>(&input)
             .context("Wrong input format for BitDepthConverter")?;
-        let interp = Raw { bit_depth: self.target_bitdepth, ..frame.interp };
+        // the 12 to 16 bit conversion only unpacks the values, all other conversions
+        // shift them
+        let interp = if (frame.interp.bit_depth == 12) && (self.target_bitdepth == 16) {
+            Raw { bit_depth: self.target_bitdepth, ..frame.interp }
+        } else if frame.interp.bit_depth == 16 {
+            from_16_bit_interp(frame.interp, self.target_bitdepth)
+        } else {
+            frame.interp.with_shifted_bit_depth(self.target_bitdepth)
+        };
         let mut new_buffer = unsafe { self.context.get_uninit_cpu_buffer(interp.required_bytes()) };
 
         if frame.interp.bit_depth == self.target_bitdepth {
@@ -78,6 +89,29 @@ impl ProcessingNode for BitDepthConverter {
                     }
                 })
             });
+        } else if (frame.interp.bit_depth == 16) && [8, 12].contains(&self.target_bitdepth) {
+            let shift = significant_bits(&frame.interp).saturating_sub(interp.bit_depth as u32);
+            let max = Raw::default_white_level(interp.bit_depth) as u16;
+            let convert = |value: u16| (value >> shift).min(max);
+            new_buffer.as_mut_slice(|new_buffer| {
+                frame.storage.as_slice(|frame_storage| {
+                    let frame_storage: &[u16] = bytemuck::cast_slice(frame_storage);
+                    if interp.bit_depth == 8 {
+                        for (input, output) in frame_storage.iter().zip(new_buffer.iter_mut()) {
+                            *output = convert(*input) as u8;
+                        }
+                    } else {
+                        for (input, output) in
+                            frame_storage.chunks_exact(2).zip(new_buffer.chunks_exact_mut(3))
+                        {
+                            let (a, b) = (convert(input[0]), convert(input[1]));
+                            output[0] = (a >> 4) as u8;
+                            output[1] = (((a << 4) & 0xf0) | (b >> 8)) as u8;
+                            output[2] = b as u8;
+                        }
+                    }
+                })
+            });
         } else {
             println!(
                 "using unoptimized bitdepth conversion path from {} to {}",
@@ -115,10 +149,59 @@ impl ProcessingNode for BitDepthConverter {
             });
         }
 
-        let new_frame = Frame { storage: new_buffer, interp };
+        let new_frame = Frame { storage: new_buffer, interp, metadata: frame.metadata.clone() };
 
         Ok(Payload::from(new_frame))
     }
 
     fn get_caps(&self) -> Caps { self.input.get_caps() }
 }
+
+/// The number of bits the values of a 16 bit frame use. 12 bit frames that
+/// were unpacked to 16 bit still only use 12 bits, which their white level
+/// tells.
+fn significant_bits(interp: &Raw) -> u32 { (32 - interp.white_level.leading_zeros()).clamp(8, 16) }
+
+/// Converting 16 bit frames shifts the values by the bits they use more than
+/// the target bit depth, so unpacked 12 bit frames are packed again without a
+/// shift.
+fn from_16_bit_interp(interp: Raw, bit_depth: u64) -> Raw {
+    let shift = significant_bits(&interp).saturating_sub(bit_depth as u32);
+    let max = Raw::default_white_level(bit_depth);
+    Raw {
+        bit_depth,
+        black_level: interp.black_level.map(|level| (level >> shift).min(max)),
+        white_level: (interp.white_level >> shift).min(max),
+        ..interp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline_processing::frame::CfaDescriptor;
+
+    #[test]
+    fn test_from_16_bit_interp() {
+        let interp = |white_level| Raw {
+            width: 4,
+            height: 2,
+            bit_depth: 16,
+            cfa: CfaDescriptor::from_first_red(true, true),
+            fps: 24.0,
+            black_level: [128; 4],
+            white_level,
+        };
+        // unpacked 12 bit values
+        let packed = from_16_bit_interp(interp(4085), 12);
+        assert_eq!(
+            (packed.bit_depth, packed.black_level, packed.white_level),
+            (12, [128; 4], 4085)
+        );
+        let shifted = from_16_bit_interp(interp(4085), 8);
+        assert_eq!((shifted.bit_depth, shifted.black_level, shifted.white_level), (8, [8; 4], 255));
+        // full 16 bit values
+        let shifted = from_16_bit_interp(interp(65535), 8);
+        assert_eq!((shifted.black_level, shifted.white_level), ([0; 4], 255));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::pipeline_processing::{
+    frame::{Frame, Rgb},
+    live_parameters::LiveParameter,
+    node::{Caps, InputProcessingNode, NodeID, ProcessingNode, Request},
+    parametrizable::prelude::*,
+    payload::Payload,
+    processing_context::ProcessingContext,
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+pub struct CpuColorVoodoo {
+    input: InputProcessingNode,
+    pedestal: LiveParameter<u64>,
+    s_gamma: LiveParameter<f64>,
+    v_gamma: LiveParameter<f64>,
+    context: ProcessingContext,
+}
+
+impl Parameterizable for CpuColorVoodoo {
+    const DESCRIPTION: Option<&'static str> =
+        Some("cpu version of ColorVoodoo (used in its place if no gpu is present)");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("input", Mandatory(NodeInputParameter))
+            .with("pedestal", WithDefault(U8(), IntRangeValue(8)))
+            .with("s_gamma", WithDefault(FloatRange(0.0, 100.0), FloatRangeValue(1.0)))
+            .with("v_gamma", WithDefault(FloatRange(0.0, 100.0), FloatRangeValue(1.0)))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Rgb]).with_output(&[FrameType::Rgb])
+    }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> Result<Self> {
+        Ok(Self {
+            input: parameters.take("input")?,
+            pedestal: parameters.take_live("pedestal", context)?,
+            s_gamma: parameters.take_live("s_gamma", context)?,
+            v_gamma: parameters.take_live("v_gamma", context)?,
+            context: context.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for CpuColorVoodoo {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let input = self.input.pull(request.clone()).await?;
+        let frame = self
+            .context
+            .ensure_cpu_buffer::<Rgb>(&input)
+            .context("Wrong input format for CpuColorVoodoo")?;
+
+        let (pedestal, s_gamma, v_gamma) =
+            (self.pedestal.get() as f32, self.s_gamma.get() as f32, self.v_gamma.get() as f32);
+        let row_bytes = frame.interp.width as usize * 3;
+        let storage = self
+            .context
+            .map_rows_parallel(
+                request.priority(),
+                &frame.storage,
+                frame.interp.height as usize,
+                (row_bytes, row_bytes),
+                move |_, input, output| {
+                    for (input, output) in input.chunks_exact(3).zip(output.chunks_exact_mut(3)) {
+                        output.copy_from_slice(&color_voodoo(
+                            [input[0], input[1], input[2]],
+                            pedestal,
+                            s_gamma,
+                            v_gamma,
+                        ));
+                    }
+                },
+            )
+            .await;
+
+        Ok(Payload::from(Frame { interp: frame.interp, storage, metadata: frame.metadata.clone() }))
+    }
+
+    fn get_caps(&self) -> Caps { self.input.get_caps() }
+}
+
+fn mix(a: f32, b: f32, t: f32) -> f32 { a * (1.0 - t) + b * t }
+
+fn step(edge: f32, x: f32) -> f32 {
+    if x < edge {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+fn fract(x: f32) -> f32 { x - x.floor() }
+
+// these are straight ports of the functions in `color_voodoo.glsl`
+fn rgb2hsv([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let k = [0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0];
+    let t = step(b, g);
+    let p = [mix(b, g, t), mix(g, b, t), mix(k[3], k[0], t), mix(k[2], k[1], t)];
+    let t = step(p[0], r);
+    let q = [mix(p[0], r, t), mix(p[1], p[1], t), mix(p[3], p[2], t), mix(r, p[0], t)];
+
+    let d = q[0] - q[3].min(q[1]);
+    let e = 1.0e-10;
+    [(q[2] + (q[3] - q[1]) / (6.0 * d + e)).abs(), d / (q[0] + e), q[0]]
+}
+
+fn hsv2rgb([h, s, v]: [f32; 3]) -> [f32; 3] {
+    let k = [1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0];
+    let channel = |offset: f32| {
+        v * mix(k[0], ((fract(h + offset) * 6.0 - k[3]).abs() - k[0]).clamp(0.0, 1.0), s)
+    };
+    [channel(k[0]), channel(k[1]), channel(k[2])]
+}
+
+pub fn color_voodoo(rgb: [u8; 3], pedestal: f32, s_gamma: f32, v_gamma: f32) -> [u8; 3] {
+    let rgb = rgb.map(|v| (v as f32 - pedestal) / (256.0 - pedestal));
+    let [h, s, v] = rgb2hsv(rgb);
+    let hsv = [h, s.powf(s_gamma), v.powf(v_gamma)];
+    hsv2rgb(hsv).map(|v| (v * 256.0) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_voodoo() {
+        // with gammas of 1 and no pedestal the colors stay the same
+        assert_eq!(color_voodoo([128, 128, 128], 0.0, 1.0, 1.0), [128, 128, 128]);
+        assert_eq!(color_voodoo([200, 100, 100], 0.0, 1.0, 1.0), [200, 100, 100]);
+        assert_eq!(color_voodoo([255, 0, 0], 0.0, 1.0, 1.0), [255, 0, 0]);
+        // the pedestal is mapped to black
+        assert_eq!(color_voodoo([8, 8, 8], 8.0, 1.0, 1.0), [0, 0, 0]);
+        // v = 0.5 becomes 0.25
+        assert_eq!(color_voodoo([128, 128, 128], 0.0, 1.0, 2.0), [64, 64, 64]);
+        // s^0 = 1 fully saturates and keeps the value
+        assert_eq!(color_voodoo([200, 100, 100], 0.0, 0.0, 1.0), [200, 0, 0]);
+    }
+}
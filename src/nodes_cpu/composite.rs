@@ -0,0 +1,275 @@
+use crate::pipeline_processing::{
+    frame::{Frame, FrameInterpretation, Rgb, Rgba},
+    metadata::FrameMetadata,
+    node::{Caps, InputProcessingNode, NodeID, ProcessingNode, Request},
+    parametrizable::prelude::*,
+    payload::Payload,
+    processing_context::ProcessingContext,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use futures::future::try_join_all;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    /// the first input left of `position`, the second one right of it
+    WipeVertical,
+    /// the first input above `position`, the second one below it
+    WipeHorizontal,
+    SideBySide,
+    Grid,
+    /// the absolute difference of two inputs, multiplied by `gain`
+    Difference,
+    /// the second input scaled by `scale` in the lower right corner of the
+    /// first
+    PictureInPicture,
+}
+
+/// Combines the frames of several inputs into one frame to compare them.
+pub struct Composite {
+    inputs: Vec<InputProcessingNode>,
+    mode: Mode,
+    position: f64,
+    gain: f64,
+    scale: f64,
+    context: ProcessingContext,
+}
+
+impl Parameterizable for Composite {
+    const DESCRIPTION: Option<&'static str> = Some(
+        "combine rgb frames for comparisons, next to each other, split by a wipe, as their \
+         difference or as picture in picture",
+    );
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("inputs", Mandatory(ListParameter(Box::new(NodeInputParameter))))
+            .with(
+                "mode",
+                Optional(ChoiceParameter(vec![
+                    "side-by-side",
+                    "grid",
+                    "wipe-vertical",
+                    "wipe-horizontal",
+                    "difference",
+                    "picture-in-picture",
+                ])),
+            )
+            .with("position", WithDefault(FloatRange(0.0, 1.0), FloatRangeValue(0.5)))
+            .with("gain", WithDefault(FloatRange(0.0, 1000.0), FloatRangeValue(1.0)))
+            .with("scale", WithDefault(FloatRange(0.0, 1.0), FloatRangeValue(0.25)))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new()
+            .with_input("inputs", &[FrameType::Rgb, FrameType::Rgba])
+            .with_output(&[FrameType::Rgb])
+    }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> Result<Self> {
+        let mode = match &*parameters.take::<String>("mode")? {
+            "wipe-vertical" => Mode::WipeVertical,
+            "wipe-horizontal" => Mode::WipeHorizontal,
+            "side-by-side" => Mode::SideBySide,
+            "grid" => Mode::Grid,
+            "difference" => Mode::Difference,
+            "picture-in-picture" => Mode::PictureInPicture,
+            other => return Err(anyhow!("unknown mode {other}")),
+        };
+        let inputs = parameters.take_vec::<InputProcessingNode>("inputs")?;
+        match mode {
+            Mode::SideBySide | Mode::Grid if inputs.is_empty() => {
+                return Err(anyhow!("Composite needs at least one input"))
+            }
+            Mode::SideBySide | Mode::Grid => {}
+            _ if inputs.len() != 2 => {
+                return Err(anyhow!("Composite needs exactly two inputs for {mode:?}"))
+            }
+            _ => {}
+        }
+
+        Ok(Self {
+            inputs,
+            mode,
+            position: parameters.take("position")?,
+            gain: parameters.take("gain")?,
+            scale: parameters.take("scale")?,
+            context: context.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for Composite {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let payloads =
+            try_join_all(self.inputs.iter().map(|input| input.pull(request.clone()))).await?;
+        let frames = payloads
+            .iter()
+            .map(|payload| self.rgb_image(payload))
+            .collect::<Result<Vec<_>>>()
+            .context("Wrong input format for Composite")?;
+        let (images, mut infos): (Vec<_>, Vec<_>) = frames.into_iter().unzip();
+        // the fps and metadata are taken from the first input
+        let (fps, metadata) = infos.swap_remove(0);
+
+        let composite = self.composite(&images)?;
+        let interp = Rgb { width: composite.width, height: composite.height, fps };
+        let mut storage = unsafe { self.context.get_uninit_cpu_buffer(interp.required_bytes()) };
+        storage.as_mut_slice(|storage| storage.copy_from_slice(&composite.data));
+
+        Ok(Payload::from(Frame { interp, storage, metadata }))
+    }
+
+    fn get_caps(&self) -> Caps {
+        let caps: Vec<_> = self.inputs.iter().map(|input| input.get_caps()).collect();
+        Caps {
+            frame_count: caps.iter().filter_map(|caps| caps.frame_count).min(),
+            random_access: caps.iter().all(|caps| caps.random_access),
+        }
+    }
+}
+
+impl Composite {
+    /// Copies an `Rgb` or `Rgba` frame into an rgb image, the fps and metadata
+    /// of the frame are returned alongside
+    fn rgb_image(&self, payload: &Payload) -> Result<(RgbImage, (f64, FrameMetadata))> {
+        if let Ok(frame) = self.context.ensure_cpu_buffer::<Rgb>(payload) {
+            let data = frame.storage.as_slice(|data| data.to_vec());
+            let image = RgbImage { width: frame.interp.width, height: frame.interp.height, data };
+            Ok((image, (frame.interp.fps, frame.metadata.clone())))
+        } else {
+            let frame = self.context.ensure_cpu_buffer::<Rgba>(payload)?;
+            let data = frame.storage.as_slice(|data| {
+                data.chunks_exact(4).flat_map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect()
+            });
+            let image = RgbImage { width: frame.interp.width, height: frame.interp.height, data };
+            Ok((image, (frame.interp.fps, frame.metadata.clone())))
+        }
+    }
+
+    fn composite(&self, images: &[RgbImage]) -> Result<RgbImage> {
+        let first = &images[0];
+        if self.mode != Mode::PictureInPicture {
+            if let Some(other) = images.iter().find(|image| image.size() != first.size()) {
+                return Err(anyhow!(
+                    "all inputs of Composite need the same size for {:?}, but got {:?} and {:?}",
+                    self.mode,
+                    first.size(),
+                    other.size()
+                ));
+            }
+        }
+
+        Ok(match self.mode {
+            Mode::WipeVertical => {
+                let split = (first.width as f64 * self.position).round() as u64;
+                RgbImage::from_fn(first.width, first.height, |x, y| {
+                    images[if x < split { 0 } else { 1 }].pixel(x, y)
+                })
+            }
+            Mode::WipeHorizontal => {
+                let split = (first.height as f64 * self.position).round() as u64;
+                RgbImage::from_fn(first.width, first.height, |x, y| {
+                    images[if y < split { 0 } else { 1 }].pixel(x, y)
+                })
+            }
+            Mode::SideBySide => grid(images, images.len() as u64),
+            Mode::Grid => grid(images, (images.len() as f64).sqrt().ceil() as u64),
+            Mode::Difference => RgbImage::from_fn(first.width, first.height, |x, y| {
+                let (a, b) = (images[0].pixel(x, y), images[1].pixel(x, y));
+                [0, 1, 2].map(|c| {
+                    let difference = (a[c] as f64 - b[c] as f64).abs() * self.gain;
+                    difference.min(255.0) as u8
+                })
+            }),
+            Mode::PictureInPicture => {
+                let inset = &images[1];
+                let inset_width = ((first.width as f64 * self.scale) as u64).max(1);
+                let inset_height = ((inset_width as f64 * inset.height as f64 / inset.width as f64)
+                    as u64)
+                    .clamp(1, first.height);
+                let margin = first.width.min(first.height) / 32;
+                let left = first.width.saturating_sub(inset_width + margin);
+                let top = first.height.saturating_sub(inset_height + margin);
+                RgbImage::from_fn(first.width, first.height, |x, y| {
+                    if x >= left && x < left + inset_width && y >= top && y < top + inset_height {
+                        // nearest neighbour scaling is good enough for a preview
+                        inset.pixel(
+                            (x - left) * inset.width / inset_width,
+                            (y - top) * inset.height / inset_height,
+                        )
+                    } else {
+                        first.pixel(x, y)
+                    }
+                })
+            }
+        })
+    }
+}
+
+/// Arranges equally sized images in a grid with `columns` columns, empty cells
+/// stay black.
+fn grid(images: &[RgbImage], columns: u64) -> RgbImage {
+    let (width, height) = images[0].size();
+    let rows = (images.len() as u64 + columns - 1) / columns;
+    RgbImage::from_fn(width * columns, height * rows, |x, y| {
+        let index = (y / height * columns + x / width) as usize;
+        match images.get(index) {
+            Some(image) => image.pixel(x % width, y % height),
+            None => [0; 3],
+        }
+    })
+}
+
+struct RgbImage {
+    width: u64,
+    height: u64,
+    data: Vec<u8>,
+}
+
+impl RgbImage {
+    fn from_fn(width: u64, height: u64, pixel: impl Fn(u64, u64) -> [u8; 3]) -> Self {
+        let mut data = Vec::with_capacity((width * height * 3) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                data.extend_from_slice(&pixel(x, y));
+            }
+        }
+        Self { width, height, data }
+    }
+
+    fn size(&self) -> (u64, u64) { (self.width, self.height) }
+
+    fn pixel(&self, x: u64, y: u64) -> [u8; 3] {
+        let offset = ((y * self.width + x) * 3) as usize;
+        [self.data[offset], self.data[offset + 1], self.data[offset + 2]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: u64, height: u64, value: u8) -> RgbImage {
+        RgbImage::from_fn(width, height, |_, _| [value; 3])
+    }
+
+    #[test]
+    fn test_grid() {
+        let images = [image(2, 1, 1), image(2, 1, 2), image(2, 1, 3)];
+        let composite = grid(&images, 2);
+        assert_eq!(composite.size(), (4, 2));
+        assert_eq!(composite.pixel(1, 0), [1; 3]);
+        assert_eq!(composite.pixel(2, 0), [2; 3]);
+        assert_eq!(composite.pixel(0, 1), [3; 3]);
+        assert_eq!(composite.pixel(3, 1), [0; 3]);
+
+        let side_by_side = grid(&images, 3);
+        assert_eq!(side_by_side.size(), (6, 1));
+        assert_eq!(side_by_side.pixel(5, 0), [3; 3]);
+    }
+}
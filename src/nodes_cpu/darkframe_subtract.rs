@@ -0,0 +1,162 @@
+use crate::pipeline_processing::{
+    frame::{Frame, Raw},
+    node::{Caps, InputProcessingNode, NodeID, ProcessingNode, Request},
+    parametrizable::prelude::*,
+    payload::Payload,
+    processing_context::ProcessingContext,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+pub struct CpuDarkframeSubtract {
+    input: InputProcessingNode,
+    darkframe: Arc<Vec<f32>>,
+    darkframe_max: f32,
+    width: usize,
+    height: usize,
+    context: ProcessingContext,
+}
+
+impl Parameterizable for CpuDarkframeSubtract {
+    const DESCRIPTION: Option<&'static str> =
+        Some("cpu version of DarkframeSubtract (used in its place if no gpu is present)");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("input", Mandatory(NodeInputParameter))
+            .with("darkframe", Mandatory(PathParameter(PathCheck::Exists)))
+            .with("width", Mandatory(IntRange(0, i64::MAX)))
+            .with("height", Mandatory(IntRange(0, i64::MAX)))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).with_output(&[FrameType::Raw])
+    }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> Result<Self> {
+        let width = parameters.take::<i64>("width")? as usize;
+        let height = parameters.take::<i64>("height")? as usize;
+
+        let darkframe = std::fs::read(parameters.take::<String>("darkframe")?)?;
+        if darkframe.len() != width * height * 4 {
+            return Err(anyhow!(
+                "darkframe has {} bytes, but {width}x{height} f32 values need {} bytes",
+                darkframe.len(),
+                width * height * 4
+            ));
+        }
+        let darkframe: Vec<_> = darkframe
+            .chunks_exact(4)
+            .map(|v| f32::from_le_bytes([v[0], v[1], v[2], v[3]]))
+            .collect();
+        let darkframe_max = darkframe.iter().copied().fold(f32::MIN, f32::max);
+
+        Ok(Self {
+            input: parameters.take("input")?,
+            darkframe: Arc::new(darkframe),
+            darkframe_max,
+            width,
+            height,
+            context: context.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for CpuDarkframeSubtract {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let input = self.input.pull(request.clone()).await?;
+        let frame = self
+            .context
+            .ensure_cpu_buffer::<Raw>(&input)
+            .context("Wrong input format for CpuDarkframeSubtract")?;
+
+        if frame.interp.bit_depth != 12 {
+            return Err(anyhow!(
+                "A frame with bit_depth=12 is required. Convert the bit depth of the frame!"
+            ));
+        }
+        if (frame.interp.width as usize, frame.interp.height as usize) != (self.width, self.height)
+        {
+            return Err(anyhow!(
+                "the darkframe is {}x{}, but the frame is {}x{}",
+                self.width,
+                self.height,
+                frame.interp.width,
+                frame.interp.height
+            ));
+        }
+
+        let darkframe = self.darkframe.clone();
+        let width = self.width;
+        let row_bytes = width * 3 / 2;
+        let storage = self
+            .context
+            .map_rows_parallel(
+                request.priority(),
+                &frame.storage,
+                self.height,
+                (row_bytes, row_bytes),
+                move |first_row, input, output| {
+                    let darkframe = &darkframe[first_row * width..];
+                    subtract_darkframe(input, output, darkframe)
+                },
+            )
+            .await;
+
+        let interp = subtracted_interp(frame.interp, self.darkframe_max);
+        Ok(Payload::from(Frame { interp, storage, metadata: frame.metadata.clone() }))
+    }
+
+    fn get_caps(&self) -> Caps { self.input.get_caps() }
+}
+
+/// After the subtraction every pixel has a black level of 128 (the offset that
+/// is added) and the brightest value that doesn't clip in any pixel becomes
+/// the white level.
+pub fn subtracted_interp(interp: Raw, darkframe_max: f32) -> Raw {
+    let white_level =
+        (interp.white_level as f32 + 128.0 - darkframe_max).round().clamp(128.0, 4095.0) as u32;
+    Raw { black_level: [128; 4], white_level, ..interp }
+}
+
+/// The same as `darkframe_subtract.glsl`: the 12 bit values get an offset of
+/// 128 and then the darkframe value at the same position subtracted.
+pub fn subtract_darkframe(input: &[u8], output: &mut [u8], darkframe: &[f32]) {
+    for ((input, output), dark) in
+        input.chunks_exact(3).zip(output.chunks_exact_mut(3)).zip(darkframe.chunks_exact(2))
+    {
+        let (a, b, c) = (input[0] as u32, input[1] as u32, input[2] as u32);
+        let first_value = ((a << 4) | (b >> 4)) as f32 + 128.0;
+        let second_value = (((b << 8) & 0xf00) | c) as f32 + 128.0;
+
+        let corr_first = (first_value - dark[0]).round() as u32;
+        let corr_second = (second_value - dark[1]).round() as u32;
+
+        output[0] = (corr_first >> 4) as u8;
+        output[1] = (((corr_first << 4) & 0xf0) | (corr_second >> 8)) as u8;
+        output[2] = corr_second as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subtract_darkframe() {
+        // 0x123 and 0x456
+        let input = [0x12, 0x34, 0x56];
+        let mut output = [0; 3];
+
+        subtract_darkframe(&input, &mut output, &[128.0, 128.0]);
+        assert_eq!(output, input);
+
+        subtract_darkframe(&input, &mut output, &[128.4 + 0x23 as f32, 127.4]);
+        assert_eq!(output, [0x10, 0x04, 0x57]);
+    }
+}
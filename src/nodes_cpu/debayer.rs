@@ -0,0 +1,344 @@
+use crate::pipeline_processing::{
+    frame::{CfaColor, CfaDescriptor, Frame, FrameInterpretation, Raw, Rgb},
+    node::{Caps, InputProcessingNode, NodeID, ProcessingNode, Request},
+    parametrizable::prelude::*,
+    payload::Payload,
+    processing_context::ProcessingContext,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+
+enum DebayerMode {
+    Bilinear,
+    EdgeAware,
+}
+
+pub struct CpuDebayer {
+    input: InputProcessingNode,
+    mode: DebayerMode,
+    context: ProcessingContext,
+}
+
+impl Parameterizable for CpuDebayer {
+    const DESCRIPTION: Option<&'static str> =
+        Some("debayer 8 bit raw frames on the cpu (used in place of Debayer if no gpu is present)");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("input", Mandatory(NodeInputParameter))
+            .with("mode", Optional(ChoiceParameter(vec!["bilinear", "edge-aware"])))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).with_output(&[FrameType::Rgb])
+    }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> Result<Self> {
+        let mode = match &*parameters.take::<String>("mode")? {
+            "bilinear" => Ok(DebayerMode::Bilinear),
+            "edge-aware" => Ok(DebayerMode::EdgeAware),
+            other => Err(anyhow!("unknown debayer mode {other}")),
+        }?;
+
+        Ok(Self { input: parameters.take("input")?, mode, context: context.clone() })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for CpuDebayer {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let input = self.input.pull(request).await?;
+        let frame = self
+            .context
+            .ensure_cpu_buffer::<Raw>(&input)
+            .context("Wrong input format for CpuDebayer")?;
+
+        if frame.interp.bit_depth != 8 {
+            return Err(anyhow!(
+                "A frame with bit_depth=8 is required. Convert the bit depth of the frame!"
+            ));
+        }
+
+        let interp =
+            Rgb { width: frame.interp.width, height: frame.interp.height, fps: frame.interp.fps };
+        let mut new_buffer = unsafe { self.context.get_uninit_cpu_buffer(interp.required_bytes()) };
+
+        new_buffer.as_mut_slice(|new_buffer| {
+            frame.storage.as_slice(|frame_storage| {
+                let (width, height) = (interp.width as usize, interp.height as usize);
+                match self.mode {
+                    DebayerMode::Bilinear => {
+                        debayer_bilinear(frame_storage, new_buffer, width, height, frame.interp.cfa)
+                    }
+                    DebayerMode::EdgeAware => debayer_edge_aware(
+                        frame_storage,
+                        new_buffer,
+                        width,
+                        height,
+                        frame.interp.cfa,
+                    ),
+                }
+            })
+        });
+
+        Ok(Payload::from(Frame { interp, storage: new_buffer, metadata: frame.metadata.clone() }))
+    }
+
+    fn get_caps(&self) -> Caps { self.input.get_caps() }
+}
+
+pub struct CpuDebayerResolutionLoss {
+    input: InputProcessingNode,
+    context: ProcessingContext,
+}
+
+impl Parameterizable for CpuDebayerResolutionLoss {
+    const DESCRIPTION: Option<&'static str> = Some(
+        "debayer 8 bit raw frames to half the resolution on the cpu (used in place of \
+         DebayerResolutionLoss if no gpu is present)",
+    );
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new().with("input", Mandatory(NodeInputParameter))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).with_output(&[FrameType::Rgb])
+    }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> Result<Self> {
+        Ok(Self { input: parameters.take("input")?, context: context.clone() })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for CpuDebayerResolutionLoss {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let input = self.input.pull(request).await?;
+        let frame = self
+            .context
+            .ensure_cpu_buffer::<Raw>(&input)
+            .context("Wrong input format for CpuDebayerResolutionLoss")?;
+
+        if frame.interp.bit_depth != 8 {
+            return Err(anyhow!(
+                "A frame with bit_depth=8 is required. Convert the bit depth of the frame!"
+            ));
+        }
+
+        let interp = Rgb {
+            width: frame.interp.width / 2,
+            height: frame.interp.height / 2,
+            fps: frame.interp.fps,
+        };
+        let mut new_buffer = unsafe { self.context.get_uninit_cpu_buffer(interp.required_bytes()) };
+
+        new_buffer.as_mut_slice(|new_buffer| {
+            frame.storage.as_slice(|frame_storage| {
+                debayer_resolution_loss(
+                    frame_storage,
+                    new_buffer,
+                    frame.interp.width as usize,
+                    frame.interp.height as usize,
+                    frame.interp.cfa,
+                )
+            })
+        });
+
+        Ok(Payload::from(Frame { interp, storage: new_buffer, metadata: frame.metadata.clone() }))
+    }
+
+    fn get_caps(&self) -> Caps { self.input.get_caps() }
+}
+
+// mirrors the coordinate at the border, so that the neighbours keep their cfa
+// color
+fn reflect(v: isize, len: usize) -> usize {
+    let max = len as isize - 1;
+    let v = v.abs();
+    let v = if v > max { 2 * max - v } else { v };
+    v.clamp(0, max) as usize
+}
+
+/// The same algorithm as `debayer.glsl`, with the borders mirrored instead of
+/// reading outside of the frame.
+pub fn debayer_bilinear(
+    src: &[u8],
+    dst: &mut [u8],
+    width: usize,
+    height: usize,
+    cfa: CfaDescriptor,
+) {
+    let px = |x: isize, y: isize| src[reflect(y, height) * width + reflect(x, width)] as f32;
+
+    for y in 0..height {
+        for x in 0..width {
+            let (xi, yi) = (x as isize, y as isize);
+            /*
+            variables a-i are the neighbour pixels (we are e)
+            a b c
+            d e f
+            g h i
+            */
+            let (a, b, c) = (px(xi - 1, yi - 1), px(xi, yi - 1), px(xi + 1, yi - 1));
+            let (d, e, f) = (px(xi - 1, yi), px(xi, yi), px(xi + 1, yi));
+            let (g, h, i) = (px(xi - 1, yi + 1), px(xi, yi + 1), px(xi + 1, yi + 1));
+
+            let rgb = match cfa.color_at(x, y) {
+                CfaColor::Red => [e, (f + d + h + b) / 4., (i + a + g + c) / 4.],
+                CfaColor::Blue => [(i + a + g + c) / 4., (f + d + h + b) / 4., e],
+                CfaColor::GreenInRedRow => [(d + f) / 2., e, (b + h) / 2.],
+                CfaColor::GreenInBlueRow => [(b + h) / 2., e, (d + f) / 2.],
+            };
+
+            let idx = (y * width + x) * 3;
+            for (dst, value) in dst[idx..idx + 3].iter_mut().zip(rgb) {
+                *dst = value as u8;
+            }
+        }
+    }
+}
+
+/// Gradient directed green interpolation (Hamilton-Adams) followed by
+/// interpolating the red and blue color differences to the green channel.
+/// Produces far less zipper artifacts along edges than the bilinear variant.
+pub fn debayer_edge_aware(
+    src: &[u8],
+    dst: &mut [u8],
+    width: usize,
+    height: usize,
+    cfa: CfaDescriptor,
+) {
+    let px = |x: isize, y: isize| src[reflect(y, height) * width + reflect(x, width)] as f32;
+
+    let mut green = vec![0f32; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let (xi, yi) = (x as isize, y as isize);
+            let e = px(xi, yi);
+            green[y * width + x] = match cfa.color_at(x, y) {
+                CfaColor::GreenInRedRow | CfaColor::GreenInBlueRow => e,
+                CfaColor::Red | CfaColor::Blue => {
+                    let laplace_h = 2. * e - px(xi - 2, yi) - px(xi + 2, yi);
+                    let laplace_v = 2. * e - px(xi, yi - 2) - px(xi, yi + 2);
+                    let grad_h = (px(xi - 1, yi) - px(xi + 1, yi)).abs() + laplace_h.abs();
+                    let grad_v = (px(xi, yi - 1) - px(xi, yi + 1)).abs() + laplace_v.abs();
+                    let green_h = (px(xi - 1, yi) + px(xi + 1, yi)) / 2. + laplace_h / 4.;
+                    let green_v = (px(xi, yi - 1) + px(xi, yi + 1)) / 2. + laplace_v / 4.;
+
+                    if grad_h < grad_v {
+                        green_h
+                    } else if grad_v < grad_h {
+                        green_v
+                    } else {
+                        (green_h + green_v) / 2.
+                    }
+                }
+            }
+            .clamp(0., 255.);
+        }
+    }
+
+    let g = |x: isize, y: isize| green[reflect(y, height) * width + reflect(x, width)];
+    let diff = |x: isize, y: isize| px(x, y) - g(x, y);
+
+    for y in 0..height {
+        for x in 0..width {
+            let (xi, yi) = (x as isize, y as isize);
+            let e = px(xi, yi);
+            let green = g(xi, yi);
+            let diff_diagonal = (diff(xi - 1, yi - 1)
+                + diff(xi + 1, yi - 1)
+                + diff(xi - 1, yi + 1)
+                + diff(xi + 1, yi + 1))
+                / 4.;
+            let diff_horizontal = (diff(xi - 1, yi) + diff(xi + 1, yi)) / 2.;
+            let diff_vertical = (diff(xi, yi - 1) + diff(xi, yi + 1)) / 2.;
+
+            let rgb = match cfa.color_at(x, y) {
+                CfaColor::Red => [e, green, green + diff_diagonal],
+                CfaColor::Blue => [green + diff_diagonal, green, e],
+                CfaColor::GreenInRedRow => [green + diff_horizontal, e, green + diff_vertical],
+                CfaColor::GreenInBlueRow => [green + diff_vertical, e, green + diff_horizontal],
+            };
+
+            let idx = (y * width + x) * 3;
+            for (dst, value) in dst[idx..idx + 3].iter_mut().zip(rgb) {
+                *dst = value.round().clamp(0., 255.) as u8;
+            }
+        }
+    }
+}
+
+/// The same algorithm as `debayer_loss.glsl`: every 2x2 cfa block becomes one
+/// output pixel. `width` and `height` are the dimensions of the raw frame.
+pub fn debayer_resolution_loss(
+    src: &[u8],
+    dst: &mut [u8],
+    width: usize,
+    height: usize,
+    cfa: CfaDescriptor,
+) {
+    let red_x = (!cfa.red_in_first_col) as usize;
+    let red_y = (!cfa.red_in_first_row) as usize;
+    let at = |x: usize, y: usize| src[y * width + x];
+
+    for y in 0..height / 2 {
+        for x in 0..width / 2 {
+            let (base_x, base_y) = (x * 2, y * 2);
+            let idx = (y * (width / 2) + x) * 3;
+            dst[idx] = at(base_x + red_x, base_y + red_y);
+            dst[idx + 1] = at(base_x + (1 - red_x), base_y + red_y) / 2
+                + at(base_x + red_x, base_y + (1 - red_y)) / 2;
+            dst[idx + 2] = at(base_x + (1 - red_x), base_y + (1 - red_y));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat_bayer(width: usize, height: usize, cfa: CfaDescriptor) -> Vec<u8> {
+        let mut data = vec![0; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                data[y * width + x] = match cfa.color_at(x, y) {
+                    CfaColor::Red => 200,
+                    CfaColor::GreenInRedRow | CfaColor::GreenInBlueRow => 100,
+                    CfaColor::Blue => 50,
+                };
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_flat_color_is_preserved() {
+        let (width, height) = (8, 6);
+        for (red_in_first_col, red_in_first_row) in
+            [(true, true), (true, false), (false, true), (false, false)]
+        {
+            let cfa = CfaDescriptor::from_first_red(red_in_first_col, red_in_first_row);
+            let src = flat_bayer(width, height, cfa);
+
+            let mut dst = vec![0; width * height * 3];
+            debayer_bilinear(&src, &mut dst, width, height, cfa);
+            assert!(dst.chunks_exact(3).all(|px| px == [200, 100, 50]));
+
+            let mut dst = vec![0; width * height * 3];
+            debayer_edge_aware(&src, &mut dst, width, height, cfa);
+            assert!(dst.chunks_exact(3).all(|px| px == [200, 100, 50]));
+
+            let mut dst = vec![0; width * height * 3 / 4];
+            debayer_resolution_loss(&src, &mut dst, width, height, cfa);
+            assert!(dst.chunks_exact(3).all(|px| px == [200, 100, 50]));
+        }
+    }
+}
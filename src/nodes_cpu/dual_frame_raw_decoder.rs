@@ -34,6 +34,9 @@ impl Parameterizable for DualFrameRawDecoder {
             .with("red-in-first-col", WithDefault(BoolParameter, BoolValue(true)))
             .with("red-in-first-row", WithDefault(BoolParameter, BoolValue(false)))
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Rgb]).with_output(&[FrameType::Raw])
+    }
 
     fn from_parameters(
         mut parameters: Parameters,
@@ -163,6 +166,8 @@ impl ProcessingNode for DualFrameRawDecoder {
             bit_depth: 12,
             cfa: self.cfa_descriptor,
             fps: frame_a.interp.fps / 2.0,
+            black_level: frame_a.interp.black_level,
+            white_level: frame_a.interp.white_level,
         };
 
         let mut new_buffer = unsafe { self.context.get_uninit_cpu_buffer(interp.required_bytes()) };
@@ -190,7 +195,7 @@ impl ProcessingNode for DualFrameRawDecoder {
             })
         });
 
-        Ok(Payload::from(Frame { interp, storage: new_buffer }))
+        Ok(Payload::from(Frame { interp, storage: new_buffer, metadata: frame_a.metadata.clone() }))
     }
 
     fn get_caps(&self) -> Caps {
@@ -219,6 +224,9 @@ impl Parameterizable for ReverseDualFrameRawDecoder {
                 ),
             )
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).with_output(&[FrameType::Rgb])
+    }
 
     fn from_parameters(
         mut parameters: Parameters,
@@ -267,6 +275,7 @@ impl ProcessingNode for ReverseDualFrameRawDecoder {
                 fps: frame.interp.fps * 2.0,
             },
             storage: out_buffer,
+            metadata: frame.metadata.clone(),
         }))
     }
 
@@ -23,6 +23,9 @@ impl Parameterizable for Fp32ToUInt16 {
             .with("input", Mandatory(NodeInputParameter))
             .with("multiplier", WithDefault(PositiveReal(), FloatRangeValue(1.0)))
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).with_output(&[FrameType::Raw])
+    }
 
     fn from_parameters(
         mut parameters: Parameters,
@@ -45,7 +48,12 @@ impl ProcessingNode for Fp32ToUInt16 {
             .context
             .ensure_cpu_buffer::<Raw>(&input)
             .context("Wrong input format for FPotUint16")?;
-        let interp = Raw { bit_depth: 16, ..frame.interp };
+        let interp = Raw {
+            bit_depth: 16,
+            black_level: frame.interp.black_level.map(|v| (v as f64 * self.multiplier) as u32),
+            white_level: (frame.interp.white_level as f64 * self.multiplier) as u32,
+            ..frame.interp
+        };
 
         let mut new_buffer =
             unsafe { self.context.get_uninit_cpu_buffer(interp.required_bytes()) };
@@ -61,7 +69,7 @@ impl ProcessingNode for Fp32ToUInt16 {
             })
         });
         
-        let new_frame = Frame { storage: new_buffer, interp };
+        let new_frame = Frame { storage: new_buffer, interp, metadata: frame.metadata.clone() };
 
         Ok(Payload::from(new_frame))
     }
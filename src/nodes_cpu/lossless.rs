@@ -0,0 +1,662 @@
+//! A lossless codec for raw bayer frames.
+//!
+//! The frame is split into its four cfa planes, so that neighbouring samples of
+//! a plane have the same color. Every sample is predicted from its left, upper
+//! and upper left neighbour with the median edge detector of LOCO-I / JPEG-LS
+//! and the residuals are coded with adaptive Golomb-Rice codes. To use all
+//! threads, every plane is split into stripes of rows, which are coded
+//! independently of each other and in parallel.
+//!
+//! The compressed data starts with the number of stripes per plane and the
+//! length of every coded stripe as `u32` little endian, followed by the coded
+//! stripes, plane by plane in row-major cfa order.
+
+use crate::pipeline_processing::{
+    frame::{Frame, FrameInterpretation, LosslessCompressed, Raw},
+    node::{Caps, InputProcessingNode, NodeID, ProcessingNode, Request},
+    parametrizable::prelude::*,
+    payload::Payload,
+    processing_context::ProcessingContext,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use std::{ops::Range, sync::Arc};
+
+/// residuals with a longer unary prefix are written verbatim
+const ESCAPE_LIMIT: u32 = 24;
+/// number of samples after which the statistics of the rice parameter
+/// estimation are halved
+const RESET_INTERVAL: u32 = 64;
+
+pub struct LosslessCompress {
+    input: InputProcessingNode,
+    context: ProcessingContext,
+}
+impl Parameterizable for LosslessCompress {
+    const DESCRIPTION: Option<&'static str> =
+        Some("losslessly compress raw frames with a predictor and rice coding per cfa plane");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new().with("input", Mandatory(NodeInputParameter))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new()
+            .with_input("input", &[FrameType::Raw])
+            .with_output(&[FrameType::LosslessCompressed])
+    }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> Result<Self> {
+        Ok(Self { input: parameters.take("input")?, context: context.clone() })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for LosslessCompress {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let input = self.input.pull(request.clone()).await?;
+        let frame = self
+            .context
+            .ensure_cpu_buffer::<Raw>(&input)
+            .context("Wrong input format for LosslessCompress")?;
+        let interp = frame.interp;
+
+        let planes = Arc::new(frame.storage.as_slice(|data| split_planes(data, &interp))?);
+        let plane_width = interp.width as usize / 2;
+        let stripes = stripes_per_plane(self.context.num_threads(), interp.height as usize / 2);
+        let ranges = stripe_ranges(plane_width, interp.height as usize / 2, stripes);
+        let coded_stripes = futures::future::join_all((0..4 * stripes).map(|i| {
+            let planes = planes.clone();
+            let range = ranges[i % stripes].clone();
+            self.context.spawn(request.priority(), async move {
+                encode_plane(&planes[i / stripes][range], plane_width, interp.bit_depth as u32)
+            })
+        }))
+        .await;
+
+        let compressed = join_coded_stripes(&coded_stripes);
+        let storage = unsafe {
+            let mut buffer = self.context.get_uninit_cpu_buffer(compressed.len());
+            buffer.as_mut_slice(|buffer| buffer.copy_from_slice(&compressed));
+            buffer
+        };
+
+        Ok(Payload::from(Frame {
+            interp: LosslessCompressed { inner: interp, compressed_size: compressed.len() },
+            storage,
+            metadata: frame.metadata.clone(),
+        }))
+    }
+
+    fn get_caps(&self) -> Caps { self.input.get_caps() }
+}
+
+pub struct LosslessDecompress {
+    input: InputProcessingNode,
+    context: ProcessingContext,
+}
+impl Parameterizable for LosslessDecompress {
+    const DESCRIPTION: Option<&'static str> =
+        Some("decompress raw frames compressed by LosslessCompress");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new().with("input", Mandatory(NodeInputParameter))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new()
+            .with_input("input", &[FrameType::LosslessCompressed])
+            .with_output(&[FrameType::Raw])
+    }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> Result<Self> {
+        Ok(Self { input: parameters.take("input")?, context: context.clone() })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for LosslessDecompress {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let input = self.input.pull(request.clone()).await?;
+        let frame = self
+            .context
+            .ensure_cpu_buffer::<LosslessCompressed>(&input)
+            .context("Wrong input format for LosslessDecompress")?;
+        let interp = frame.interp.inner;
+
+        let coded_stripes = Arc::new(frame.storage.as_slice(split_coded_stripes)?);
+        let plane_width = interp.width as usize / 2;
+        let stripes = coded_stripes.len() / 4;
+        let ranges = stripe_ranges(plane_width, interp.height as usize / 2, stripes);
+        let stripes_data = futures::future::join_all((0..4 * stripes).map(|i| {
+            let coded_stripes = coded_stripes.clone();
+            let len = ranges[i % stripes].len();
+            self.context.spawn(request.priority(), async move {
+                decode_plane(&coded_stripes[i], len, plane_width, interp.bit_depth as u32)
+            })
+        }))
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?;
+        let planes: Vec<_> = stripes_data.chunks(stripes).map(|plane| plane.concat()).collect();
+
+        let mut storage = unsafe { self.context.get_uninit_cpu_buffer(interp.required_bytes()) };
+        storage.as_mut_slice(|data| merge_planes(&planes, data, &interp))?;
+
+        Ok(Payload::from(Frame { interp, storage, metadata: frame.metadata.clone() }))
+    }
+
+    fn get_caps(&self) -> Caps { self.input.get_caps() }
+}
+
+fn check_interp(interp: &Raw) -> Result<()> {
+    if interp.width % 2 != 0 || interp.height % 2 != 0 {
+        return Err(anyhow!(
+            "lossless compression needs an even width and height, but the frame is {}x{}",
+            interp.width,
+            interp.height
+        ));
+    }
+    if ![8, 12, 16].contains(&interp.bit_depth) {
+        return Err(anyhow!(
+            "lossless compression supports bit depths of 8, 12 and 16, but the frame has {}",
+            interp.bit_depth
+        ));
+    }
+    Ok(())
+}
+
+fn sample_at(data: &[u8], bit_depth: u64, index: usize) -> u16 {
+    match bit_depth {
+        8 => data[index] as u16,
+        12 => {
+            let bytes = &data[index / 2 * 3..index / 2 * 3 + 3];
+            if index % 2 == 0 {
+                ((bytes[0] as u16) << 4) | (bytes[1] >> 4) as u16
+            } else {
+                (((bytes[1] & 0xf) as u16) << 8) | bytes[2] as u16
+            }
+        }
+        _ => u16::from_ne_bytes([data[index * 2], data[index * 2 + 1]]),
+    }
+}
+
+/// Unpacks the frame into its four cfa planes (in row-major order of the 2x2
+/// cfa pattern).
+pub fn split_planes(data: &[u8], interp: &Raw) -> Result<[Vec<u16>; 4]> {
+    check_interp(interp)?;
+    let (width, height) = (interp.width as usize, interp.height as usize);
+    let mut planes: [Vec<u16>; 4] = Default::default();
+    for plane in &mut planes {
+        plane.reserve(width * height / 4);
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            planes[(y % 2) * 2 + x % 2].push(sample_at(data, interp.bit_depth, y * width + x));
+        }
+    }
+    Ok(planes)
+}
+
+/// The inverse of `split_planes`.
+pub fn merge_planes(planes: &[Vec<u16>], data: &mut [u8], interp: &Raw) -> Result<()> {
+    check_interp(interp)?;
+    let (width, height) = (interp.width as usize, interp.height as usize);
+    let sample = |x: usize, y: usize| planes[(y % 2) * 2 + x % 2][(y / 2) * (width / 2) + x / 2];
+
+    match interp.bit_depth {
+        8 => {
+            for y in 0..height {
+                for x in 0..width {
+                    data[y * width + x] = sample(x, y) as u8;
+                }
+            }
+        }
+        12 => {
+            for y in 0..height {
+                for x in (0..width).step_by(2) {
+                    let (a, b) = (sample(x, y), sample(x + 1, y));
+                    let bytes = &mut data[(y * width + x) / 2 * 3..][..3];
+                    bytes[0] = (a >> 4) as u8;
+                    bytes[1] = (((a << 4) & 0xf0) | (b >> 8)) as u8;
+                    bytes[2] = b as u8;
+                }
+            }
+        }
+        _ => {
+            for y in 0..height {
+                for x in 0..width {
+                    let index = (y * width + x) * 2;
+                    data[index..index + 2].copy_from_slice(&sample(x, y).to_ne_bytes());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Uses enough stripes to give every thread work, but at most one per row.
+fn stripes_per_plane(num_threads: usize, plane_height: usize) -> usize {
+    ((num_threads + 3) / 4).clamp(1, plane_height.max(1))
+}
+
+/// The samples of the plane that belong to each of its stripes. The last
+/// stripes might be shorter or even empty.
+fn stripe_ranges(plane_width: usize, plane_height: usize, stripes: usize) -> Vec<Range<usize>> {
+    let rows = (plane_height + stripes - 1) / stripes;
+    (0..stripes)
+        .map(|stripe| {
+            let first_row = (stripe * rows).min(plane_height);
+            let last_row = ((stripe + 1) * rows).min(plane_height);
+            first_row * plane_width..last_row * plane_width
+        })
+        .collect()
+}
+
+/// The median edge detector of LOCO-I
+fn predict(left: i32, up: i32, up_left: i32) -> i32 {
+    if up_left >= left.max(up) {
+        left.min(up)
+    } else if up_left <= left.min(up) {
+        left.max(up)
+    } else {
+        left + up - up_left
+    }
+}
+
+fn predict_at(plane: &[u16], width: usize, index: usize, bit_depth: u32) -> i32 {
+    let (x, y) = (index % width, index / width);
+    match (x, y) {
+        (0, 0) => 1 << (bit_depth - 1),
+        (_, 0) => plane[index - 1] as i32,
+        (0, _) => plane[index - width] as i32,
+        _ => predict(
+            plane[index - 1] as i32,
+            plane[index - width] as i32,
+            plane[index - width - 1] as i32,
+        ),
+    }
+}
+
+/// Estimates the rice parameter from the running sum of the mapped residuals
+/// like JPEG-LS does.
+struct RiceParameter {
+    sum: u32,
+    count: u32,
+}
+impl RiceParameter {
+    fn new() -> Self { Self { sum: 4, count: 1 } }
+    fn k(&self) -> u32 {
+        let mut k = 0;
+        while (self.count << k) < self.sum {
+            k += 1;
+        }
+        k
+    }
+    fn update(&mut self, mapped: u32) {
+        self.sum += mapped;
+        self.count += 1;
+        if self.count == RESET_INTERVAL {
+            self.sum >>= 1;
+            self.count >>= 1;
+        }
+    }
+}
+
+pub fn encode_plane(plane: &[u16], width: usize, bit_depth: u32) -> Vec<u8> {
+    let mut writer = BitWriter::with_capacity(plane.len() * bit_depth as usize / 8);
+    let mut rice = RiceParameter::new();
+    for (index, &sample) in plane.iter().enumerate() {
+        let residual = sample as i32 - predict_at(plane, width, index, bit_depth);
+        let mapped = ((residual << 1) ^ (residual >> 31)) as u32;
+
+        let k = rice.k();
+        let quotient = mapped >> k;
+        if quotient < ESCAPE_LIMIT {
+            writer.write(1, quotient + 1);
+            writer.write(mapped & ((1 << k) - 1), k);
+        } else {
+            writer.write(1, ESCAPE_LIMIT + 1);
+            writer.write(mapped, bit_depth + 1);
+        }
+        rice.update(mapped);
+    }
+    writer.finish()
+}
+
+pub fn decode_plane(data: &[u8], len: usize, width: usize, bit_depth: u32) -> Result<Vec<u16>> {
+    let mut reader = BitReader::new(data);
+    let mut rice = RiceParameter::new();
+    let mut plane = Vec::with_capacity(len);
+    for index in 0..len {
+        let k = rice.k();
+        let quotient = reader.read_unary(ESCAPE_LIMIT);
+        let mapped = if quotient < ESCAPE_LIMIT {
+            (quotient << k) | reader.read(k)
+        } else {
+            reader.read(bit_depth + 1)
+        };
+        let residual = (mapped >> 1) as i32 ^ -((mapped & 1) as i32);
+
+        let sample = predict_at(&plane, width, index, bit_depth) + residual;
+        if !(0..1 << bit_depth).contains(&sample) {
+            return Err(anyhow!("corrupted lossless data: sample {sample} out of range"));
+        }
+        plane.push(sample as u16);
+        rice.update(mapped);
+    }
+    if reader.overrun() {
+        return Err(anyhow!("corrupted lossless data: plane is shorter than expected"));
+    }
+    Ok(plane)
+}
+
+/// Joins the coded stripes of all four planes (all stripes of the first plane
+/// first).
+fn join_coded_stripes(coded_stripes: &[Vec<u8>]) -> Vec<u8> {
+    let header_len = 4 + 4 * coded_stripes.len();
+    let mut data =
+        Vec::with_capacity(header_len + coded_stripes.iter().map(Vec::len).sum::<usize>());
+    data.extend_from_slice(&(coded_stripes.len() as u32 / 4).to_le_bytes());
+    for stripe in coded_stripes {
+        data.extend_from_slice(&(stripe.len() as u32).to_le_bytes());
+    }
+    for stripe in coded_stripes {
+        data.extend_from_slice(stripe);
+    }
+    data
+}
+
+fn split_coded_stripes(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let stripes = data
+        .get(..4)
+        .map(|stripes| u32::from_le_bytes(stripes.try_into().unwrap()) as usize)
+        .filter(|stripes| *stripes > 0)
+        .ok_or_else(|| anyhow!("corrupted lossless data: missing number of stripes"))?;
+    let mut offset = 4 + 4 * 4 * stripes;
+    let lengths = data
+        .get(4..offset)
+        .ok_or_else(|| anyhow!("corrupted lossless data: missing stripe lengths"))?;
+    lengths
+        .chunks_exact(4)
+        .map(|len| {
+            let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+            let stripe = data
+                .get(offset..offset + len)
+                .ok_or_else(|| anyhow!("corrupted lossless data: stripe exceeds the frame"))?;
+            offset += len;
+            Ok(stripe.to_vec())
+        })
+        .collect()
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    accumulator: u64,
+    bits: u32,
+}
+impl BitWriter {
+    fn with_capacity(capacity: usize) -> Self {
+        Self { bytes: Vec::with_capacity(capacity), accumulator: 0, bits: 0 }
+    }
+    /// writes the lowest `n` bits of `value`, most significant bit first
+    fn write(&mut self, value: u32, n: u32) {
+        self.accumulator = (self.accumulator << n) | value as u64;
+        self.bits += n;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.bytes.push((self.accumulator >> self.bits) as u8);
+        }
+    }
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push((self.accumulator << (8 - self.bits)) as u8);
+        }
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    // the next bits to read, aligned to the most significant bit
+    accumulator: u64,
+    bits: u32,
+}
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self { Self { data, position: 0, accumulator: 0, bits: 0 } }
+    fn refill(&mut self) {
+        while self.bits <= 56 {
+            let byte = self.data.get(self.position).copied().unwrap_or(0);
+            self.accumulator |= (byte as u64) << (56 - self.bits);
+            self.position += 1;
+            self.bits += 8;
+        }
+    }
+    fn read(&mut self, n: u32) -> u32 {
+        if n == 0 {
+            return 0;
+        }
+        self.refill();
+        let value = (self.accumulator >> (64 - n)) as u32;
+        self.accumulator <<= n;
+        self.bits -= n;
+        value
+    }
+    /// reads the number of zeros before the next one, but at most `limit`
+    fn read_unary(&mut self, limit: u32) -> u32 {
+        self.refill();
+        let zeros = self.accumulator.leading_zeros().min(limit);
+        self.accumulator <<= zeros + 1;
+        self.bits -= zeros + 1;
+        zeros
+    }
+    /// whether more bits were read than there are in the data
+    fn overrun(&self) -> bool { self.position * 8 - self.bits as usize > self.data.len() * 8 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        nodes_io::axiom_container::{read_frame, read_index, ContainerInterpretation},
+        pipeline_processing::{frame::CfaDescriptor, test_util::run_graph},
+    };
+    use std::{fs, io::BufReader, path::Path, time::Instant};
+
+    #[test]
+    fn test_lossless_roundtrip() {
+        for bit_depth in [8, 12, 16] {
+            let interp = Raw {
+                width: 64,
+                height: 32,
+                bit_depth,
+                cfa: CfaDescriptor::from_first_red(true, true),
+                fps: 24.0,
+                black_level: [0; 4],
+                white_level: Raw::default_white_level(bit_depth),
+            };
+            let max = (1u32 << bit_depth) - 1;
+            let samples: Vec<u16> = (0..64 * 32)
+                .map(|i: u32| {
+                    let (x, y) = (i % 64, i / 64);
+                    // a smooth gradient with some noise, a cfa pattern and a few extreme values
+                    let value = x * 7 + y * 3 + (i.wrapping_mul(2654435761) >> 28) + (x % 2) * 40;
+                    if i % 97 == 0 {
+                        max as u16
+                    } else {
+                        (value * max / 800).min(max) as u16
+                    }
+                })
+                .collect();
+
+            let mut data = vec![0u8; interp.required_bytes()];
+            let planes = [0, 1, 2, 3].map(|plane| {
+                samples
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| (i / 64 % 2) * 2 + i % 2 == plane)
+                    .map(|(_, v)| *v)
+                    .collect::<Vec<_>>()
+            });
+            merge_planes(&planes, &mut data, &interp).unwrap();
+            assert_eq!(split_planes(&data, &interp).unwrap(), planes);
+
+            let coded_planes = planes
+                .iter()
+                .map(|plane| encode_plane(plane, 32, bit_depth as u32))
+                .collect::<Vec<_>>();
+            let compressed = join_coded_stripes(&coded_planes);
+            assert!(compressed.len() < data.len(), "no compression at bit depth {bit_depth}");
+
+            let decoded = split_coded_stripes(&compressed)
+                .unwrap()
+                .iter()
+                .map(|plane| decode_plane(plane, 32 * 16, 32, bit_depth as u32).unwrap())
+                .collect::<Vec<_>>();
+            let mut decompressed = vec![0u8; interp.required_bytes()];
+            merge_planes(&decoded, &mut decompressed, &interp).unwrap();
+            assert_eq!(decompressed, data);
+        }
+    }
+
+    #[test]
+    fn test_stripes() {
+        assert_eq!(stripes_per_plane(1, 100), 1);
+        assert_eq!(stripes_per_plane(16, 100), 4);
+        assert_eq!(stripes_per_plane(64, 3), 3);
+        assert_eq!(stripe_ranges(10, 10, 4), vec![0..30, 30..60, 60..90, 90..100]);
+        assert_eq!(stripe_ranges(2, 5, 4), vec![0..4, 4..8, 8..10, 10..10]);
+
+        let plane: Vec<u16> = (0..10 * 16).map(|i: u32| (i * 37 % 4096) as u16).collect();
+        let ranges = stripe_ranges(10, 16, 3);
+        let coded_stripes: Vec<_> = (0..4)
+            .flat_map(|_| ranges.iter().map(|range| encode_plane(&plane[range.clone()], 10, 12)))
+            .collect();
+        let split = split_coded_stripes(&join_coded_stripes(&coded_stripes)).unwrap();
+        assert_eq!(split, coded_stripes);
+
+        let decoded: Vec<u16> = split[..3]
+            .iter()
+            .zip(&ranges)
+            .flat_map(|(stripe, range)| decode_plane(stripe, range.len(), 10, 12).unwrap())
+            .collect();
+        assert_eq!(decoded, plane);
+    }
+
+    /// Compares the codec to zstd (with the default level of `ZstdWriter`) on a
+    /// noisy 12 bit frame. Run with `--release -- --nocapture` to see the
+    /// ratios and speeds.
+    #[test]
+    fn test_compared_to_zstd() {
+        let interp = Raw {
+            width: 512,
+            height: 256,
+            bit_depth: 12,
+            cfa: CfaDescriptor::from_first_red(true, true),
+            fps: 24.0,
+            black_level: [128; 4],
+            white_level: 4095,
+        };
+        // a gradient with 5 bits of noise from xorshift
+        let mut state = 0x2545f491u32;
+        let planes = [0, 1, 2, 3].map(|plane: u32| {
+            (0..256 * 128)
+                .map(|i: u32| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    let (x, y) = (i % 256, i / 256);
+                    (128 + x * 6 + y * 4 + plane * 100 + (state >> 27)) as u16
+                })
+                .collect::<Vec<_>>()
+        });
+        let mut data = vec![0u8; interp.required_bytes()];
+        merge_planes(&planes, &mut data, &interp).unwrap();
+
+        let start = Instant::now();
+        let coded_planes = split_planes(&data, &interp)
+            .unwrap()
+            .iter()
+            .map(|plane| encode_plane(plane, 256, 12))
+            .collect::<Vec<_>>();
+        let lossless = join_coded_stripes(&coded_planes).len();
+        let lossless_time = start.elapsed();
+
+        let start = Instant::now();
+        let zstd = zstd::bulk::compress(&data, 3).unwrap().len();
+        let zstd_time = start.elapsed();
+
+        let speed = |time: std::time::Duration| data.len() as f64 / time.as_secs_f64() / 1e6;
+        let ratio = |len: usize| data.len() as f64 / len as f64;
+        eprintln!(
+            "lossless: ratio {:.2} at {:.0} MB/s (single threaded), zstd: ratio {:.2} at {:.0} MB/s",
+            ratio(lossless),
+            speed(lossless_time),
+            ratio(zstd),
+            speed(zstd_time)
+        );
+        assert!(lossless < zstd, "lossless: {lossless} bytes, zstd: {zstd} bytes");
+    }
+
+    fn read_container(path: &Path) -> (ContainerInterpretation, Vec<Vec<u8>>) {
+        let context = ProcessingContext::default();
+        let mut file = BufReader::new(fs::File::open(path).unwrap());
+        let index = read_index(&mut file).unwrap();
+        let frames = index
+            .frame_offsets
+            .iter()
+            .map(|offset| {
+                let (data, _) = read_frame(&mut file, *offset, &index.interp, &context).unwrap();
+                data.as_slice(|data| data.to_vec())
+            })
+            .collect();
+        (index.interp, frames)
+    }
+
+    #[test]
+    fn test_container_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("lossless-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (raw, compressed, decompressed) =
+            (dir.join("raw.axr"), dir.join("compressed.axr"), dir.join("decompressed.axr"));
+
+        run_graph(&format!(
+            "
+            pattern: {{ type: TestPattern, width: 64, height: 32, number-of-frames: 3 }}
+            raw: {{ type: AxiomContainerWriter, input: <pattern, path: '{}' }}
+            compress: {{ type: LosslessCompress, input: <pattern }}
+            compressed: {{ type: AxiomContainerWriter, input: <compress, path: '{}' }}
+            ",
+            raw.display(),
+            compressed.display()
+        ));
+        run_graph(&format!(
+            "
+            reader: {{ type: AxiomContainerReader, file: '{}' }}
+            decompress: {{ type: LosslessDecompress, input: <reader }}
+            writer: {{ type: AxiomContainerWriter, input: <decompress, path: '{}' }}
+            ",
+            compressed.display(),
+            decompressed.display()
+        ));
+
+        let (raw_interp, raw_frames) = read_container(&raw);
+        let (compressed_interp, compressed_frames) = read_container(&compressed);
+        let inner = match raw_interp {
+            ContainerInterpretation::Raw(inner) => inner,
+            interp => panic!("expected raw frames, got {interp:?}"),
+        };
+        assert_eq!(compressed_interp, ContainerInterpretation::LosslessCompressed(inner));
+        assert!(compressed_frames.iter().all(|frame| frame.len() < inner.required_bytes()));
+        assert_eq!(read_container(&decompressed), (raw_interp, raw_frames));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
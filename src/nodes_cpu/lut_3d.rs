@@ -0,0 +1,191 @@
+use crate::{
+    nodes_gpu::lut_3d::read_cube_file,
+    pipeline_processing::{
+        frame::{Frame, Rgb},
+        live_parameters::LiveParameter,
+        node::{Caps, InputProcessingNode, NodeID, ProcessingNode, Request},
+        parametrizable::prelude::*,
+        payload::Payload,
+        processing_context::ProcessingContext,
+    },
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+pub struct CpuLut3d {
+    input: InputProcessingNode,
+    file: LiveParameter<String>,
+    /// the lut with the version of `file` it was read from
+    lut: Mutex<(u64, Arc<Lut>)>,
+    context: ProcessingContext,
+}
+
+impl Parameterizable for CpuLut3d {
+    const DESCRIPTION: Option<&'static str> =
+        Some("cpu version of Lut3d (used in its place if no gpu is present)");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("input", Mandatory(NodeInputParameter))
+            .with("file", Mandatory(PathParameter(PathCheck::Exists)))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Rgb]).with_output(&[FrameType::Rgb])
+    }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> Result<Self> {
+        // a lut that can't be read is rejected when it is set while running
+        let file: LiveParameter<String> =
+            parameters
+                .take_live_checked("file", context, |path| read_cube_file(path).map(|_| ()))?;
+        let (version, path) = file.get_versioned();
+        let (size, bgra) = read_cube_file(path)?;
+
+        Ok(Self {
+            input: parameters.take("input")?,
+            file,
+            lut: Mutex::new((version, Arc::new(Lut { size, bgra }))),
+            context: context.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for CpuLut3d {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let input = self.input.pull(request.clone()).await?;
+        let frame = self
+            .context
+            .ensure_cpu_buffer::<Rgb>(&input)
+            .context("Wrong input format for CpuLut3d")?;
+
+        let lut = self.current_lut();
+        let row_bytes = frame.interp.width as usize * 3;
+        let storage = self
+            .context
+            .map_rows_parallel(
+                request.priority(),
+                &frame.storage,
+                frame.interp.height as usize,
+                (row_bytes, row_bytes),
+                move |_, input, output| {
+                    for (input, output) in input.chunks_exact(3).zip(output.chunks_exact_mut(3)) {
+                        output.copy_from_slice(&lut.sample([input[0], input[1], input[2]]));
+                    }
+                },
+            )
+            .await;
+
+        Ok(Payload::from(Frame { interp: frame.interp, storage, metadata: frame.metadata.clone() }))
+    }
+
+    fn get_caps(&self) -> Caps { self.input.get_caps() }
+}
+
+impl CpuLut3d {
+    /// The lut is read again if the file was changed while running. If that
+    /// fails anyway (the file changed after it was checked), the previous lut
+    /// is kept to not interrupt a recording.
+    fn current_lut(&self) -> Arc<Lut> {
+        let (version, path) = self.file.get_versioned();
+        let mut lut = self.lut.lock();
+        if lut.0 != version {
+            match read_cube_file(path.clone()) {
+                Ok((size, bgra)) => lut.1 = Arc::new(Lut { size, bgra }),
+                Err(error) => {
+                    eprintln!("couldn't read the lut {path}, keeping the old one: {error}")
+                }
+            }
+            lut.0 = version;
+        }
+        lut.1.clone()
+    }
+}
+
+/// The lut in the same layout that is uploaded to the gpu by `Lut3d`.
+pub struct Lut {
+    pub size: usize,
+    pub bgra: Vec<u8>,
+}
+
+impl Lut {
+    fn texel(&self, [x, y, z]: [usize; 3]) -> [f32; 3] {
+        let idx = ((z * self.size + y) * self.size + x) * 4;
+        let texel = &self.bgra[idx..idx + 3];
+        [texel[2] as f32 / 255.0, texel[1] as f32 / 255.0, texel[0] as f32 / 255.0]
+    }
+
+    /// Trilinear filtering with repeat addressing, like the sampler of
+    /// `Lut3d`.
+    pub fn sample(&self, rgb: [u8; 3]) -> [u8; 3] {
+        let size = self.size as f32;
+        let mut lower = [0usize; 3];
+        let mut upper = [0usize; 3];
+        let mut weight = [0f32; 3];
+        for i in 0..3 {
+            let coord = rgb[i] as f32 / 255.0 * size - 0.5;
+            let floor = coord.floor();
+            weight[i] = coord - floor;
+            lower[i] = (floor as i64).rem_euclid(self.size as i64) as usize;
+            upper[i] = (floor as i64 + 1).rem_euclid(self.size as i64) as usize;
+        }
+
+        let mut result = [0f32; 3];
+        for corner in 0..8 {
+            let mut pos = [0usize; 3];
+            let mut corner_weight = 1.0;
+            for i in 0..3 {
+                if corner & (1 << i) == 0 {
+                    pos[i] = lower[i];
+                    corner_weight *= 1.0 - weight[i];
+                } else {
+                    pos[i] = upper[i];
+                    corner_weight *= weight[i];
+                }
+            }
+            for (result, value) in result.iter_mut().zip(self.texel(pos)) {
+                *result += value * corner_weight;
+            }
+        }
+
+        result.map(|v| (v * 255.0) as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lut(size: usize, color: impl Fn([usize; 3]) -> [u8; 3]) -> Lut {
+        let mut bgra = vec![];
+        for z in 0..size {
+            for y in 0..size {
+                for x in 0..size {
+                    let [r, g, b] = color([x, y, z]);
+                    bgra.extend([b, g, r, 255]);
+                }
+            }
+        }
+        Lut { size, bgra }
+    }
+
+    #[test]
+    fn test_lut_sample() {
+        // the texel centers are at 0.25 and 0.75 of a lut with two texels
+        let red = lut(2, |[x, _, _]| [x as u8 * 255, 0, 0]);
+        assert_eq!(red.sample([64, 0, 0]), [0, 0, 0]);
+        assert_eq!(red.sample([128, 0, 0]), [128, 0, 0]);
+        assert_eq!(red.sample([191, 0, 0]), [254, 0, 0]);
+        // below the first center the repeat addressing blends in the last texel
+        assert_eq!(red.sample([0, 0, 0]), [127, 0, 0]);
+
+        let identity = lut(4, |[x, y, z]| [x as u8 * 85, y as u8 * 85, z as u8 * 85]);
+        assert_eq!(identity.sample([32, 96, 159]), [0, 85, 169]);
+    }
+}
@@ -0,0 +1,507 @@
+use crate::pipeline_processing::{
+    buffers::CpuBuffer,
+    frame::{Frame, Raw, Rgb},
+    metadata::MetadataEntry,
+    node::{Caps, InputProcessingNode, NodeID, ProcessingNode, Request},
+    parametrizable::prelude::*,
+    payload::Payload,
+    processing_context::ProcessingContext,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs::File, io::Write, path::Path, sync::Arc};
+
+/// The difference of a frame to its reference in one channel. The psnr is
+/// infinite for identical frames.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChannelMetrics {
+    pub channel: String,
+    pub mse: f64,
+    pub psnr: f64,
+    pub ssim: f64,
+    pub max_abs_error: f64,
+}
+
+/// The quality of a frame compared to a reference frame, the first entry is for
+/// all channels together
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FrameQuality(pub Vec<ChannelMetrics>);
+impl MetadataEntry for FrameQuality {
+    const KEY: &'static str = "frame-quality";
+}
+
+enum ReportFormat {
+    Csv,
+    Json,
+}
+
+struct Report {
+    file: Mutex<OrderedRows<File>>,
+    format: ReportFormat,
+}
+
+impl Report {
+    fn create(path: &str) -> Result<Self> {
+        let format = match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => ReportFormat::Csv,
+            Some("json" | "jsonl" | "ndjson") => ReportFormat::Json,
+            _ => return Err(anyhow!("the report {path} has to be a .csv or .json file")),
+        };
+        let mut file = File::create(path).with_context(|| format!("couldn't create {path}"))?;
+        if let ReportFormat::Csv = format {
+            writeln!(file, "frame,channel,mse,psnr,ssim,max_abs_error")?;
+        }
+        Ok(Self { file: Mutex::new(OrderedRows::new(file)), format })
+    }
+
+    fn write(&self, frame_number: u64, metrics: &FrameQuality) -> Result<()> {
+        let text = match self.format {
+            ReportFormat::Csv => metrics
+                .0
+                .iter()
+                .map(|m| {
+                    format!(
+                        "{frame_number},{},{},{},{},{}\n",
+                        m.channel, m.mse, m.psnr, m.ssim, m.max_abs_error
+                    )
+                })
+                .collect(),
+            // one json object per line, psnr values of infinity become null
+            ReportFormat::Json => {
+                let line = serde_json::json!({ "frame": frame_number, "channels": metrics.0 });
+                format!("{line}\n")
+            }
+        };
+        self.file.lock().write(frame_number, text)
+    }
+}
+
+// a frame that never comes (because pulling it failed) doesn't hold back the
+// report for longer than this many frames
+const MAX_PENDING_FRAMES: usize = 256;
+
+/// Writes the rows of the frames in frame order. Frames can be finished out of
+/// order, so the rows of a frame are kept until the ones of the frames before
+/// it are written.
+struct OrderedRows<W: Write> {
+    writer: W,
+    next_frame: u64,
+    pending: BTreeMap<u64, String>,
+}
+
+impl<W: Write> OrderedRows<W> {
+    fn new(writer: W) -> Self { Self { writer, next_frame: 0, pending: BTreeMap::new() } }
+
+    fn write(&mut self, frame_number: u64, rows: String) -> Result<()> {
+        self.pending.insert(frame_number, rows);
+        while let Some(&first) = self.pending.keys().next() {
+            if first > self.next_frame && self.pending.len() <= MAX_PENDING_FRAMES {
+                break;
+            }
+            self.write_first(first)?;
+        }
+        Ok(())
+    }
+
+    /// Writes all rows that are kept, the frames before them are not coming
+    /// anymore
+    fn finish(&mut self) -> Result<()> {
+        while let Some(&first) = self.pending.keys().next() {
+            self.write_first(first)?;
+        }
+        Ok(self.writer.flush()?)
+    }
+
+    fn write_first(&mut self, first: u64) -> Result<()> {
+        if let Some(rows) = self.pending.remove(&first) {
+            self.writer.write_all(rows.as_bytes())?;
+        }
+        self.next_frame = self.next_frame.max(first + 1);
+        Ok(())
+    }
+}
+
+impl<W: Write> Drop for OrderedRows<W> {
+    fn drop(&mut self) {
+        if let Err(error) = self.finish() {
+            eprintln!("couldn't finish the report of QualityMetrics: {error:#}");
+        }
+    }
+}
+
+/// Compares the frames of `input` to the ones of `reference` and passes on the
+/// `input` frames with the `FrameQuality` in their metadata.
+pub struct QualityMetrics {
+    input: InputProcessingNode,
+    reference: InputProcessingNode,
+    report: Option<Arc<Report>>,
+    context: ProcessingContext,
+}
+
+impl Parameterizable for QualityMetrics {
+    const DESCRIPTION: Option<&'static str> = Some(
+        "compute the mse, psnr, ssim and maximum absolute error of input compared to reference \
+         (per cfa or color channel), optionally written to a .csv or .json report",
+    );
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("input", Mandatory(NodeInputParameter))
+            .with("reference", Mandatory(NodeInputParameter))
+            .with("report", Optional(PathParameter(PathCheck::Any)))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new()
+            .with_input("input", &[FrameType::Raw, FrameType::Rgb])
+            .with_input("reference", &[FrameType::Raw, FrameType::Rgb])
+            .with_output_of("input")
+    }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> Result<Self> {
+        let report = match parameters.take::<String>("report")?.as_str() {
+            "" => None,
+            path => Some(Arc::new(Report::create(path)?)),
+        };
+        Ok(Self {
+            input: parameters.take("input")?,
+            reference: parameters.take("reference")?,
+            report,
+            context: context.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for QualityMetrics {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let (input, reference) =
+            futures::join!(self.input.pull(request.clone()), self.reference.pull(request.clone()));
+        let (input, reference) = (input?, reference?);
+
+        let context = &self.context;
+        let (metrics, payload) = if let Ok(frame) = context.ensure_cpu_buffer::<Raw>(&input) {
+            let reference = context
+                .ensure_cpu_buffer::<Raw>(&reference)
+                .context("the reference of QualityMetrics has to be Raw like the input")?;
+            let metrics = raw_metrics(&frame, &reference)?;
+            let mut metadata = frame.metadata.clone();
+            metadata.insert(metrics.clone());
+            let frame = Frame { interp: frame.interp, storage: frame.storage.clone(), metadata };
+            (metrics, Payload::from(frame))
+        } else {
+            let frame = context
+                .ensure_cpu_buffer::<Rgb>(&input)
+                .context("Wrong input format for QualityMetrics")?;
+            let reference = context
+                .ensure_cpu_buffer::<Rgb>(&reference)
+                .context("the reference of QualityMetrics has to be Rgb like the input")?;
+            let metrics = rgb_metrics(&frame, &reference)?;
+            let mut metadata = frame.metadata.clone();
+            metadata.insert(metrics.clone());
+            let frame = Frame { interp: frame.interp, storage: frame.storage.clone(), metadata };
+            (metrics, Payload::from(frame))
+        };
+
+        if let Some(report) = &self.report {
+            report.write(request.frame_number(), &metrics)?;
+        }
+        Ok(payload)
+    }
+
+    fn get_caps(&self) -> Caps {
+        let (input, reference) = (self.input.get_caps(), self.reference.get_caps());
+        let frame_count = match (input.frame_count, reference.frame_count) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        Caps { frame_count, random_access: input.random_access && reference.random_access }
+    }
+}
+
+fn raw_metrics(
+    frame: &Frame<Raw, CpuBuffer>,
+    reference: &Frame<Raw, CpuBuffer>,
+) -> Result<FrameQuality> {
+    let (interp, reference_interp) = (frame.interp, reference.interp);
+    if (interp.width, interp.height, interp.bit_depth)
+        != (reference_interp.width, reference_interp.height, reference_interp.bit_depth)
+    {
+        return Err(anyhow!(
+            "the input is {}x{} with {} bits, but the reference is {}x{} with {} bits",
+            interp.width,
+            interp.height,
+            interp.bit_depth,
+            reference_interp.width,
+            reference_interp.height,
+            reference_interp.bit_depth
+        ));
+    }
+    if interp.cfa != reference_interp.cfa {
+        return Err(anyhow!(
+            "the input has the cfa pattern {:?}, but the reference has {:?}",
+            interp.cfa,
+            reference_interp.cfa
+        ));
+    }
+    let samples = frame.storage.as_slice(|data| raw_samples(data, interp.bit_depth))?;
+    let reference = reference.storage.as_slice(|data| raw_samples(data, interp.bit_depth))?;
+
+    // every position of the 2x2 cfa pattern is its own channel
+    let (width, height) = (interp.width as usize, interp.height as usize);
+    let plane = |samples: &[f64], dx: usize, dy: usize| -> Vec<f64> {
+        (dy..height)
+            .step_by(2)
+            .flat_map(|y| (dx..width).step_by(2).map(move |x| samples[y * width + x]))
+            .collect()
+    };
+    // the sensor saturates at the white level, which can be far below the
+    // maximum of the bit depth (e.g. 12 bit data stored as 16 bit)
+    let peak = interp.white_level as f64;
+    let mut channels = vec![];
+    for (dy, dx) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+        let name = interp.cfa.color_at(dx, dy).name();
+        let (a, b) = (plane(&samples, dx, dy), plane(&reference, dx, dy));
+        let (plane_width, plane_height) = ((width + 1 - dx) / 2, (height + 1 - dy) / 2);
+        channels.push(plane_metrics(name, &a, &b, plane_width, plane_height, peak));
+    }
+    Ok(with_overall(channels, &samples, &reference, peak))
+}
+
+fn rgb_metrics(
+    frame: &Frame<Rgb, CpuBuffer>,
+    reference: &Frame<Rgb, CpuBuffer>,
+) -> Result<FrameQuality> {
+    if (frame.interp.width, frame.interp.height)
+        != (reference.interp.width, reference.interp.height)
+    {
+        return Err(anyhow!(
+            "the input is {}x{}, but the reference is {}x{}",
+            frame.interp.width,
+            frame.interp.height,
+            reference.interp.width,
+            reference.interp.height
+        ));
+    }
+    let to_f64 = |data: &[u8]| data.iter().map(|v| *v as f64).collect::<Vec<_>>();
+    let samples = frame.storage.as_slice(to_f64);
+    let reference = reference.storage.as_slice(to_f64);
+
+    let (width, height) = (frame.interp.width as usize, frame.interp.height as usize);
+    let channel = |samples: &[f64], c: usize| samples.iter().skip(c).step_by(3).copied().collect();
+    let mut channels = vec![];
+    for (c, name) in ["red", "green", "blue"].into_iter().enumerate() {
+        let (a, b): (Vec<_>, Vec<_>) = (channel(&samples, c), channel(&reference, c));
+        channels.push(plane_metrics(name, &a, &b, width, height, 255.0));
+    }
+    Ok(with_overall(channels, &samples, &reference, 255.0))
+}
+
+/// Unpacks the values of a raw frame with 8, 12 or 16 bits per value
+pub(crate) fn raw_samples(data: &[u8], bit_depth: u64) -> Result<Vec<f64>> {
+    Ok(match bit_depth {
+        8 => data.iter().map(|v| *v as f64).collect(),
+        12 => data
+            .chunks_exact(3)
+            .flat_map(|v| {
+                let (a, b, c) = (v[0] as u16, v[1] as u16, v[2] as u16);
+                [((a << 4) | (b >> 4)) as f64, (((b & 0xf) << 8) | c) as f64]
+            })
+            .collect(),
+        16 => data.chunks_exact(2).map(|v| u16::from_le_bytes([v[0], v[1]]) as f64).collect(),
+        _ => {
+            return Err(anyhow!(
+                "only 8, 12 and 16 bit raw frames are supported, not {bit_depth} bit"
+            ))
+        }
+    })
+}
+
+// the window for the ssim is moved by half its size
+const SSIM_WINDOW: usize = 8;
+
+/// Compares two images of `width` x `height` values
+fn plane_metrics(
+    channel: &str,
+    a: &[f64],
+    b: &[f64],
+    width: usize,
+    height: usize,
+    peak: f64,
+) -> ChannelMetrics {
+    let (mse, psnr, max_abs_error) = error_metrics(a, b, peak);
+    let ssim = ssim(a, b, width, height, peak);
+    ChannelMetrics { channel: channel.to_string(), mse, psnr, ssim, max_abs_error }
+}
+
+/// Puts the metrics over all values in front of the ones of the channels. The
+/// ssim is the mean of the ssim of the channels, as it only makes sense
+/// within a channel.
+fn with_overall(channels: Vec<ChannelMetrics>, a: &[f64], b: &[f64], peak: f64) -> FrameQuality {
+    let (mse, psnr, max_abs_error) = error_metrics(a, b, peak);
+    let ssim = channels.iter().map(|c| c.ssim).sum::<f64>() / channels.len().max(1) as f64;
+    let all = ChannelMetrics { channel: "all".to_string(), mse, psnr, ssim, max_abs_error };
+    FrameQuality([all].into_iter().chain(channels).collect())
+}
+
+/// The mean squared error, the psnr and the maximum absolute error
+fn error_metrics(a: &[f64], b: &[f64], peak: f64) -> (f64, f64, f64) {
+    let squared_error: f64 = a.iter().zip(b).map(|(a, b)| (a - b).powi(2)).sum();
+    let mse = squared_error / a.len().max(1) as f64;
+    let psnr = if mse == 0.0 { f64::INFINITY } else { 10.0 * (peak * peak / mse).log10() };
+    let max_abs_error = a.iter().zip(b).map(|(a, b)| (a - b).abs()).fold(0.0, f64::max);
+    (mse, psnr, max_abs_error)
+}
+
+fn ssim(a: &[f64], b: &[f64], width: usize, height: usize, peak: f64) -> f64 {
+    if width == 0 || height == 0 {
+        return 1.0;
+    }
+    let (c1, c2) = ((0.01 * peak).powi(2), (0.03 * peak).powi(2));
+    let (window_width, window_height) = (SSIM_WINDOW.min(width), SSIM_WINDOW.min(height));
+    let starts = |size: usize, window: usize| (0..=size - window).step_by((window / 2).max(1));
+
+    let mut sum = 0.0;
+    let mut windows = 0;
+    for y0 in starts(height, window_height) {
+        for x0 in starts(width, window_width) {
+            let indices = (y0..y0 + window_height)
+                .flat_map(|y| (x0..x0 + window_width).map(move |x| y * width + x));
+            let n = (window_width * window_height) as f64;
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) =
+                (0.0, 0.0, 0.0, 0.0, 0.0);
+            for i in indices {
+                let (a, b) = (a[i], b[i]);
+                sum_a += a;
+                sum_b += b;
+                sum_aa += a * a;
+                sum_bb += b * b;
+                sum_ab += a * b;
+            }
+            let (mean_a, mean_b) = (sum_a / n, sum_b / n);
+            let var_a = sum_aa / n - mean_a * mean_a;
+            let var_b = sum_bb / n - mean_b * mean_b;
+            let covariance = sum_ab / n - mean_a * mean_b;
+
+            sum += ((2.0 * mean_a * mean_b + c1) * (2.0 * covariance + c2))
+                / ((mean_a * mean_a + mean_b * mean_b + c1) * (var_a + var_b + c2));
+            windows += 1;
+        }
+    }
+    if windows == 0 {
+        1.0
+    } else {
+        sum / windows as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline_processing::frame::{CfaDescriptor, FrameInterpretation};
+
+    #[test]
+    fn test_plane_metrics() {
+        let a: Vec<f64> = (0..256).map(|v| (v % 16 * 16) as f64).collect();
+        let identical = plane_metrics("all", &a, &a, 16, 16, 255.0);
+        assert_eq!(
+            (identical.mse, identical.psnr, identical.max_abs_error),
+            (0.0, f64::INFINITY, 0.0)
+        );
+        assert!((identical.ssim - 1.0).abs() < 1e-9);
+
+        let b: Vec<f64> = a.iter().map(|v| v + 5.0).collect();
+        let shifted = plane_metrics("all", &a, &b, 16, 16, 255.0);
+        assert_eq!((shifted.mse, shifted.max_abs_error), (25.0, 5.0));
+        assert!((shifted.psnr - 34.151).abs() < 1e-3, "{}", shifted.psnr);
+        assert!(shifted.ssim < 1.0 && shifted.ssim > 0.9, "{}", shifted.ssim);
+
+        let noise: Vec<f64> =
+            a.iter().enumerate().map(|(i, v)| v + (i % 7) as f64 * 20.0).collect();
+        let noisy = plane_metrics("all", &a, &noise, 16, 16, 255.0);
+        assert!(noisy.ssim < shifted.ssim, "{} {}", noisy.ssim, shifted.ssim);
+    }
+
+    #[test]
+    fn test_raw_samples() {
+        assert_eq!(raw_samples(&[0xab, 0xcd, 0xef], 12).unwrap(), vec![0xabc as f64, 0xdef as f64]);
+        assert_eq!(raw_samples(&[0x34, 0x12], 16).unwrap(), vec![0x1234 as f64]);
+        assert!(raw_samples(&[0], 10).is_err());
+    }
+
+    #[test]
+    fn test_ordered_rows() {
+        let mut written = vec![];
+        {
+            let mut rows = OrderedRows::new(&mut written);
+            for frame in [1, 0, 3, 2, 5] {
+                rows.write(frame, format!("{frame}\n")).unwrap();
+            }
+        }
+        // frame 4 never came, the rows after it are written when the report is dropped
+        assert_eq!(String::from_utf8(written).unwrap(), "0\n1\n2\n3\n5\n");
+
+        let mut written = vec![];
+        let mut rows = OrderedRows::new(&mut written);
+        for frame in 1..=MAX_PENDING_FRAMES as u64 + 1 {
+            rows.write(frame, format!("{frame}\n")).unwrap();
+        }
+        // frame 0 is given up on once too many frames are waiting for it
+        assert!(rows.pending.is_empty());
+        rows.write(0, "0\n".to_string()).unwrap();
+        drop(rows);
+        assert!(String::from_utf8(written).unwrap().starts_with("1\n2\n"));
+    }
+
+    #[test]
+    fn test_raw_metrics_cfa() {
+        let context = ProcessingContext::default();
+        let frame = |red_in_first_col| {
+            let interp = Raw {
+                width: 4,
+                height: 2,
+                bit_depth: 8,
+                cfa: CfaDescriptor::from_first_red(red_in_first_col, true),
+                fps: 24.0,
+                black_level: [0; 4],
+                white_level: 255,
+            };
+            let mut storage = unsafe { context.get_uninit_cpu_buffer(interp.required_bytes()) };
+            storage.as_mut_slice(|storage| storage.fill(128));
+            Frame { interp, storage, metadata: Default::default() }
+        };
+        assert_eq!(raw_metrics(&frame(true), &frame(true)).unwrap().0[0].mse, 0.0);
+        assert!(raw_metrics(&frame(true), &frame(false)).is_err());
+    }
+
+    #[test]
+    fn test_raw_metrics_white_level() {
+        let context = ProcessingContext::default();
+        // 12 bit data in 16 bit samples, the error is as large as the white level
+        let frame = |value: u16| {
+            let interp = Raw {
+                width: 4,
+                height: 2,
+                bit_depth: 16,
+                cfa: CfaDescriptor::from_first_red(true, true),
+                fps: 24.0,
+                black_level: [0; 4],
+                white_level: 4095,
+            };
+            let mut storage = unsafe { context.get_uninit_cpu_buffer(interp.required_bytes()) };
+            storage.as_mut_slice(|storage| {
+                storage
+                    .chunks_mut(2)
+                    .for_each(|sample| sample.copy_from_slice(&value.to_be_bytes()))
+            });
+            Frame { interp, storage, metadata: Default::default() }
+        };
+        let quality = raw_metrics(&frame(0), &frame(4095)).unwrap();
+        assert_eq!((quality.0[0].mse, quality.0[0].psnr), (4095.0 * 4095.0, 0.0));
+    }
+}
@@ -1,8 +1,16 @@
 pub mod average;
 pub mod benchmark_sink;
 pub mod bitdepth_convert;
+pub mod color_voodoo;
+pub mod composite;
+pub mod darkframe_subtract;
+pub mod debayer;
 pub mod dual_frame_raw_decoder;
 pub mod fp_to_uint;
+pub mod lossless;
+pub mod lut_3d;
+pub mod metrics;
 pub mod row_noise_removal;
+pub mod statistics;
 pub mod sz3;
 pub mod zstd;
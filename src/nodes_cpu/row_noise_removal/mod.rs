@@ -3,14 +3,17 @@ use crate::pipeline_processing::{
     parametrizable::{Parameterizable, Parameters, ParametersDescriptor},
     payload::Payload,
 };
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 
 
-use crate::pipeline_processing::{
-    frame::{Frame, FrameInterpretation, Raw},
-    node::{Caps, NodeID, ProcessingNode, Request},
-    parametrizable::prelude::*,
-    processing_context::ProcessingContext,
+use crate::{
+    pipeline_processing::{
+        frame::{Frame, FrameInterpretation, Raw},
+        node::{Caps, NodeID, ProcessingNode, Request},
+        parametrizable::prelude::*,
+        processing_context::ProcessingContext,
+    },
+    util::edit_distance::closest_match,
 };
 use async_trait::async_trait;
 
@@ -57,11 +60,21 @@ pub struct RowNoiseRemoval {
 }
 
 impl Parameterizable for RowNoiseRemoval {
+    const DESCRIPTION: Option<&'static str> = Some(
+        "remove the row noise using the dark columns, the model is either internal:good, \
+         internal:only_dark, internal:only_green, internal:mean, a yaml file or given inline as \
+         inline-model",
+    );
+
     fn describe_parameters() -> ParametersDescriptor {
         ParametersDescriptor::new()
             .with("input", Mandatory(NodeInputParameter))
             .with("strip-dark-columns", Optional(BoolParameter))
             .with("model", WithDefault(StringParameter, StringValue("internal:good".to_owned())))
+            .with("inline-model", Optional(MapParameter))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).with_output(&[FrameType::Raw])
     }
 
     fn from_parameters(
@@ -70,24 +83,48 @@ impl Parameterizable for RowNoiseRemoval {
         context: &ProcessingContext,
     ) -> Result<Self> {
         let model_path: String = parameters.take("model")?;
-        let model_yml = match model_path.as_str() {
-            "internal:good" => include_str!("./good.yml").to_owned(),
-            "internal:only_dark" => include_str!("./only_dark.yml").to_owned(),
-            "internal:only_green" => include_str!("./only_green.yml").to_owned(),
-            "internal:mean" => include_str!("./mean.yml").to_owned(),
-            _ => std::fs::read_to_string(&model_path)
-                .with_context(|| format!("Failed to read model from {}", model_path))?,
+        let inline_model: serde_yaml::Mapping = parameters.take("inline-model")?;
+        let model = if inline_model.is_empty() {
+            serde_yaml::from_str(&read_model(&model_path)?)?
+        } else {
+            serde_yaml::from_value(serde_yaml::Value::Mapping(inline_model))
+                .context("invalid inline-model")?
         };
 
         Ok(Self {
             input: parameters.take("input")?,
             strip_dark_columns: parameters.take("strip-dark-columns")?,
             context: context.clone(),
-            model: serde_yaml::from_str(&model_yml)?,
+            model,
         })
     }
 }
 
+const INTERNAL_MODELS: [(&str, &str); 4] = [
+    ("internal:good", include_str!("./good.yml")),
+    ("internal:only_dark", include_str!("./only_dark.yml")),
+    ("internal:only_green", include_str!("./only_green.yml")),
+    ("internal:mean", include_str!("./mean.yml")),
+];
+
+/// The yaml of an internal model or of a model file
+fn read_model(model: &str) -> Result<String> {
+    if let Some((_, yaml)) = INTERNAL_MODELS.iter().find(|(name, _)| *name == model) {
+        return Ok(yaml.to_string());
+    }
+    let internal_models = || INTERNAL_MODELS.iter().map(|(name, _)| *name);
+    let did_you_mean = closest_match(model, internal_models())
+        .map(|name| format!(", did you mean {name}?"))
+        .unwrap_or_default();
+    if model.starts_with("internal:") {
+        return Err(anyhow!(
+            "there is no model {model}, the internal models are {}{did_you_mean}",
+            internal_models().collect::<Vec<_>>().join(", ")
+        ));
+    }
+    std::fs::read_to_string(model)
+        .with_context(|| format!("Failed to read model from {model}{did_you_mean}"))
+}
 
 fn get_col_parity_for_row(interp: Raw, row: usize) -> usize {
     let parity_for_even_row = if interp.cfa.red_in_first_col && interp.cfa.red_in_first_row {
@@ -108,7 +145,6 @@ fn get_col_parity_for_row(interp: Raw, row: usize) -> usize {
 
 // per side
 const NUM_DARKCOLS: usize = 8;
-const BLACK_LEVEL: f32 = 128f32;
 
 #[async_trait]
 impl ProcessingNode for RowNoiseRemoval {
@@ -118,7 +154,12 @@ impl ProcessingNode for RowNoiseRemoval {
         let interp = frame.interp;
         let width = interp.width as usize;
         let height = interp.height as usize;
-        assert_eq!(frame.interp.bit_depth, 16);
+        if interp.bit_depth != 16 {
+            return Err(anyhow!(
+                "RowNoiseRemoval needs 16 bit input, but got {} bit (use a BitDepthConverter)",
+                interp.bit_depth
+            ));
+        }
 
         let model = &self.model;
 
@@ -166,6 +207,8 @@ impl ProcessingNode for RowNoiseRemoval {
         let output_interp = Raw { width: output_width as u64, ..interp };
         let mut row_noise_removed =
             unsafe { self.context.get_uninit_cpu_buffer(output_interp.required_bytes()) };
+        // the largest offset that was subtracted from a row, for the white level
+        let mut max_offset = 0f32;
 
         frame.storage.as_slice(|src| {
             let src: &[u16] = bytemuck::cast_slice(src);
@@ -196,27 +239,31 @@ impl ProcessingNode for RowNoiseRemoval {
                             offset += weights_even[col]
                                 * (src[(even_row as isize + 2 * lag) as usize * width + col]
                                     as f32
-                                    - BLACK_LEVEL);
+                                    - interp.black_level_at(col, even_row) as f32);
                             offset += weights_even[col + NUM_DARKCOLS]
                                 * (src[(even_row as isize + 2 * lag) as usize * width
                                     + (width - NUM_DARKCOLS)
                                     + col] as f32
-                                    - BLACK_LEVEL);
+                                    - interp.black_level_at(width - NUM_DARKCOLS + col, even_row)
+                                        as f32);
                         }
 
                         for col in 0..NUM_DARKCOLS {
                             offset += weights_odd[col]
                                 * (src[(even_row as isize + 1 + 2 * lag) as usize * width + col]
                                     as f32
-                                    - BLACK_LEVEL);
+                                    - interp.black_level_at(col, even_row + 1) as f32);
                             offset += weights_odd[col + NUM_DARKCOLS]
                                 * (src[(even_row as isize + 1 + 2 * lag) as usize * width
                                     + (width - NUM_DARKCOLS)
                                     + col] as f32
-                                    - BLACK_LEVEL);
+                                    - interp
+                                        .black_level_at(width - NUM_DARKCOLS + col, even_row + 1)
+                                        as f32);
                         }
                     }
 
+                    max_offset = max_offset.max(offset);
                     for col in 0..output_width {
                         dst[row * output_width + col] =
                             (src[row * width + col + strip_offset] as f32 - offset) as u16;
@@ -241,8 +288,67 @@ impl ProcessingNode for RowNoiseRemoval {
             })
         });
 
-        Ok(Payload::from(Frame { storage: row_noise_removed, interp: output_interp }))
+        Ok(Payload::from(Frame {
+            storage: row_noise_removed,
+            interp: corrected_interp(output_interp, model, max_offset),
+            metadata: frame.metadata.clone(),
+        }))
     }
 
     fn get_caps(&self) -> Caps { self.input.get_caps() }
 }
+
+/// The row offsets are measured against the black level, so only the constant
+/// offset of the model moves it. A saturated pixel ends up at the white level
+/// minus the offset of its row, so the largest offset gives the brightest value
+/// that still clips in every row.
+fn corrected_interp(interp: Raw, model: &RowNoiseRemovalModel, max_offset: f32) -> Raw {
+    let black_level = [0, 1, 2, 3].map(|i| {
+        let weights = if i < 2 { &model.weights_even } else { &model.weights_odd };
+        (interp.black_level[i] as f32 - weights.offset).round().max(0.0) as u32
+    });
+    let white_level = (interp.white_level as f32 - max_offset)
+        .round()
+        .clamp(black_level.into_iter().max().unwrap_or(0) as f32, u16::MAX as f32)
+        as u32;
+    Raw { black_level, white_level, ..interp }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline_processing::frame::CfaDescriptor;
+
+    #[test]
+    fn test_corrected_interp() {
+        let model: RowNoiseRemovalModel = serde_yaml::from_str(
+            "{ weights_even: { offset: 2.0 }, weights_odd: { offset: -1.0 } }",
+        )
+        .unwrap();
+        let interp = Raw {
+            width: 32,
+            height: 8,
+            bit_depth: 16,
+            cfa: CfaDescriptor::from_first_red(true, true),
+            fps: 24.0,
+            black_level: [128; 4],
+            white_level: 4095,
+        };
+
+        let corrected = corrected_interp(interp, &model, 10.4);
+        assert_eq!(corrected.black_level, [126, 126, 129, 129]);
+        assert_eq!(corrected.white_level, 4085);
+    }
+
+    #[test]
+    fn test_read_model() {
+        for (name, _) in INTERNAL_MODELS {
+            let yaml = read_model(name).unwrap();
+            assert!(serde_yaml::from_str::<RowNoiseRemovalModel>(&yaml).is_ok(), "{name}");
+        }
+        let error = format!("{:#}", read_model("internal:god").unwrap_err());
+        assert!(error.contains("did you mean internal:good?"), "{error}");
+        assert!(read_model("internal:unknown").is_err());
+        assert!(read_model("/does/not/exist.yml").is_err());
+    }
+}
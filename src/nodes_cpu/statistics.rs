@@ -0,0 +1,362 @@
+use crate::{
+    nodes_cpu::metrics::raw_samples,
+    pipeline_processing::{
+        frame::{Frame, Raw},
+        metadata::{FrameMetadata, MetadataEntry},
+        node::{
+            Caps,
+            InputProcessingNode,
+            NodeID,
+            ProcessingNode,
+            ProgressUpdate,
+            Request,
+            SinkNode,
+        },
+        parametrizable::prelude::*,
+        payload::Payload,
+        processing_context::ProcessingContext,
+        puller::pull_ordered,
+    },
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::Arc,
+};
+
+/// The statistics of one position of the 2x2 cfa pattern. Everything except
+/// the black level is computed from the active area, without the dark columns.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChannelStatistics {
+    pub channel: String,
+    pub mean: f64,
+    pub std_dev: f64,
+    /// the number of pixels that are zero
+    pub clipped_low: u64,
+    /// the number of pixels at or above the white level
+    pub clipped_high: u64,
+    /// the mean of the dark columns, if there are any
+    pub black_level: Option<f64>,
+    pub histogram: Vec<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FrameStatistics(pub Vec<ChannelStatistics>);
+impl MetadataEntry for FrameStatistics {
+    const KEY: &'static str = "frame-statistics";
+}
+
+/// Computes the `FrameStatistics` of raw frames and passes the frames on with
+/// the statistics in their metadata.
+pub struct Statistics {
+    input: InputProcessingNode,
+    bins: usize,
+    dark_columns: usize,
+    context: ProcessingContext,
+}
+
+impl Parameterizable for Statistics {
+    const DESCRIPTION: Option<&'static str> = Some(
+        "compute a histogram, mean, standard deviation, clipped pixels and the black level (from \
+         dark-columns at the left and right edge, e.g. 8 for the AXIOM Beta) for every cfa \
+         channel",
+    );
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("input", Mandatory(NodeInputParameter))
+            .with("bins", WithDefault(NaturalGreaterZero(), IntRangeValue(256)))
+            .with("dark-columns", Optional(NaturalWithZero()))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).with_output(&[FrameType::Raw])
+    }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> Result<Self> {
+        Ok(Self {
+            input: parameters.take("input")?,
+            bins: parameters.take::<u64>("bins")? as usize,
+            dark_columns: parameters.take::<u64>("dark-columns")? as usize,
+            context: context.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for Statistics {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let input = self.input.pull(request).await?;
+        let frame = self
+            .context
+            .ensure_cpu_buffer::<Raw>(&input)
+            .context("Wrong input format for Statistics")?;
+
+        let interp = frame.interp;
+        let samples = frame.storage.as_slice(|data| raw_samples(data, interp.bit_depth))?;
+        let statistics = frame_statistics(&samples, &interp, self.bins, self.dark_columns)?;
+
+        let mut metadata = frame.metadata.clone();
+        metadata.insert(statistics);
+        Ok(Payload::from(Frame { interp, storage: frame.storage.clone(), metadata }))
+    }
+
+    fn get_caps(&self) -> Caps { self.input.get_caps() }
+}
+
+fn frame_statistics(
+    samples: &[f64],
+    interp: &Raw,
+    bins: usize,
+    dark_columns: usize,
+) -> Result<FrameStatistics> {
+    let (width, height) = (interp.width as usize, interp.height as usize);
+    if width <= 2 * dark_columns {
+        return Err(anyhow!("a frame of width {width} doesn't have {dark_columns} dark columns"));
+    }
+    // the histogram spans the values up to the white level, the ones above it
+    // are counted in the last bin
+    let value_range = (interp.white_level as u64 + 1) as f64;
+
+    let mut channels = vec![];
+    for (dy, dx) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+        let values = |columns: std::ops::Range<usize>| {
+            (dy..height).step_by(2).flat_map(move |y| {
+                columns.clone().filter(move |x| x % 2 == dx).map(move |x| samples[y * width + x])
+            })
+        };
+
+        let (mut count, mut sum, mut sum_squares) = (0u64, 0.0, 0.0);
+        let (mut clipped_low, mut clipped_high) = (0, 0);
+        let mut histogram = vec![0; bins];
+        for value in values(dark_columns..width - dark_columns) {
+            count += 1;
+            sum += value;
+            sum_squares += value * value;
+            if value <= 0.0 {
+                clipped_low += 1;
+            }
+            if value >= interp.white_level as f64 {
+                clipped_high += 1;
+            }
+            histogram[((value / value_range * bins as f64) as usize).min(bins - 1)] += 1;
+        }
+        let mean = sum / count.max(1) as f64;
+        let std_dev = (sum_squares / count.max(1) as f64 - mean * mean).max(0.0).sqrt();
+
+        let black_level = if dark_columns == 0 {
+            None
+        } else {
+            let dark = values(0..dark_columns).chain(values(width - dark_columns..width));
+            let (dark_count, dark_sum) = dark.fold((0, 0.0), |(n, sum), v| (n + 1, sum + v));
+            Some(dark_sum / (dark_count as f64).max(1.0))
+        };
+
+        channels.push(ChannelStatistics {
+            channel: interp.cfa.color_at(dx, dy).name().to_string(),
+            mean,
+            std_dev,
+            clipped_low,
+            clipped_high,
+            black_level,
+            histogram,
+        });
+    }
+    Ok(FrameStatistics(channels))
+}
+
+enum StatisticsFormat {
+    Csv,
+    /// a single json array of all frames
+    Json,
+    /// one json object per line
+    NdJson,
+}
+
+/// Writes the `FrameStatistics` of every frame of its input (usually a
+/// `Statistics` node) to a .csv, .json or .ndjson file.
+pub struct StatisticsWriter {
+    file: Mutex<BufWriter<File>>,
+    format: StatisticsFormat,
+    input: InputProcessingNode,
+    number_of_frames: u64,
+    priority: u8,
+}
+
+impl Parameterizable for StatisticsWriter {
+    const DESCRIPTION: Option<&'static str> =
+        Some("write the statistics computed by Statistics to a .csv, .json or .ndjson file");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("path", Mandatory(PathParameter(PathCheck::Any)))
+            .with("input", Mandatory(NodeInputParameter))
+            .with("priority", Optional(U8()))
+            .with("number-of-frames", Optional(NaturalWithZero()))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).sink()
+    }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        _context: &ProcessingContext,
+    ) -> Result<Self> {
+        let path = parameters.take::<String>("path")?;
+        let format = match Path::new(&path).extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => StatisticsFormat::Csv,
+            Some("json") => StatisticsFormat::Json,
+            Some("ndjson" | "jsonl") => StatisticsFormat::NdJson,
+            _ => {
+                return Err(anyhow!("the statistics file {path} has to be .csv, .json or .ndjson"))
+            }
+        };
+        let file = File::create(&path).with_context(|| format!("couldn't create {path}"))?;
+        Ok(Self {
+            file: Mutex::new(BufWriter::new(file)),
+            format,
+            input: parameters.take("input")?,
+            number_of_frames: parameters.take("number-of-frames")?,
+            priority: parameters.take("priority")?,
+        })
+    }
+}
+
+#[async_trait]
+impl SinkNode for StatisticsWriter {
+    async fn run(
+        &self,
+        context: &ProcessingContext,
+        progress_callback: Arc<dyn Fn(ProgressUpdate) + Send + Sync>,
+    ) -> Result<()> {
+        let rx = pull_ordered(
+            context,
+            self.priority,
+            progress_callback,
+            self.input.clone_for_same_puller(),
+            self.number_of_frames,
+        );
+
+        match self.format {
+            StatisticsFormat::Csv => writeln!(
+                self.file.lock(),
+                "frame,channel,mean,std_dev,clipped_low,clipped_high,black_level,histogram"
+            )?,
+            StatisticsFormat::Json => writeln!(self.file.lock(), "[")?,
+            StatisticsFormat::NdJson => {}
+        }
+
+        // the frames arrive in order, so counting them gives the frame numbers
+        let mut frame_number = 0u64;
+        while let Ok(payload) = rx.recv_async().await {
+            let statistics = FrameMetadata::of_payload(&payload)
+                .get::<FrameStatistics>()
+                .context("the input of StatisticsWriter has to come from a Statistics node")?;
+            self.write_frame(frame_number, &statistics)?;
+            frame_number += 1;
+        }
+
+        let mut file = self.file.lock();
+        if let StatisticsFormat::Json = self.format {
+            writeln!(file, "\n]")?;
+        }
+        Ok(file.flush()?)
+    }
+}
+
+impl StatisticsWriter {
+    fn write_frame(&self, frame_number: u64, statistics: &FrameStatistics) -> Result<()> {
+        let mut file = self.file.lock();
+        match self.format {
+            StatisticsFormat::Csv => {
+                for channel in &statistics.0 {
+                    let histogram: Vec<_> =
+                        channel.histogram.iter().map(|v| v.to_string()).collect();
+                    writeln!(
+                        file,
+                        "{frame_number},{},{},{},{},{},{},{}",
+                        channel.channel,
+                        channel.mean,
+                        channel.std_dev,
+                        channel.clipped_low,
+                        channel.clipped_high,
+                        channel.black_level.map(|v| v.to_string()).unwrap_or_default(),
+                        histogram.join(" ")
+                    )?;
+                }
+            }
+            StatisticsFormat::Json => {
+                if frame_number > 0 {
+                    writeln!(file, ",")?;
+                }
+                let frame = serde_json::json!({ "frame": frame_number, "channels": statistics.0 });
+                write!(file, "{frame}")?;
+            }
+            StatisticsFormat::NdJson => {
+                let frame = serde_json::json!({ "frame": frame_number, "channels": statistics.0 });
+                writeln!(file, "{frame}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline_processing::frame::CfaDescriptor;
+
+    #[test]
+    fn test_frame_statistics() {
+        let interp = Raw {
+            width: 8,
+            height: 2,
+            bit_depth: 8,
+            cfa: CfaDescriptor::from_first_red(true, true),
+            fps: 24.0,
+            black_level: [0; 4],
+            white_level: 200,
+        };
+        // two dark columns on each side with 10, the active area has 0 and 255 in
+        // red and 100 everywhere else
+        let samples: Vec<f64> = (0..16)
+            .map(|i| match (i % 8, i / 8) {
+                (0 | 1 | 6 | 7, _) => 10.0,
+                (2, 0) => 0.0,
+                (4, 0) => 255.0,
+                _ => 100.0,
+            })
+            .collect();
+
+        let statistics = frame_statistics(&samples, &interp, 4, 2).unwrap();
+        let red = &statistics.0[0];
+        assert_eq!(red.channel, "red");
+        assert_eq!((red.mean, red.std_dev), (127.5, 127.5));
+        assert_eq!((red.clipped_low, red.clipped_high), (1, 1));
+        assert_eq!(red.black_level, Some(10.0));
+        assert_eq!(red.histogram, vec![1, 0, 0, 1]);
+
+        let blue = &statistics.0[3];
+        assert_eq!(blue.channel, "blue");
+        assert_eq!((blue.mean, blue.std_dev), (100.0, 0.0));
+        assert_eq!(blue.histogram, vec![0, 2, 0, 0]);
+
+        // the bins are spread over the values up to the white level, not the bit depth
+        let interp = Raw { bit_depth: 16, white_level: 255, ..interp };
+        let statistics = frame_statistics(&samples, &interp, 4, 2).unwrap();
+        assert_eq!(statistics.0[0].histogram, vec![1, 0, 0, 1]);
+        assert_eq!(statistics.0[3].histogram, vec![0, 2, 0, 0]);
+
+        assert_eq!(frame_statistics(&samples, &interp, 4, 0).unwrap().0[0].black_level, None);
+        assert!(frame_statistics(&samples, &interp, 4, 4).is_err());
+    }
+}
@@ -1,26 +1,28 @@
 use crate::pipeline_processing::{
-    frame::{Frame, Raw, Rgb, SZ3Compressed},
+    frame::{
+        Frame,
+        FrameInterpretation,
+        FrameInterpretations,
+        Raw,
+        Rgb,
+        SZ3Compressed,
+        SZ3DataType,
+        SZ3ErrorBound,
+    },
+    metadata::FrameMetadata,
     node::{Caps, InputProcessingNode, NodeID, ProcessingNode, Request},
     parametrizable::prelude::*,
     payload::Payload,
     processing_context::ProcessingContext,
 };
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use std::sync::Arc;
-
-enum DataType {
-    F32,
-    F64,
-    I64,
-    I32,
-}
 
 pub struct SZ3Compress {
     input: InputProcessingNode,
     dims: Option<Vec<i64>>,
-    error_bound: sz3::ErrorBound,
-    data_type: DataType,
+    error_bound: SZ3ErrorBound,
+    data_type: SZ3DataType,
     context: ProcessingContext,
 }
 impl Parameterizable for SZ3Compress {
@@ -28,13 +30,23 @@ impl Parameterizable for SZ3Compress {
         ParametersDescriptor::new()
             .with("input", Mandatory(NodeInputParameter))
             .with("tolerance", Mandatory(PositiveReal()))
-            .with("error_control", Mandatory(StringParameter))
-            .with("data_type", Mandatory(StringParameter))
+            .with("error_control", Mandatory(ChoiceParameter(vec!["abs", "rel", "l2norm", "psnr"])))
+            .with(
+                "data_type",
+                Mandatory(ChoiceParameter(vec![
+                    "float", "f32", "double", "f64", "int", "i32", "long", "i64",
+                ])),
+            )
             .with(
                 "dims",
                 WithDefault(ListParameter(Box::new(IntRange(-1, i64::MAX))), ListValue(vec![])),
             )
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new()
+            .with_input("input", &[FrameType::Raw, FrameType::Rgb])
+            .with_output(&[FrameType::SZ3Compressed])
+    }
 
     fn from_parameters(
         mut parameters: Parameters,
@@ -42,19 +54,19 @@ impl Parameterizable for SZ3Compress {
         context: &ProcessingContext,
     ) -> Result<Self> {
         let tolerance = parameters.take("tolerance")?;
-        let error_bound = match &*parameters.take::<String>("error_control")?.to_lowercase() {
-            "abs" => Ok(sz3::ErrorBound::Absolute(tolerance)),
-            "rel" => Ok(sz3::ErrorBound::Relative(tolerance)),
-            "l2norm" => Ok(sz3::ErrorBound::L2Norm(tolerance)),
-            "psnr" => Ok(sz3::ErrorBound::PSNR(tolerance)),
+        let error_bound = match &*parameters.take::<String>("error_control")? {
+            "abs" => Ok(SZ3ErrorBound::Absolute(tolerance)),
+            "rel" => Ok(SZ3ErrorBound::Relative(tolerance)),
+            "l2norm" => Ok(SZ3ErrorBound::L2Norm(tolerance)),
+            "psnr" => Ok(SZ3ErrorBound::Psnr(tolerance)),
             other => Err(anyhow::anyhow!("unknown error control {other}")),
         }?;
 
-        let data_type = match &*parameters.take::<String>("data_type")?.to_lowercase() {
-            "float" | "f32" => Ok(DataType::F32),
-            "double" | "f64" => Ok(DataType::F64),
-            "int" | "i32" => Ok(DataType::I32),
-            "long" | "i64" => Ok(DataType::I64),
+        let data_type = match &*parameters.take::<String>("data_type")? {
+            "float" | "f32" => Ok(SZ3DataType::F32),
+            "double" | "f64" => Ok(SZ3DataType::F64),
+            "int" | "i32" => Ok(SZ3DataType::I32),
+            "long" | "i64" => Ok(SZ3DataType::I64),
             other => Err(anyhow::anyhow!("unknown data type {other}")),
         }?;
 
@@ -82,12 +94,12 @@ impl Parameterizable for SZ3Compress {
 impl ProcessingNode for SZ3Compress {
     async fn pull(&self, request: Request) -> Result<Payload> {
         let input = self.input.pull(request).await?;
-        let (bytes, frame_dims, interp) =
+        let (bytes, frame_dims, inner) =
             if let Ok(frame) = self.context.ensure_cpu_buffer::<Raw>(&input) {
                 (
                     frame.storage.clone(),
                     vec![frame.interp.width as _, frame.interp.height as _],
-                    Arc::new(frame.interp) as Arc<_>,
+                    FrameInterpretations::Raw(frame.interp),
                 )
             } else {
                 let frame = self
@@ -97,42 +109,13 @@ impl ProcessingNode for SZ3Compress {
                 (
                     frame.storage.clone(),
                     vec![3, frame.interp.width as _, frame.interp.height as _],
-                    Arc::new(frame.interp) as Arc<_>,
+                    FrameInterpretations::Rgb(frame.interp),
                 )
             };
 
         let dims = self.dims.clone().unwrap_or(frame_dims);
-
-        let compressed = bytes.as_slice(|data| {
-            macro_rules! compress {
-                ($ty:ty) => {{
-                    let data: &[$ty] = bytemuck::cast_slice(data);
-                    let mut builder = sz3::DimensionedData::build(&data);
-                    let add_remainder_dim = *dims.last().unwrap() == -1;
-                    for dim in dims {
-                        if dim == -1 {
-                            break;
-                        } else {
-                            builder = builder.dim(dim as _)?;
-                        }
-                    }
-                    let data = if add_remainder_dim {
-                        builder.remainder_dim()?
-                    } else {
-                        builder.finish()?
-                    };
-
-                    anyhow::Result::<_, anyhow::Error>::Ok(sz3::compress(&data, self.error_bound)?)
-                }};
-            }
-
-            match self.data_type {
-                DataType::F64 => compress!(f64),
-                DataType::F32 => compress!(f32),
-                DataType::I64 => compress!(i64),
-                DataType::I32 => compress!(i32),
-            }
-        })?;
+        let compressed =
+            bytes.as_slice(|data| compress(data, self.data_type, &dims, self.error_bound))?;
 
         let buffer = unsafe {
             let mut buffer = self.context.get_uninit_cpu_buffer(compressed.len());
@@ -142,10 +125,189 @@ impl ProcessingNode for SZ3Compress {
             buffer
         };
 
-        let new_frame = Frame { interp: SZ3Compressed::new(interp, buffer.len()), storage: buffer };
+        let new_frame = Frame {
+            interp: SZ3Compressed {
+                inner,
+                data_type: self.data_type,
+                dims,
+                error_bound: self.error_bound,
+                compressed_size: buffer.len(),
+            },
+            storage: buffer,
+            metadata: FrameMetadata::of_payload(&input),
+        };
 
         Ok(Payload::from(new_frame))
     }
 
     fn get_caps(&self) -> Caps { self.input.get_caps() }
 }
+
+pub struct SZ3Decompress {
+    input: InputProcessingNode,
+    context: ProcessingContext,
+}
+impl Parameterizable for SZ3Decompress {
+    const DESCRIPTION: Option<&'static str> =
+        Some("decompress frames compressed by SZ3Compress back to their original interpretation");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new().with("input", Mandatory(NodeInputParameter))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::SZ3Compressed]).with_output(&[
+            FrameType::Raw,
+            FrameType::Rgb,
+            FrameType::Rgba,
+        ])
+    }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> Result<Self> {
+        Ok(Self { input: parameters.take("input")?, context: context.clone() })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for SZ3Decompress {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let input = self.input.pull(request).await?;
+        let frame = self
+            .context
+            .ensure_cpu_buffer::<SZ3Compressed>(&input)
+            .context("Wrong input format for SZ3Decompress")?;
+
+        let decompressed = frame.storage.as_slice(|data| decompress(data, frame.interp.data_type));
+        let inner = frame.interp.inner;
+        if decompressed.len() != inner.required_bytes() {
+            return Err(anyhow!(
+                "decompressed frame has {} bytes, but {} were expected for {inner:?}",
+                decompressed.len(),
+                inner.required_bytes(),
+            ));
+        }
+
+        let storage = unsafe {
+            let mut buffer = self.context.get_uninit_cpu_buffer(decompressed.len());
+            buffer.as_mut_slice(|data| data.copy_from_slice(&decompressed));
+            buffer
+        };
+        let metadata = frame.metadata.clone();
+        Ok(match inner {
+            FrameInterpretations::Raw(interp) => Payload::from(Frame { interp, storage, metadata }),
+            FrameInterpretations::Rgb(interp) => Payload::from(Frame { interp, storage, metadata }),
+            FrameInterpretations::Rgba(interp) => {
+                Payload::from(Frame { interp, storage, metadata })
+            }
+        })
+    }
+
+    fn get_caps(&self) -> Caps { self.input.get_caps() }
+}
+
+fn sz3_error_bound(error_bound: SZ3ErrorBound) -> sz3::ErrorBound {
+    match error_bound {
+        SZ3ErrorBound::Absolute(tolerance) => sz3::ErrorBound::Absolute(tolerance),
+        SZ3ErrorBound::Relative(tolerance) => sz3::ErrorBound::Relative(tolerance),
+        SZ3ErrorBound::L2Norm(tolerance) => sz3::ErrorBound::L2Norm(tolerance),
+        SZ3ErrorBound::Psnr(tolerance) => sz3::ErrorBound::PSNR(tolerance),
+    }
+}
+
+pub fn compress(
+    data: &[u8],
+    data_type: SZ3DataType,
+    dims: &[i64],
+    error_bound: SZ3ErrorBound,
+) -> Result<Vec<u8>> {
+    macro_rules! compress {
+        ($ty:ty) => {{
+            let data: &[$ty] = bytemuck::cast_slice(data);
+            let mut builder = sz3::DimensionedData::build(&data);
+            let add_remainder_dim = *dims.last().unwrap() == -1;
+            for &dim in dims {
+                if dim == -1 {
+                    break;
+                } else {
+                    builder = builder.dim(dim as _)?;
+                }
+            }
+            let data =
+                if add_remainder_dim { builder.remainder_dim()? } else { builder.finish()? };
+
+            Ok(sz3::compress(&data, sz3_error_bound(error_bound))?)
+        }};
+    }
+
+    match data_type {
+        SZ3DataType::F64 => compress!(f64),
+        SZ3DataType::F32 => compress!(f32),
+        SZ3DataType::I64 => compress!(i64),
+        SZ3DataType::I32 => compress!(i32),
+    }
+}
+
+pub fn decompress(data: &[u8], data_type: SZ3DataType) -> Vec<u8> {
+    macro_rules! decompress {
+        ($ty:ty) => {{
+            let (_config, decompressed) = sz3::decompress::<$ty, _>(data);
+            bytemuck::cast_slice(decompressed.data()).to_vec()
+        }};
+    }
+
+    match data_type {
+        SZ3DataType::F64 => decompress!(f64),
+        SZ3DataType::F32 => decompress!(f32),
+        SZ3DataType::I64 => decompress!(i64),
+        SZ3DataType::I32 => decompress!(i32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_within_error_bound() {
+        let (width, height) = (64, 48);
+        let original: Vec<f32> = (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as f32, (i / width) as f32);
+                (x * 0.1).sin() * 100.0 + y * 2.0
+            })
+            .collect();
+        let bytes: &[u8] = bytemuck::cast_slice(&original);
+
+        let tolerance = 0.01;
+        let compressed = compress(
+            bytes,
+            SZ3DataType::F32,
+            &[width as i64, height as i64],
+            SZ3ErrorBound::Absolute(tolerance),
+        )
+        .unwrap();
+        assert!(compressed.len() < bytes.len());
+
+        let decompressed = decompress(&compressed, SZ3DataType::F32);
+        let decompressed: &[f32] = bytemuck::cast_slice(&decompressed);
+        assert_eq!(decompressed.len(), original.len());
+
+        let max_error = original
+            .iter()
+            .zip(decompressed)
+            .map(|(a, b)| (a - b).abs() as f64)
+            .fold(0.0, f64::max);
+        assert!(max_error <= tolerance * 1.0001, "max error {max_error} exceeds {tolerance}");
+
+        let (min, max) =
+            original.iter().fold((f32::MAX, f32::MIN), |(min, max), v| (min.min(*v), max.max(*v)));
+        let mse =
+            original.iter().zip(decompressed).map(|(a, b)| ((a - b) as f64).powi(2)).sum::<f64>()
+                / original.len() as f64;
+        let psnr = 20.0 * ((max - min) as f64).log10() - 10.0 * mse.log10();
+        assert!(psnr > 60.0, "psnr of {psnr}dB is too low");
+    }
+}
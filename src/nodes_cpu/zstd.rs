@@ -1,31 +1,370 @@
+//! Zstd compressed recordings.
+//!
+//! `ZstdBlobWriter` writes the zstd seekable format: every chunk of frames is
+//! an independent zstd frame, and a seek table (a skippable frame with
+//! `SEEK_TABLE_MAGIC`) at the end of the file lists the compressed and
+//! decompressed size of every zstd frame. The frame interpretation is stored in
+//! a skippable frame with `HEADER_MAGIC` at the start of the file, which is
+//! listed in the seek table with a decompressed size of zero. Like the frame
+//! records of the AXIOM raw container, every chunk carries the `FrameMetadata`
+//! of its frames: a skippable frame with `METADATA_MAGIC` right before the
+//! chunk (also listed with a decompressed size of zero) contains it as a yaml
+//! list. Chunks without any metadata have no such frame. Because only
+//! skippable frames are added, these files can still be decompressed with the
+//! regular zstd tools.
+//!
+//! `ZstdBlobReader` uses the seek table for random access if it is present and
+//! otherwise falls back to decompressing a plain zstd stream sequentially.
+
 use crate::{
+    nodes_io::axiom_container::frame_parts,
     pipeline_processing::{
+        buffers::CpuBuffer,
         frame::{Frame, FrameInterpretation, FrameInterpretations},
-        node::{Caps, EOFError, NodeID, ProcessingNode, Request},
+        metadata::FrameMetadata,
+        node::{
+            Caps,
+            EOFError,
+            InputProcessingNode,
+            NodeID,
+            ProcessingNode,
+            ProgressUpdate,
+            Request,
+            SinkNode,
+        },
         parametrizable::prelude::*,
         payload::Payload,
-        processing_context::ProcessingContext,
+        processing_context::{Priority, ProcessingContext},
+        puller::pull_ordered,
     },
     util::async_notifier::AsyncNotifier,
 };
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
+use futures::{stream::FuturesOrdered, StreamExt};
 use std::{
-    io::Read,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
     sync::{Arc, Mutex},
 };
 
+const HEADER_MAGIC: u32 = 0x184D2A50;
+const METADATA_MAGIC: u32 = 0x184D2A51;
+const SEEK_TABLE_MAGIC: u32 = 0x184D2A5E;
+const SEEKABLE_MAGIC: u32 = 0x8F92EAB1;
+const SEEK_TABLE_FOOTER_LEN: u64 = 4 + 1 + 4;
+const HEADER_IDENTIFIER: &[u8; 8] = b"AXIOMZST";
+
+/// compressed and decompressed size of every zstd frame in the file
+#[derive(Debug, Default, PartialEq)]
+pub struct SeekTable(pub Vec<(u32, u32)>);
+
+impl SeekTable {
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        let frame_size = self.0.len() as u32 * 8 + SEEK_TABLE_FOOTER_LEN as u32;
+        writer.write_all(&SEEK_TABLE_MAGIC.to_le_bytes())?;
+        writer.write_all(&frame_size.to_le_bytes())?;
+        for (compressed_size, decompressed_size) in &self.0 {
+            writer.write_all(&compressed_size.to_le_bytes())?;
+            writer.write_all(&decompressed_size.to_le_bytes())?;
+        }
+        writer.write_all(&(self.0.len() as u32).to_le_bytes())?;
+        // the seek table descriptor, we don't write checksums
+        writer.write_all(&[0])?;
+        writer.write_all(&SEEKABLE_MAGIC.to_le_bytes())?;
+        Ok(())
+    }
+
+    /// Reads the seek table from the end of the file. Returns `None` if the
+    /// file doesn't end with a seek table.
+    pub fn read_from<R: Read + Seek>(reader: &mut R) -> Result<Option<Self>> {
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        if file_len < SEEK_TABLE_FOOTER_LEN + 8 {
+            return Ok(None);
+        }
+        reader.seek(SeekFrom::End(-(SEEK_TABLE_FOOTER_LEN as i64)))?;
+        let number_of_frames = read_u32(reader)? as u64;
+        let mut descriptor = [0u8];
+        reader.read_exact(&mut descriptor)?;
+        if read_u32(reader)? != SEEKABLE_MAGIC {
+            return Ok(None);
+        }
+
+        let has_checksums = descriptor[0] & 0x80 != 0;
+        let entry_len = if has_checksums { 12 } else { 8 };
+        let table_len = 8 + number_of_frames * entry_len + SEEK_TABLE_FOOTER_LEN;
+        if table_len > file_len {
+            return Err(anyhow!(
+                "seek table with {number_of_frames} entries is larger than the file"
+            ));
+        }
+        reader.seek(SeekFrom::End(-(table_len as i64)))?;
+        if read_u32(reader)? != SEEK_TABLE_MAGIC {
+            return Err(anyhow!("seek table has an invalid magic number"));
+        }
+        read_u32(reader)?;
+
+        let entries = (0..number_of_frames)
+            .map(|_| {
+                let entry = (read_u32(reader)?, read_u32(reader)?);
+                if has_checksums {
+                    read_u32(reader)?;
+                }
+                Ok(entry)
+            })
+            .collect::<Result<_>>()?;
+        Ok(Some(Self(entries)))
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Writes the skippable frame containing the interpretation and returns its
+/// length.
+pub fn write_header(writer: &mut impl Write, interp: &FrameInterpretations) -> Result<u32> {
+    let interp_yaml = serde_yaml::to_string(interp)?;
+    let content_len = (HEADER_IDENTIFIER.len() + interp_yaml.len()) as u32;
+    writer.write_all(&HEADER_MAGIC.to_le_bytes())?;
+    writer.write_all(&content_len.to_le_bytes())?;
+    writer.write_all(HEADER_IDENTIFIER)?;
+    writer.write_all(interp_yaml.as_bytes())?;
+    Ok(8 + content_len)
+}
+
+/// Reads the interpretation from the start of the file. Returns `None` if the
+/// file doesn't start with a header.
+pub fn read_header<R: Read + Seek>(reader: &mut R) -> Result<Option<FrameInterpretations>> {
+    reader.seek(SeekFrom::Start(0))?;
+    let mut start = [0u8; 16];
+    if reader.read_exact(&mut start).is_err()
+        || start[0..4] != HEADER_MAGIC.to_le_bytes()
+        || &start[8..16] != HEADER_IDENTIFIER
+    {
+        return Ok(None);
+    }
+    let content_len = u32::from_le_bytes(start[4..8].try_into().unwrap()) as usize;
+    let mut interp_yaml = vec![0u8; content_len.saturating_sub(HEADER_IDENTIFIER.len())];
+    reader.read_exact(&mut interp_yaml)?;
+    Ok(Some(
+        serde_yaml::from_slice(&interp_yaml)
+            .context("couldn't parse the frame interpretation of the zstd file")?,
+    ))
+}
+
+/// Writes the skippable frame with the metadata of the frames of the following
+/// chunk and returns its length.
+pub fn write_metadata(writer: &mut impl Write, metadata: &[FrameMetadata]) -> Result<u32> {
+    let metadata_yaml = serde_yaml::to_string(metadata)?;
+    writer.write_all(&METADATA_MAGIC.to_le_bytes())?;
+    writer.write_all(&(metadata_yaml.len() as u32).to_le_bytes())?;
+    writer.write_all(metadata_yaml.as_bytes())?;
+    Ok(8 + metadata_yaml.len() as u32)
+}
+
+fn parse_metadata(skippable_frame: &[u8], frame_count: u64) -> Result<Vec<FrameMetadata>> {
+    if skippable_frame.len() < 8 || skippable_frame[0..4] != METADATA_MAGIC.to_le_bytes() {
+        return Err(anyhow!("expected the metadata of a chunk, but found another frame"));
+    }
+    let metadata: Vec<FrameMetadata> = serde_yaml::from_slice(&skippable_frame[8..])
+        .context("couldn't parse the frame metadata of the zstd file")?;
+    if metadata.len() as u64 != frame_count {
+        return Err(anyhow!(
+            "the metadata of a chunk with {frame_count} frames has {} entries",
+            metadata.len()
+        ));
+    }
+    Ok(metadata)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Chunk {
+    offset: u64,
+    compressed_size: usize,
+    /// the size of the skippable frame with the metadata before the chunk, zero
+    /// if there is none
+    metadata_size: usize,
+    first_frame: u64,
+    frame_count: u64,
+}
+
+struct DecompressedChunk {
+    data: Vec<u8>,
+    metadata: Vec<FrameMetadata>,
+}
+
+struct SeekableZstd {
+    file: Mutex<BufReader<File>>,
+    chunks: Vec<Chunk>,
+    frame_count: u64,
+    // the last decompressed chunk, so that reading sequentially through chunks with
+    // multiple frames doesn't decompress every chunk multiple times
+    last_chunk: Mutex<Option<(usize, Arc<DecompressedChunk>)>>,
+}
+
+impl SeekableZstd {
+    fn new(file: BufReader<File>, seek_table: SeekTable, frame_bytes: usize) -> Result<Self> {
+        let mut chunks = Vec::new();
+        let mut offset = 0;
+        let mut frame_count = 0;
+        let mut metadata_size = 0;
+        for (compressed_size, decompressed_size) in seek_table.0 {
+            if decompressed_size as usize % frame_bytes != 0 {
+                return Err(anyhow!(
+                    "zstd frame at offset {offset} contains {decompressed_size} bytes, which is \
+                     not a multiple of the frame size {frame_bytes}"
+                ));
+            }
+            // skippable frames like the header decompress to nothing
+            if decompressed_size > 0 {
+                let chunk_frames = (decompressed_size as usize / frame_bytes) as u64;
+                chunks.push(Chunk {
+                    offset,
+                    compressed_size: compressed_size as usize,
+                    metadata_size: std::mem::take(&mut metadata_size),
+                    first_frame: frame_count,
+                    frame_count: chunk_frames,
+                });
+                frame_count += chunk_frames;
+            } else if offset > 0 {
+                // every skippable frame after the header holds the metadata of the next
+                // chunk
+                metadata_size = compressed_size as usize;
+            }
+            offset += compressed_size as u64;
+        }
+
+        Ok(Self { file: Mutex::new(file), chunks, frame_count, last_chunk: Mutex::new(None) })
+    }
+
+    fn read_frame(&self, frame_number: u64, buffer: &mut [u8]) -> Result<FrameMetadata> {
+        if frame_number >= self.frame_count {
+            return Err(anyhow!(
+                "frame {} was requested but this stream only has a length of {}",
+                frame_number,
+                self.frame_count
+            ))
+            .context(EOFError);
+        }
+        let chunk_index =
+            self.chunks.partition_point(|chunk| chunk.first_frame <= frame_number) - 1;
+        let chunk = self.chunks[chunk_index];
+
+        let cached = match &*self.last_chunk.lock().unwrap() {
+            Some((index, decompressed)) if *index == chunk_index => Some(decompressed.clone()),
+            _ => None,
+        };
+        let decompressed = match cached {
+            Some(decompressed) => decompressed,
+            None => {
+                // the metadata is right before the chunk, so both are read at once
+                let mut read = vec![0u8; chunk.metadata_size + chunk.compressed_size];
+                {
+                    let mut file = self.file.lock().unwrap();
+                    file.seek(SeekFrom::Start(chunk.offset - chunk.metadata_size as u64))?;
+                    file.read_exact(&mut read).context("error while reading file")?;
+                }
+                let (metadata, compressed) = read.split_at(chunk.metadata_size);
+                let metadata = if metadata.is_empty() {
+                    vec![FrameMetadata::default(); chunk.frame_count as usize]
+                } else {
+                    parse_metadata(metadata, chunk.frame_count)?
+                };
+                // the decompression happens outside of the lock, so multiple frames
+                // can be decompressed in parallel
+                let data =
+                    zstd::bulk::decompress(compressed, chunk.frame_count as usize * buffer.len())?;
+                let decompressed = Arc::new(DecompressedChunk { data, metadata });
+                *self.last_chunk.lock().unwrap() = Some((chunk_index, decompressed.clone()));
+                decompressed
+            }
+        };
+
+        let index = (frame_number - chunk.first_frame) as usize;
+        let start = index * buffer.len();
+        buffer.copy_from_slice(&decompressed.data[start..start + buffer.len()]);
+        Ok(decompressed.metadata[index].clone())
+    }
+}
+
+type Decoder = zstd::stream::read::Decoder<'static, BufReader<File>>;
+
+/// A plain zstd stream, which can only be decompressed from the start.
+struct SequentialZstd {
+    /// the number of the next frame in the stream, it only changes while
+    /// `decoder` is locked
+    next_frame: AsyncNotifier<u64>,
+    /// the decoder and the frame it decompressed last, which is kept for
+    /// pullers that request the same frame number at the same time
+    decoder: Mutex<(Decoder, Option<(u64, Vec<u8>)>)>,
+}
+
+impl SequentialZstd {
+    fn new(decoder: Decoder) -> Self {
+        Self { next_frame: AsyncNotifier::new(0), decoder: Mutex::new((decoder, None)) }
+    }
+
+    async fn wait_for(&self, frame_number: u64) {
+        self.next_frame.wait(move |next_frame| *next_frame >= frame_number).await;
+    }
+
+    /// Decompresses the frame after `wait_for` returned for it.
+    fn read_frame(&self, frame_number: u64, buffer: &mut [u8]) -> Result<()> {
+        let mut decoder = self.decoder.lock().unwrap();
+        let (decoder, last_frame) = &mut *decoder;
+        if let Some((last_frame_number, data)) = last_frame {
+            if *last_frame_number == frame_number {
+                buffer.copy_from_slice(data);
+                return Ok(());
+            }
+        }
+
+        let next_frame = self.next_frame.update(|next_frame| *next_frame);
+        if next_frame != frame_number {
+            return Err(anyhow!(
+                "frame {frame_number} was requested, but the zstd stream without a seek table is \
+                 already at frame {next_frame}"
+            ));
+        }
+        let read = decoder.read_exact(buffer);
+        // also at the end of the stream, so that the pullers waiting for the next
+        // frames get their EOFError
+        self.next_frame.update(|next_frame| *next_frame = frame_number + 1);
+        read.context(EOFError)?;
+        *last_frame = Some((frame_number, buffer.to_vec()));
+        Ok(())
+    }
+}
+
+enum ZstdSource {
+    Seekable(SeekableZstd),
+    Sequential(SequentialZstd),
+}
+
 pub struct ZstdBlobReader {
-    frame_and_file: AsyncNotifier<(
-        u64,
-        Arc<Mutex<zstd::stream::read::Decoder<'static, std::io::BufReader<std::fs::File>>>>,
-    )>,
+    source: ZstdSource,
     interp: FrameInterpretations,
     context: ProcessingContext,
 }
 impl Parameterizable for ZstdBlobReader {
+    const DESCRIPTION: Option<&'static str> = Some(
+        "read zstd compressed frames. Files written by ZstdBlobWriter contain their \
+         interpretation and allow random access, for plain zstd streams the interpretation has to \
+         be given",
+    );
+
     fn describe_parameters() -> ParametersDescriptor {
-        ParametersDescriptor::new().with_interpretation().with("file", Mandatory(StringParameter))
+        ParametersDescriptor::new()
+            .with_interpretation()
+            .with("width", Optional(NaturalWithZero()))
+            .with("height", Optional(NaturalWithZero()))
+            .with("file", Mandatory(PathParameter(PathCheck::Exists)))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_output(&[FrameType::Raw, FrameType::Rgb, FrameType::Rgba])
     }
     fn from_parameters(
         mut options: Parameters,
@@ -36,14 +375,28 @@ impl Parameterizable for ZstdBlobReader {
         Self: Sized,
     {
         let path: String = options.take("file")?;
-        let file = std::fs::File::open(path)?;
+        let mut file = BufReader::new(File::open(path)?);
+
+        if let (Some(interp), Some(seek_table)) =
+            (read_header(&mut file)?, SeekTable::read_from(&mut file)?)
+        {
+            let source = SeekableZstd::new(file, seek_table, interp.required_bytes())?;
+            return Ok(Self {
+                source: ZstdSource::Seekable(source),
+                interp,
+                context: context.clone(),
+            });
+        }
 
         let interp = options.get_interpretation()?;
+        if interp.required_bytes() == 0 {
+            return Err(anyhow!(
+                "the zstd file has no seek table, so width and height have to be specified"
+            ));
+        }
+        file.seek(SeekFrom::Start(0))?;
         Ok(Self {
-            frame_and_file: AsyncNotifier::new((
-                0,
-                Arc::new(Mutex::new(zstd::stream::read::Decoder::new(file)?)),
-            )),
+            source: ZstdSource::Sequential(SequentialZstd::new(Decoder::with_buffer(file)?)),
             interp,
             context: context.clone(),
         })
@@ -53,34 +406,311 @@ impl Parameterizable for ZstdBlobReader {
 #[async_trait]
 impl ProcessingNode for ZstdBlobReader {
     async fn pull(&self, request: Request) -> Result<Payload> {
-        // TODO(robin): this is probably unsafe, because we can get multiple threads
-        // waiting for the same frame_number, so then one will acquire the mutex
-        // and the other will wait, but produce a frame for the same frame
-        // number with different data, because it acquires the mutex after the
-        // first thread is done
         let frame_number = request.frame_number();
-        let (_, decoder) =
-            self.frame_and_file.wait(move |(frame_no, _)| *frame_no == frame_number).await;
-        let mut decoder = decoder.lock().unwrap();
-
         let mut buffer =
             unsafe { self.context.get_uninit_cpu_buffer(self.interp.required_bytes()) };
-        // dbg!(self.interp.required_bytes());
-        buffer.as_mut_slice(move |buffer| {
-            decoder.read_exact(buffer).context(EOFError)?;
-            // dbg!(buffer[0], buffer[1], buffer[2]);
-            anyhow::Result::<_, anyhow::Error>::Ok(())
-        })?;
-        self.frame_and_file.update(|(frame_no, _)| *frame_no = frame_number + 1);
 
+        let metadata = match &self.source {
+            ZstdSource::Seekable(source) => {
+                buffer.as_mut_slice(|buffer| source.read_frame(frame_number, buffer))?
+            }
+            ZstdSource::Sequential(source) => {
+                source.wait_for(frame_number).await;
+                buffer.as_mut_slice(|buffer| source.read_frame(frame_number, buffer))?;
+                FrameMetadata::default()
+            }
+        };
         let payload = match self.interp {
-            FrameInterpretations::Raw(interp) => Payload::from(Frame { storage: buffer, interp }),
-            FrameInterpretations::Rgb(interp) => Payload::from(Frame { storage: buffer, interp }),
-            FrameInterpretations::Rgba(interp) => Payload::from(Frame { storage: buffer, interp }),
+            FrameInterpretations::Raw(interp) => {
+                Payload::from(Frame { storage: buffer, interp, metadata })
+            }
+            FrameInterpretations::Rgb(interp) => {
+                Payload::from(Frame { storage: buffer, interp, metadata })
+            }
+            FrameInterpretations::Rgba(interp) => {
+                Payload::from(Frame { storage: buffer, interp, metadata })
+            }
         };
 
         Ok(payload)
     }
 
-    fn get_caps(&self) -> Caps { Caps { frame_count: None, random_access: false } }
+    fn get_caps(&self) -> Caps {
+        match &self.source {
+            ZstdSource::Seekable(source) => {
+                Caps { frame_count: Some(source.frame_count), random_access: true }
+            }
+            ZstdSource::Sequential(_) => Caps { frame_count: None, random_access: false },
+        }
+    }
+}
+
+pub struct ZstdBlobWriter {
+    file: Mutex<Option<File>>,
+    input: InputProcessingNode,
+    number_of_frames: u64,
+    priority: u8,
+    level: i32,
+    frames_per_chunk: usize,
+}
+impl Parameterizable for ZstdBlobWriter {
+    const DESCRIPTION: Option<&'static str> = Some(
+        "writes zstd compressed frames with a seek table, so that they can be read with random \
+         access by ZstdBlobReader",
+    );
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("path", Mandatory(PathParameter(PathCheck::Any)))
+            .with("input", Mandatory(NodeInputParameter))
+            .with("priority", Optional(U8()))
+            .with("number-of-frames", Optional(NaturalWithZero()))
+            .with("level", WithDefault(IntRange(1, 22), IntRangeValue(3)))
+            .with("frames-per-chunk", WithDefault(NaturalGreaterZero(), IntRangeValue(1)))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new()
+            .with_input("input", &[FrameType::Raw, FrameType::Rgb, FrameType::Rgba])
+            .sink()
+    }
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        _context: &ProcessingContext,
+    ) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            file: Mutex::new(Some(File::create(parameters.take::<String>("path")?)?)),
+            input: parameters.take("input")?,
+            number_of_frames: parameters.take("number-of-frames")?,
+            priority: parameters.take("priority")?,
+            level: parameters.take::<i64>("level")? as i32,
+            frames_per_chunk: parameters.take("frames-per-chunk")?,
+        })
+    }
+}
+
+#[async_trait]
+impl SinkNode for ZstdBlobWriter {
+    async fn run(
+        &self,
+        context: &ProcessingContext,
+        progress_callback: Arc<dyn Fn(ProgressUpdate) + Send + Sync>,
+    ) -> Result<()> {
+        let file = self
+            .file
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| anyhow!("ZstdBlobWriter can only be run once"))?;
+        let mut file = BufWriter::new(file);
+
+        let rx = pull_ordered(
+            context,
+            self.priority,
+            progress_callback,
+            self.input.clone_for_same_puller(),
+            self.number_of_frames,
+        );
+
+        let mut interp = None;
+        let mut seek_table = SeekTable::default();
+        let mut chunk = Vec::new();
+        let mut chunk_metadata = Vec::new();
+        let mut chunk_number = 0;
+        let mut compressing = FuturesOrdered::new();
+        loop {
+            let payload = rx.recv_async().await.ok();
+            if let Some(payload) = &payload {
+                let (frame_interp, buffer, metadata) = frame_parts(context, payload)?;
+                match interp {
+                    None => {
+                        check_chunk_size(buffer.len(), self.frames_per_chunk)?;
+                        // the header needs the interpretation, so it is written with the first
+                        // frame
+                        let header_len = write_header(&mut file, &frame_interp)?;
+                        seek_table.0.push((header_len, 0));
+                        interp = Some(frame_interp);
+                    }
+                    Some(interp) if interp != frame_interp => {
+                        return Err(anyhow!(
+                            "all frames of a zstd file need the same interpretation, but got \
+                             {frame_interp:?} after {interp:?}"
+                        ))
+                    }
+                    _ => {}
+                }
+                chunk.push(buffer);
+                chunk_metadata.push(metadata);
+            }
+
+            if chunk.len() == self.frames_per_chunk || (payload.is_none() && !chunk.is_empty()) {
+                let level = self.level;
+                let frames = std::mem::take(&mut chunk);
+                let metadata = std::mem::take(&mut chunk_metadata);
+                compressing.push_back(context.spawn(
+                    Priority::new(self.priority, chunk_number),
+                    async move {
+                        compress_chunk(&frames, level).map(|compressed| (compressed, metadata))
+                    },
+                ));
+                chunk_number += 1;
+            }
+
+            if payload.is_none() || compressing.len() >= context.num_threads() {
+                if let Some(compressed) = compressing.next().await {
+                    let ((compressed, decompressed_size), metadata) = compressed?;
+                    if metadata.iter().any(|metadata| !metadata.is_empty()) {
+                        seek_table.0.push((write_metadata(&mut file, &metadata)?, 0));
+                    }
+                    // compressing can make incompressible data a bit larger
+                    let too_large = || "a chunk doesn't fit into the seek table";
+                    let sizes = (
+                        u32::try_from(compressed.len()).with_context(too_large)?,
+                        u32::try_from(decompressed_size).with_context(too_large)?,
+                    );
+                    file.write_all(&compressed)?;
+                    seek_table.0.push(sizes);
+                }
+            }
+            if payload.is_none() && compressing.is_empty() {
+                break;
+            }
+        }
+
+        seek_table.write_to(&mut file)?;
+        file.flush()?;
+
+        Ok(())
+    }
+}
+
+/// The seek table stores the sizes of the chunks as u32, so a chunk can't have
+/// more than 4 GiB.
+fn check_chunk_size(frame_size: usize, frames_per_chunk: usize) -> Result<()> {
+    let max_frames = u32::MAX as usize / frame_size.max(1);
+    if frames_per_chunk > max_frames {
+        return Err(anyhow!(
+            "{frames_per_chunk} frames of {frame_size} bytes don't fit into a chunk of the seek \
+             table, frames-per-chunk can be at most {max_frames} for these frames"
+        ));
+    }
+    Ok(())
+}
+
+/// Compresses the frames into a single zstd frame and returns it together with
+/// its decompressed size.
+fn compress_chunk(frames: &[CpuBuffer], level: i32) -> std::io::Result<(Vec<u8>, usize)> {
+    let mut data = Vec::with_capacity(frames.iter().map(|frame| frame.len()).sum());
+    for frame in frames {
+        frame.as_slice(|slice| data.extend_from_slice(slice));
+    }
+    Ok((zstd::bulk::compress(&data, level)?, data.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline_processing::frame::Rgb;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_seek_table_roundtrip() {
+        let interp = FrameInterpretations::Rgb(Rgb { width: 4, height: 4, fps: 24.0 });
+        let frames: Vec<Vec<u8>> = (0..5u8).map(|i| vec![i; interp.required_bytes()]).collect();
+
+        let mut file = Cursor::new(Vec::new());
+        let mut seek_table = SeekTable::default();
+        seek_table.0.push((write_header(&mut file, &interp).unwrap(), 0));
+        for chunk in frames.chunks(2) {
+            let data = chunk.concat();
+            let compressed = zstd::bulk::compress(&data, 3).unwrap();
+            file.write_all(&compressed).unwrap();
+            seek_table.0.push((compressed.len() as u32, data.len() as u32));
+        }
+        seek_table.write_to(&mut file).unwrap();
+
+        assert_eq!(read_header(&mut file).unwrap(), Some(interp));
+        assert_eq!(SeekTable::read_from(&mut file).unwrap(), Some(seek_table));
+
+        // the skippable frames are ignored by regular zstd decoders
+        let decompressed = zstd::stream::decode_all(&file.get_ref()[..]).unwrap();
+        assert_eq!(decompressed, frames.concat());
+
+        let plain = Cursor::new(zstd::bulk::compress(&frames.concat(), 3).unwrap());
+        assert_eq!(SeekTable::read_from(&mut plain.clone()).unwrap(), None);
+        assert_eq!(read_header(&mut plain.clone()).unwrap(), None);
+    }
+
+    fn temp_file(name: &str, content: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("{name}-{}.zst", std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_chunk_size() {
+        // 4096x3072 12 bit frames
+        let frame_size = 4096 * 3072 * 12 / 8;
+        assert!(check_chunk_size(frame_size, 227).is_ok());
+        assert!(check_chunk_size(frame_size, 228).is_err());
+    }
+
+    #[test]
+    fn test_metadata_roundtrip() {
+        use crate::pipeline_processing::metadata::SensorSequence;
+
+        let interp = FrameInterpretations::Rgb(Rgb { width: 2, height: 2, fps: 24.0 });
+        let frame_bytes = interp.required_bytes();
+        let mut file = Cursor::new(Vec::new());
+        let mut seek_table = SeekTable::default();
+        seek_table.0.push((write_header(&mut file, &interp).unwrap(), 0));
+        // a chunk with metadata followed by one without
+        let metadata = [FrameMetadata::new().with(SensorSequence(7)), FrameMetadata::new()];
+        seek_table.0.push((write_metadata(&mut file, &metadata).unwrap(), 0));
+        for data in [[vec![1; frame_bytes], vec![2; frame_bytes]].concat(), vec![3; frame_bytes]] {
+            let compressed = zstd::bulk::compress(&data, 3).unwrap();
+            file.write_all(&compressed).unwrap();
+            seek_table.0.push((compressed.len() as u32, data.len() as u32));
+        }
+        seek_table.write_to(&mut file).unwrap();
+
+        let path = temp_file("zstd-metadata", file.get_ref());
+        let mut reader = BufReader::new(File::open(&path).unwrap());
+        let seek_table = SeekTable::read_from(&mut reader).unwrap().unwrap();
+        let zstd = SeekableZstd::new(reader, seek_table, frame_bytes).unwrap();
+        assert_eq!(zstd.frame_count, 3);
+
+        let mut buffer = vec![0; frame_bytes];
+        for (frame_number, expected) in [(2, FrameMetadata::new()), (0, metadata[0].clone())] {
+            assert_eq!(zstd.read_frame(frame_number, &mut buffer).unwrap(), expected);
+            assert_eq!(buffer, vec![frame_number as u8 + 1; frame_bytes]);
+        }
+        assert_eq!(zstd.read_frame(1, &mut buffer).unwrap().get::<SensorSequence>(), None);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_sequential_same_frame() {
+        let frames: Vec<u8> = (0..4).flat_map(|i| [i; 3]).collect();
+        let path = temp_file("zstd-sequential", &zstd::bulk::compress(&frames, 3).unwrap());
+        let file = BufReader::new(File::open(&path).unwrap());
+        let zstd = SequentialZstd::new(Decoder::with_buffer(file).unwrap());
+
+        let mut buffer = [0; 3];
+        futures::executor::block_on(async {
+            for frame_number in [0, 0, 1, 1, 2] {
+                zstd.wait_for(frame_number).await;
+                zstd.read_frame(frame_number, &mut buffer).unwrap();
+                assert_eq!(buffer, [frame_number as u8; 3]);
+            }
+        });
+        // frames that were already passed can't be read again
+        assert!(zstd.read_frame(0, &mut buffer).is_err());
+        zstd.read_frame(3, &mut buffer).unwrap();
+        assert!(zstd.read_frame(4, &mut buffer).unwrap_err().is::<EOFError>());
+        std::fs::remove_file(path).unwrap();
+    }
 }
@@ -40,6 +40,9 @@ impl Parameterizable for GpuBitDepthConverter {
     fn describe_parameters() -> ParametersDescriptor {
         ParametersDescriptor::new().with("input", Mandatory(NodeInputParameter))
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).with_output(&[FrameType::Raw])
+    }
     fn from_parameters(
         mut parameters: Parameters,
         _is_input_to: &[NodeID],
@@ -79,7 +82,7 @@ impl ProcessingNode for GpuBitDepthConverter {
             ));
         }
 
-        let interp = Raw { bit_depth: 8, ..frame.interp };
+        let interp = frame.interp.with_shifted_bit_depth(8);
         let sink_buffer = DeviceLocalBuffer::<[u8]>::array(
             self.device.clone(),
             interp.required_bytes() as DeviceSize,
@@ -124,7 +127,11 @@ impl ProcessingNode for GpuBitDepthConverter {
             fut.then_execute(self.queue.clone(), command_buffer)?.then_signal_fence_and_flush()?;
 
         future.wait(None).unwrap();
-        Ok(Payload::from(Frame { interp, storage: GpuBuffer::from(sink_buffer) }))
+        Ok(Payload::from(Frame {
+            interp,
+            storage: GpuBuffer::from(sink_buffer),
+            metadata: frame.metadata.clone(),
+        }))
     }
 
     fn get_caps(&self) -> Caps { self.input.get_caps() }
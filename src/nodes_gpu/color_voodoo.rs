@@ -2,6 +2,7 @@ use crate::pipeline_processing::{
     buffers::GpuBuffer,
     frame::{Frame, FrameInterpretation, Rgb},
     gpu_util::ensure_gpu_buffer,
+    live_parameters::LiveParameter,
     node::{Caps, InputProcessingNode, NodeID, ProcessingNode, Request},
     parametrizable::prelude::*,
     payload::Payload,
@@ -34,9 +35,9 @@ pub struct ColorVoodoo {
     pipeline: Arc<ComputePipeline>,
     queue: Arc<Queue>,
     input: InputProcessingNode,
-    pedestal: u8,
-    s_gamma: f64,
-    v_gamma: f64,
+    pedestal: LiveParameter<u64>,
+    s_gamma: LiveParameter<f64>,
+    v_gamma: LiveParameter<f64>,
 }
 
 impl Parameterizable for ColorVoodoo {
@@ -47,6 +48,9 @@ impl Parameterizable for ColorVoodoo {
             .with("s_gamma", WithDefault(FloatRange(0.0, 100.0), FloatRangeValue(1.0)))
             .with("v_gamma", WithDefault(FloatRange(0.0, 100.0), FloatRangeValue(1.0)))
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Rgb]).with_output(&[FrameType::Rgb])
+    }
     fn from_parameters(
         mut parameters: Parameters,
         _is_input_to: &[NodeID],
@@ -73,9 +77,9 @@ impl Parameterizable for ColorVoodoo {
             pipeline,
             queue,
             input: parameters.take("input")?,
-            pedestal: parameters.take::<u64>("pedestal")? as u8,
-            s_gamma: parameters.take("s_gamma")?,
-            v_gamma: parameters.take("v_gamma")?,
+            pedestal: parameters.take_live("pedestal", context)?,
+            s_gamma: parameters.take_live("s_gamma", context)?,
+            v_gamma: parameters.take_live("v_gamma", context)?,
         })
     }
 }
@@ -96,9 +100,9 @@ impl ProcessingNode for ColorVoodoo {
         )?;
 
         let push_constants = compute_shader::ty::PushConstantData {
-            pedestal: self.pedestal as f32,
-            s_gamma: self.s_gamma as f32,
-            v_gamma: self.v_gamma as f32,
+            pedestal: self.pedestal.get() as f32,
+            s_gamma: self.s_gamma.get() as f32,
+            v_gamma: self.v_gamma.get() as f32,
             width: frame.interp.width as _,
             height: frame.interp.height as _,
         };
@@ -139,7 +143,11 @@ impl ProcessingNode for ColorVoodoo {
             fut.then_execute(self.queue.clone(), command_buffer)?.then_signal_fence_and_flush()?;
 
         future.wait(None).unwrap();
-        Ok(Payload::from(Frame { interp: frame.interp, storage: GpuBuffer::from(sink_buffer) }))
+        Ok(Payload::from(Frame {
+            interp: frame.interp,
+            storage: GpuBuffer::from(sink_buffer),
+            metadata: frame.metadata.clone(),
+        }))
     }
 
     fn get_caps(&self) -> Caps { self.input.get_caps() }
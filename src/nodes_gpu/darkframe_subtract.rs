@@ -1,11 +1,14 @@
-use crate::pipeline_processing::{
-    buffers::GpuBuffer,
-    frame::{Frame, FrameInterpretation, Raw},
-    gpu_util::ensure_gpu_buffer,
-    node::{Caps, InputProcessingNode, NodeID, ProcessingNode, Request},
-    parametrizable::prelude::*,
-    payload::Payload,
-    processing_context::ProcessingContext,
+use crate::{
+    nodes_cpu::darkframe_subtract::subtracted_interp,
+    pipeline_processing::{
+        buffers::GpuBuffer,
+        frame::{Frame, FrameInterpretation, Raw},
+        gpu_util::ensure_gpu_buffer,
+        node::{Caps, InputProcessingNode, NodeID, ProcessingNode, Request},
+        parametrizable::prelude::*,
+        payload::Payload,
+        processing_context::ProcessingContext,
+    },
 };
 use anyhow::{Context, Result};
 use async_trait::async_trait;
@@ -38,16 +41,20 @@ pub struct DarkframeSubtract {
     input: InputProcessingNode,
     darkframe_view: Arc<dyn ImageViewAbstract>,
     darkframe_sampler: Arc<Sampler>,
+    darkframe_max: f32,
 }
 
 impl Parameterizable for DarkframeSubtract {
     fn describe_parameters() -> ParametersDescriptor {
         ParametersDescriptor::new()
             .with("input", Mandatory(NodeInputParameter))
-            .with("darkframe", Mandatory(StringParameter))
+            .with("darkframe", Mandatory(PathParameter(PathCheck::Exists)))
             .with("width", Mandatory(IntRange(0, i64::MAX)))
             .with("height", Mandatory(IntRange(0, i64::MAX)))
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).with_output(&[FrameType::Raw])
+    }
 
     fn from_parameters(
         mut parameters: Parameters,
@@ -71,6 +78,10 @@ impl Parameterizable for DarkframeSubtract {
         .unwrap();
 
         let darkframe = std::fs::read(parameters.take::<String>("darkframe")?)?;
+        let darkframe_max = darkframe
+            .chunks_exact(4)
+            .map(|v| f32::from_le_bytes([v[0], v[1], v[2], v[3]]))
+            .fold(f32::MIN, f32::max);
 
         let (darkframe_image, _image_fut) = ImmutableImage::from_iter(
             darkframe.into_iter(),
@@ -106,6 +117,7 @@ impl Parameterizable for DarkframeSubtract {
             input: parameters.take("input")?,
             darkframe_view: ImageView::new_default(darkframe_image).unwrap(),
             darkframe_sampler,
+            darkframe_max,
         })
     }
 }
@@ -176,7 +188,12 @@ impl ProcessingNode for DarkframeSubtract {
             fut.then_execute(self.queue.clone(), command_buffer)?.then_signal_fence_and_flush()?;
 
         future.wait(None).unwrap();
-        Ok(Payload::from(Frame { interp: frame.interp, storage: GpuBuffer::from(sink_buffer) }))
+        let interp = subtracted_interp(frame.interp, self.darkframe_max);
+        Ok(Payload::from(Frame {
+            interp,
+            storage: GpuBuffer::from(sink_buffer),
+            metadata: frame.metadata.clone(),
+        }))
     }
 
     fn get_caps(&self) -> Caps { self.input.get_caps() }
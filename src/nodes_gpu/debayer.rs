@@ -40,6 +40,9 @@ impl Parameterizable for Debayer {
     fn describe_parameters() -> ParametersDescriptor {
         ParametersDescriptor::default().with("input", Mandatory(NodeInputParameter))
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).with_output(&[FrameType::Rgb])
+    }
     fn from_parameters(
         mut parameters: Parameters,
         _is_input_to: &[NodeID],
@@ -136,7 +139,11 @@ impl ProcessingNode for Debayer {
             fut.then_execute(self.queue.clone(), command_buffer)?.then_signal_fence_and_flush()?;
 
         future.wait(None).unwrap();
-        Ok(Payload::from(Frame { interp, storage: GpuBuffer::from(sink_buffer) }))
+        Ok(Payload::from(Frame {
+            interp,
+            storage: GpuBuffer::from(sink_buffer),
+            metadata: frame.metadata.clone(),
+        }))
     }
 
     fn get_caps(&self) -> Caps { self.input.get_caps() }
@@ -1,6 +1,7 @@
 use crate::pipeline_processing::{
     frame::Rgb,
     gpu_util::ensure_gpu_buffer,
+    live_parameters::LiveParameter,
     node::{InputProcessingNode, NodeID, ProgressUpdate, SinkNode},
     parametrizable::prelude::*,
     processing_context::ProcessingContext,
@@ -130,8 +131,8 @@ mod fragment_shader {
 
 pub struct Display {
     mailbox: bool,
-    live: bool,
-    fullscreen: bool,
+    live: LiveParameter<bool>,
+    fullscreen: LiveParameter<bool>,
     // TODO(robin): readd handling for this
     do_loop: bool,
     input: InputProcessingNode,
@@ -148,17 +149,20 @@ impl Parameterizable for Display {
             .with("priority", Optional(U8()))
             .with("fullscreen", Optional(BoolParameter))
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Rgb]).sink()
+    }
 
     fn from_parameters(
         mut parameters: Parameters,
         _is_input_to: &[NodeID],
-        _context: &ProcessingContext,
+        context: &ProcessingContext,
     ) -> Result<Self> {
         Ok(Self {
             mailbox: parameters.take("mailbox")?,
-            live: parameters.take("live")?,
+            live: parameters.take_live("live", context)?,
             do_loop: parameters.take("loop")?,
-            fullscreen: parameters.take("fullscreen")?,
+            fullscreen: parameters.take_live("fullscreen", context)?,
             input: parameters.take("input")?,
             priority: parameters.take("priority")?,
         })
@@ -182,16 +186,19 @@ impl SinkNode for Display {
         let (tx, rx_winit) = flume::bounded(1);
 
         let context = context.clone();
-        let live = self.live;
+        let live = self.live.clone();
         let mailbox = self.mailbox;
-        let fullscreen = self.fullscreen;
+        let fullscreen = self.fullscreen.clone();
         std::thread::spawn(move || {
             let (device, queues) = context.require_vulkan().unwrap();
 
             let event_loop = EventLoop::new_any_thread();
+            let fullscreen_mode =
+                |enabled| if enabled { Some(Fullscreen::Borderless(None)) } else { None };
+            let (mut fullscreen_version, is_fullscreen) = fullscreen.get_versioned();
             let surface = WindowBuilder::new()
                 .with_title("axiom converter vulkan output")
-                .with_fullscreen(if fullscreen { Some(Fullscreen::Borderless(None)) } else { None })
+                .with_fullscreen(fullscreen_mode(is_fullscreen))
                 .build_vk_surface(&event_loop, device.instance().clone())
                 .unwrap();
             let queue = queues
@@ -285,6 +292,13 @@ impl SinkNode for Display {
                 }
                 Event::RedrawEventsCleared => {
                     previous_frame_end.as_mut().unwrap().cleanup_finished();
+                    // fullscreen can be switched while running, resizing the window recreates
+                    // the swapchain
+                    let (version, is_fullscreen) = fullscreen.get_versioned();
+                    if version != fullscreen_version {
+                        surface.window().set_fullscreen(fullscreen_mode(is_fullscreen));
+                        fullscreen_version = version;
+                    }
                     if recreate_swapchain {
                         dimensions = surface.window().inner_size().into();
                         let (new_swapchain, new_images) =
@@ -308,7 +322,7 @@ impl SinkNode for Display {
                     }
 
                     let now = Instant::now();
-                    let needs_new_frame = live || now > next_frame_time;
+                    let needs_new_frame = live.get() || now > next_frame_time;
 
                     if needs_new_frame {
                         match rx_winit.recv() {
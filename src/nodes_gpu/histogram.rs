@@ -39,6 +39,9 @@ impl Parameterizable for Histogram {
     fn describe_parameters() -> ParametersDescriptor {
         ParametersDescriptor::new().with("input", Mandatory(NodeInputParameter))
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).with_output(&[FrameType::Raw])
+    }
 
     fn from_parameters(
         mut parameters: Parameters,
@@ -139,8 +142,11 @@ impl ProcessingNode for Histogram {
                 bit_depth: 32,
                 cfa: frame.interp.cfa,
                 fps: frame.interp.fps,
+                black_level: [0; 4],
+                white_level: Raw::default_white_level(32),
             },
             storage: GpuBuffer::from(sink_buffer),
+            metadata: frame.metadata.clone(),
         }))
     }
 
@@ -2,6 +2,7 @@ use crate::pipeline_processing::{
     buffers::GpuBuffer,
     frame::{Frame, FrameInterpretation, Rgb},
     gpu_util::ensure_gpu_buffer,
+    live_parameters::LiveParameter,
     node::{Caps, InputProcessingNode, NodeID, ProcessingNode, Request},
     parametrizable::prelude::*,
     payload::Payload,
@@ -9,6 +10,7 @@ use crate::pipeline_processing::{
 };
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
+use parking_lot::Mutex;
 use std::{
     fs::File,
     io::{BufReader, Read},
@@ -40,7 +42,9 @@ pub struct Lut3d {
     pipeline: Arc<ComputePipeline>,
     queue: Arc<Queue>,
     input: InputProcessingNode,
-    lut_image_view: Arc<dyn ImageViewAbstract>,
+    file: LiveParameter<String>,
+    /// the lut with the version of `file` it was read from
+    lut_image_view: Mutex<(u64, Arc<dyn ImageViewAbstract>)>,
     lut_sampler: Arc<Sampler>,
 }
 
@@ -48,7 +52,10 @@ impl Parameterizable for Lut3d {
     fn describe_parameters() -> ParametersDescriptor {
         ParametersDescriptor::new()
             .with("input", Mandatory(NodeInputParameter))
-            .with("file", Mandatory(StringParameter))
+            .with("file", Mandatory(PathParameter(PathCheck::Exists)))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Rgb]).with_output(&[FrameType::Rgb])
     }
 
     fn from_parameters(
@@ -72,7 +79,13 @@ impl Parameterizable for Lut3d {
         )
         .unwrap();
 
-        let lut_image = read_lut_texture_from_cube_file(parameters.take("file")?, queue.clone())?;
+        // a lut that can't be read is rejected when it is set while running
+        let file: LiveParameter<String> =
+            parameters
+                .take_live_checked("file", context, |path| read_cube_file(path).map(|_| ()))?;
+        let (version, path) = file.get_versioned();
+        let lut_image = read_lut_texture_from_cube_file(path, queue.clone())?;
+        let lut_image_view: Arc<dyn ImageViewAbstract> = ImageView::new_default(lut_image).unwrap();
         let lut_sampler = Sampler::new(
             device.clone(),
             SamplerCreateInfo {
@@ -93,7 +106,8 @@ impl Parameterizable for Lut3d {
             pipeline,
             queue,
             input: parameters.take("input")?,
-            lut_image_view: ImageView::new_default(lut_image).unwrap(),
+            file,
+            lut_image_view: Mutex::new((version, lut_image_view)),
             lut_sampler,
         })
     }
@@ -149,7 +163,9 @@ fn read_cube_data(file_contents: &str, mut on_value: impl FnMut(f32, f32, f32))
     Ok(())
 }
 
-fn read_lut_texture_from_cube_file(path: String, queue: Arc<Queue>) -> Result<Arc<ImmutableImage>> {
+/// Reads a `.cube` file into a `size`x`size`x`size` BGRA8 volume, with red
+/// varying fastest. Returns the size and the data.
+pub(crate) fn read_cube_file(path: String) -> Result<(usize, Vec<u8>)> {
     let file = File::open(path)?;
 
     let mut reader = BufReader::new(file);
@@ -177,6 +193,12 @@ fn read_lut_texture_from_cube_file(path: String, queue: Arc<Queue>) -> Result<Ar
         ));
     }
 
+    Ok((size, buffer))
+}
+
+fn read_lut_texture_from_cube_file(path: String, queue: Arc<Queue>) -> Result<Arc<ImmutableImage>> {
+    let (size, buffer) = read_cube_file(path)?;
+
     let (image, _image_fut) = ImmutableImage::from_iter(
         buffer.into_iter(),
         vulkano::image::ImageDimensions::Dim3d {
@@ -225,7 +247,7 @@ impl ProcessingNode for Lut3d {
                 WriteDescriptorSet::buffer(1, sink_buffer.clone()),
                 WriteDescriptorSet::image_view_sampler(
                     2,
-                    self.lut_image_view.clone(),
+                    self.current_lut_image_view(),
                     self.lut_sampler.clone(),
                 ),
             ],
@@ -258,8 +280,32 @@ impl ProcessingNode for Lut3d {
             fut.then_execute(self.queue.clone(), command_buffer)?.then_signal_fence_and_flush()?;
 
         future.wait(None).unwrap();
-        Ok(Payload::from(Frame { interp: frame.interp, storage: GpuBuffer::from(sink_buffer) }))
+        Ok(Payload::from(Frame {
+            interp: frame.interp,
+            storage: GpuBuffer::from(sink_buffer),
+            metadata: frame.metadata.clone(),
+        }))
     }
 
     fn get_caps(&self) -> Caps { self.input.get_caps() }
 }
+
+impl Lut3d {
+    /// The lut is read again if the file was changed while running. If that
+    /// fails anyway (the file changed after it was checked), the previous lut
+    /// is kept to not interrupt a recording.
+    fn current_lut_image_view(&self) -> Arc<dyn ImageViewAbstract> {
+        let (version, path) = self.file.get_versioned();
+        let mut lut_image_view = self.lut_image_view.lock();
+        if lut_image_view.0 != version {
+            match read_lut_texture_from_cube_file(path.clone(), self.queue.clone()) {
+                Ok(lut_image) => lut_image_view.1 = ImageView::new_default(lut_image).unwrap(),
+                Err(error) => {
+                    eprintln!("couldn't read the lut {path}, keeping the old one: {error}")
+                }
+            }
+            lut_image_view.0 = version;
+        }
+        lut_image_view.1.clone()
+    }
+}
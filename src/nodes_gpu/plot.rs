@@ -118,6 +118,9 @@ impl Parameterizable for Plot {
             .with("input", Mandatory(NodeInputParameter))
             .with("priority", Optional(U8()))
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).sink()
+    }
 
     fn from_parameters(
         mut parameters: Parameters,
@@ -0,0 +1,415 @@
+//! The AXIOM raw container format.
+//!
+//! All integers are little endian. A file consists of
+//! * a header: `FILE_MAGIC`, the format version as `u32`, the length of the
+//!   interpretation as `u32` and the `ContainerInterpretation` of all frames as
+//!   yaml
+//! * one record per frame: `FRAME_MAGIC`, the length of the metadata as `u32`,
+//!   the length of the frame data as `u64`, the `FrameMetadata` as yaml and the
+//!   frame data (which has a different length for every frame if it is
+//!   compressed)
+//! * an index that is written when the recording is finished: `INDEX_MAGIC`,
+//!   the number of frames as `u64` and the offsets of all frame records as
+//!   `u64`, followed by the offset of the index as `u64` and `INDEX_MAGIC`
+//!   again as the last 16 bytes of the file
+//!
+//! If the index is missing (for example because the recorder crashed), the
+//! frame records are scanned from the start and everything up to the last
+//! complete frame is recovered.
+//!
+//! The records (`write_record`, `read_record` and `scan_records`) are also used
+//! on their own by other formats with a different header in every record.
+
+use crate::pipeline_processing::{
+    buffers::CpuBuffer,
+    frame::{Frame, FrameInterpretation, FrameInterpretations, LosslessCompressed, Raw, Rgb, Rgba},
+    metadata::FrameMetadata,
+    payload::Payload,
+    processing_context::ProcessingContext,
+};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+pub const FILE_MAGIC: &[u8; 8] = b"AXIOMRAW";
+pub const VERSION: u32 = 1;
+const FRAME_MAGIC: &[u8; 4] = b"AXFR";
+const INDEX_MAGIC: &[u8; 8] = b"AXINDEX\0";
+
+const FRAME_HEADER_LEN: u64 = 4 + 4 + 8;
+const TRAILER_LEN: u64 = 8 + 8;
+
+/// The interpretation of all frames of a container. The uncompressed variants
+/// are serialized like the ones of `FrameInterpretations`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ContainerInterpretation {
+    Raw(Raw),
+    Rgb(Rgb),
+    Rgba(Rgba),
+    /// raw frames compressed by `LosslessCompress`
+    LosslessCompressed(Raw),
+}
+impl ContainerInterpretation {
+    /// The length of the data of every frame or `None` if it is compressed
+    pub fn frame_bytes(&self) -> Option<usize> {
+        match self {
+            ContainerInterpretation::Raw(interp) => Some(interp.required_bytes()),
+            ContainerInterpretation::Rgb(interp) => Some(interp.required_bytes()),
+            ContainerInterpretation::Rgba(interp) => Some(interp.required_bytes()),
+            ContainerInterpretation::LosslessCompressed(_) => None,
+        }
+    }
+
+    /// Creates the payload of a frame of the container.
+    pub fn payload(&self, storage: CpuBuffer, metadata: FrameMetadata) -> Payload {
+        match *self {
+            ContainerInterpretation::Raw(interp) => {
+                Payload::from(Frame { storage, interp, metadata })
+            }
+            ContainerInterpretation::Rgb(interp) => {
+                Payload::from(Frame { storage, interp, metadata })
+            }
+            ContainerInterpretation::Rgba(interp) => {
+                Payload::from(Frame { storage, interp, metadata })
+            }
+            ContainerInterpretation::LosslessCompressed(inner) => {
+                let interp = LosslessCompressed { inner, compressed_size: storage.len() };
+                Payload::from(Frame { storage, interp, metadata })
+            }
+        }
+    }
+}
+impl From<FrameInterpretations> for ContainerInterpretation {
+    fn from(interp: FrameInterpretations) -> Self {
+        match interp {
+            FrameInterpretations::Raw(interp) => ContainerInterpretation::Raw(interp),
+            FrameInterpretations::Rgb(interp) => ContainerInterpretation::Rgb(interp),
+            FrameInterpretations::Rgba(interp) => ContainerInterpretation::Rgba(interp),
+        }
+    }
+}
+
+pub struct ContainerWriter<W: Write> {
+    writer: W,
+    position: u64,
+    frame_offsets: Vec<u64>,
+    interp: ContainerInterpretation,
+}
+
+impl<W: Write> ContainerWriter<W> {
+    pub fn new(mut writer: W, interp: ContainerInterpretation) -> Result<Self> {
+        let interp_yaml = serde_yaml::to_string(&interp)?;
+        writer.write_all(FILE_MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(interp_yaml.len() as u32).to_le_bytes())?;
+        writer.write_all(interp_yaml.as_bytes())?;
+
+        let position = (FILE_MAGIC.len() + 4 + 4 + interp_yaml.len()) as u64;
+        Ok(Self { writer, position, frame_offsets: Vec::new(), interp })
+    }
+
+    pub fn write_frame(
+        &mut self,
+        interp: &ContainerInterpretation,
+        data: &[u8],
+        metadata: &FrameMetadata,
+    ) -> Result<()> {
+        if interp != &self.interp {
+            return Err(anyhow!(
+                "all frames of a container need the same interpretation, but got {interp:?} \
+                 after {:?}",
+                self.interp
+            ));
+        }
+        if let Some(frame_bytes) = interp.frame_bytes().filter(|bytes| *bytes != data.len()) {
+            return Err(anyhow!(
+                "frame has {} bytes, but its interpretation requires {frame_bytes}",
+                data.len(),
+            ));
+        }
+
+        let metadata_yaml = serde_yaml::to_string(metadata)?;
+        let record_len = write_record(&mut self.writer, metadata_yaml.as_bytes(), data)?;
+
+        self.frame_offsets.push(self.position);
+        self.position += record_len;
+        Ok(())
+    }
+
+    /// Writes the index and returns the inner writer.
+    pub fn finish(mut self) -> Result<W> {
+        let index_offset = self.position;
+        self.writer.write_all(INDEX_MAGIC)?;
+        self.writer.write_all(&(self.frame_offsets.len() as u64).to_le_bytes())?;
+        for offset in &self.frame_offsets {
+            self.writer.write_all(&offset.to_le_bytes())?;
+        }
+        self.writer.write_all(&index_offset.to_le_bytes())?;
+        self.writer.write_all(INDEX_MAGIC)?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Writes a record with the given header and data and returns its length.
+pub fn write_record(writer: &mut impl Write, header: &[u8], data: &[u8]) -> Result<u64> {
+    writer.write_all(FRAME_MAGIC)?;
+    writer.write_all(&(header.len() as u32).to_le_bytes())?;
+    writer.write_all(&(data.len() as u64).to_le_bytes())?;
+    writer.write_all(header)?;
+    writer.write_all(data)?;
+    Ok(FRAME_HEADER_LEN + header.len() as u64 + data.len() as u64)
+}
+
+/// Reads the header of the record at `offset` and returns it together with the
+/// length of the data, which the reader is positioned at.
+pub fn read_record<R: Read + Seek>(reader: &mut R, offset: u64) -> Result<(Vec<u8>, u64)> {
+    reader.seek(SeekFrom::Start(offset))?;
+    read_magic(reader, FRAME_MAGIC)?;
+    let header_len = read_u32(reader)?;
+    let data_len = read_u64(reader)?;
+    let mut header = vec![0u8; header_len as usize];
+    reader.read_exact(&mut header)?;
+    Ok((header, data_len))
+}
+
+#[derive(Debug)]
+pub struct ContainerIndex {
+    pub interp: ContainerInterpretation,
+    pub frame_offsets: Vec<u64>,
+    /// false if the index was missing or broken and the frames were recovered
+    /// by scanning the file
+    pub complete: bool,
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_magic<const N: usize>(reader: &mut impl Read, magic: &[u8; N]) -> Result<()> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    if &bytes != magic {
+        return Err(anyhow!("expected magic {magic:?}, found {bytes:?}"));
+    }
+    Ok(())
+}
+
+pub fn read_index<R: Read + Seek>(reader: &mut R) -> Result<ContainerIndex> {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    read_magic(reader, FILE_MAGIC).context("not an AXIOM raw container")?;
+    let version = read_u32(reader)?;
+    if version != VERSION {
+        return Err(anyhow!("unsupported AXIOM raw container version {version}"));
+    }
+    let interp_len = read_u32(reader)?;
+    let mut interp_yaml = vec![0u8; interp_len as usize];
+    reader.read_exact(&mut interp_yaml)?;
+    let interp: ContainerInterpretation = serde_yaml::from_slice(&interp_yaml)
+        .context("couldn't parse the frame interpretation of the container")?;
+    let first_frame = reader.stream_position()?;
+
+    match read_index_from_trailer(reader, first_frame, file_len) {
+        Ok(frame_offsets) => Ok(ContainerIndex { interp, frame_offsets, complete: true }),
+        Err(_) => {
+            let frame_offsets = scan_records(reader, first_frame, file_len)?;
+            Ok(ContainerIndex { interp, frame_offsets, complete: false })
+        }
+    }
+}
+
+fn read_index_from_trailer<R: Read + Seek>(
+    reader: &mut R,
+    first_frame: u64,
+    file_len: u64,
+) -> Result<Vec<u64>> {
+    if file_len < first_frame + TRAILER_LEN {
+        return Err(anyhow!("file too short to contain an index"));
+    }
+    reader.seek(SeekFrom::Start(file_len - TRAILER_LEN))?;
+    let index_offset = read_u64(reader)?;
+    read_magic(reader, INDEX_MAGIC)?;
+
+    if index_offset < first_frame || index_offset > file_len - TRAILER_LEN {
+        return Err(anyhow!("index offset {index_offset} out of bounds"));
+    }
+    reader.seek(SeekFrom::Start(index_offset))?;
+    read_magic(reader, INDEX_MAGIC)?;
+    let frame_count = read_u64(reader)?;
+    if index_offset + 16 + frame_count * 8 + TRAILER_LEN != file_len {
+        return Err(anyhow!("index length doesn't match the file length"));
+    }
+
+    (0..frame_count).map(|_| read_u64(reader)).collect()
+}
+
+/// Finds the offsets of all complete records from `first_record` on, by
+/// skipping from one to the next until the end of the file (or of the
+/// records).
+pub fn scan_records<R: Read + Seek>(
+    reader: &mut R,
+    first_record: u64,
+    file_len: u64,
+) -> Result<Vec<u64>> {
+    let mut frame_offsets = Vec::new();
+    let mut offset = first_record;
+
+    while offset + FRAME_HEADER_LEN <= file_len {
+        reader.seek(SeekFrom::Start(offset))?;
+        if read_magic(reader, FRAME_MAGIC).is_err() {
+            break;
+        }
+        let header_len = read_u32(reader)? as u64;
+        let data_len = read_u64(reader)?;
+        let next_offset = offset + FRAME_HEADER_LEN + header_len + data_len;
+        if next_offset > file_len {
+            break;
+        }
+
+        frame_offsets.push(offset);
+        offset = next_offset;
+    }
+
+    Ok(frame_offsets)
+}
+
+/// Reads the frame record at `offset`, which has to have the size required by
+/// the interpretation of the container.
+pub fn read_frame<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+    interp: &ContainerInterpretation,
+    context: &ProcessingContext,
+) -> Result<(CpuBuffer, FrameMetadata)> {
+    let (metadata_yaml, data_len) = read_record(reader, offset)?;
+    if let Some(frame_bytes) = interp.frame_bytes().filter(|bytes| *bytes as u64 != data_len) {
+        return Err(anyhow!(
+            "frame at offset {offset} has {data_len} bytes, but {frame_bytes} were expected"
+        ));
+    }
+    let mut buffer = unsafe { context.get_uninit_cpu_buffer(data_len as usize) };
+    buffer.as_mut_slice(|data| reader.read_exact(data))?;
+
+    Ok((buffer, serde_yaml::from_slice(&metadata_yaml)?))
+}
+
+/// Gets the interpretation, data and metadata of any frame that can be stored
+/// in a container.
+pub fn container_frame_parts(
+    context: &ProcessingContext,
+    payload: &Payload,
+) -> Result<(ContainerInterpretation, CpuBuffer, FrameMetadata)> {
+    if let Ok(frame) = context.ensure_cpu_buffer::<LosslessCompressed>(payload) {
+        Ok((
+            ContainerInterpretation::LosslessCompressed(frame.interp.inner),
+            frame.storage.clone(),
+            frame.metadata.clone(),
+        ))
+    } else {
+        let (interp, storage, metadata) = frame_parts(context, payload)
+            .context("wanted a frame that can be stored in a container")?;
+        Ok((interp.into(), storage, metadata))
+    }
+}
+
+/// Gets the interpretation, data and metadata of any uncompressed frame.
+pub fn frame_parts(
+    context: &ProcessingContext,
+    payload: &Payload,
+) -> Result<(FrameInterpretations, CpuBuffer, FrameMetadata)> {
+    if let Ok(frame) = context.ensure_cpu_buffer::<Raw>(payload) {
+        Ok((FrameInterpretations::Raw(frame.interp), frame.storage.clone(), frame.metadata.clone()))
+    } else if let Ok(frame) = context.ensure_cpu_buffer::<Rgb>(payload) {
+        Ok((FrameInterpretations::Rgb(frame.interp), frame.storage.clone(), frame.metadata.clone()))
+    } else if let Ok(frame) = context.ensure_cpu_buffer::<Rgba>(payload) {
+        Ok((
+            FrameInterpretations::Rgba(frame.interp),
+            frame.storage.clone(),
+            frame.metadata.clone(),
+        ))
+    } else {
+        Err(anyhow!(
+            "wanted a Raw, Rgb or Rgba frame, but the payload was of type {}",
+            payload.type_name
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline_processing::{frame::CfaDescriptor, metadata::SensorSequence};
+    use std::io::Cursor;
+
+    fn test_interp() -> ContainerInterpretation {
+        ContainerInterpretation::Raw(Raw {
+            width: 4,
+            height: 2,
+            bit_depth: 12,
+            cfa: CfaDescriptor::from_first_red(true, false),
+            fps: 24.0,
+            black_level: [128; 4],
+            white_level: 4095,
+        })
+    }
+
+    fn write_frames(finish: bool) -> Vec<u8> {
+        let interp = test_interp();
+        let mut writer = ContainerWriter::new(Cursor::new(Vec::new()), interp).unwrap();
+        for i in 0..3u8 {
+            let data = vec![i; interp.frame_bytes().unwrap()];
+            let metadata = FrameMetadata::new().with(SensorSequence(i as u64));
+            writer.write_frame(&interp, &data, &metadata).unwrap();
+        }
+
+        if finish {
+            writer.finish().unwrap().into_inner()
+        } else {
+            writer.writer.into_inner()
+        }
+    }
+
+    fn check_frames(file: Vec<u8>, expected_frames: usize, expected_complete: bool) {
+        let context = ProcessingContext::default();
+        let mut reader = Cursor::new(file);
+        let index = read_index(&mut reader).unwrap();
+        assert_eq!(index.interp, test_interp());
+        assert_eq!(index.complete, expected_complete);
+        assert_eq!(index.frame_offsets.len(), expected_frames);
+
+        for (i, offset) in index.frame_offsets.iter().enumerate() {
+            let (data, metadata) =
+                read_frame(&mut reader, *offset, &index.interp, &context).unwrap();
+            assert_eq!(data.len(), index.interp.frame_bytes().unwrap());
+            data.as_slice(|data| assert!(data.iter().all(|v| *v == i as u8)));
+            assert_eq!(metadata.get::<SensorSequence>(), Some(SensorSequence(i as u64)));
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() { check_frames(write_frames(true), 3, true); }
+
+    #[test]
+    fn test_recovery_after_crash() {
+        let file = write_frames(false);
+        check_frames(file.clone(), 3, false);
+
+        // a crash in the middle of writing the last frame
+        check_frames(file[..file.len() - 5].to_vec(), 2, false);
+
+        // a crash in the middle of writing the index
+        let file = write_frames(true);
+        check_frames(file[..file.len() - 3].to_vec(), 3, false);
+    }
+}
@@ -1,10 +1,13 @@
-use crate::pipeline_processing::{
-    frame::Raw,
-    node::{InputProcessingNode, NodeID, ProgressUpdate, Request, SinkNode},
-    parametrizable::prelude::*,
-    processing_context::ProcessingContext,
+use crate::{
+    nodes_io::writer_cinema_dng::frame_to_ifd,
+    pipeline_processing::{
+        frame::Raw,
+        node::{InputProcessingNode, NodeID, ProgressUpdate, Request, SinkNode},
+        parametrizable::prelude::*,
+        processing_context::ProcessingContext,
+    },
 };
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use dav_server::{
     davpath::DavPath,
@@ -24,14 +27,7 @@ use dav_server::{
     DavHandler,
 };
 use derivative::Derivative;
-use dng::{
-    ifd::{Ifd, IfdValue},
-    tags,
-    tags::IfdType,
-    yaml::IfdYamlParser,
-    DngWriter,
-    FileType,
-};
+use dng::{ifd::Ifd, yaml::IfdYamlParser, DngWriter, FileType};
 use futures::{future, FutureExt};
 use hyper::{
     body::{Buf, Bytes},
@@ -70,7 +66,10 @@ impl Parameterizable for CinemaDngFrameserver {
             .with("priority", Optional(U8()))
             .with("host", WithDefault(StringParameter, StringValue("127.0.0.1".to_string())))
             .with("port", Optional(IntRange(0, u16::MAX as i64)))
-            .with("dcp-yaml", Optional(StringParameter))
+            .with("dcp-yaml", Optional(PathParameter(PathCheck::Exists)))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).sink()
     }
 
     fn from_parameters(
@@ -115,6 +114,7 @@ impl SinkNode for CinemaDngFrameserver {
         context: &ProcessingContext,
         _progress_callback: Arc<dyn Fn(ProgressUpdate) + Send + Sync>,
     ) -> Result<()> {
+        let run_control = context.run_control().clone();
         let context = context.clone();
         let base_ifd = self.base_ifd.clone();
         let priority = self.priority;
@@ -126,6 +126,10 @@ impl SinkNode for CinemaDngFrameserver {
             let base_ifd = base_ifd.clone();
             let input = input.clone_for_same_puller();
             async move {
+                // waits while the run is paused
+                if !context.run_control().proceed().await {
+                    return Err(anyhow!("the run was stopped, no more frames are served"));
+                }
                 let payload = input.pull(Request::new(priority, i)).await?;
 
                 let frame = context
@@ -133,32 +137,7 @@ impl SinkNode for CinemaDngFrameserver {
                     .context("Wrong input format for CinemaDngWriter")?;
 
 
-                let mut ifd = Ifd::new(IfdType::Ifd);
-                ifd.insert_from_other(base_ifd.clone());
-
-                ifd.insert(tags::ifd::ImageWidth, frame.interp.width as u32);
-                ifd.insert(tags::ifd::ImageLength, frame.interp.height as u32);
-                ifd.insert(tags::ifd::RowsPerStrip, frame.interp.height as u32);
-                ifd.insert(
-                    tags::ifd::FrameRate,
-                    IfdValue::SRational((frame.interp.fps * 10000.0) as i32, 10000),
-                );
-                ifd.insert(tags::ifd::BitsPerSample, frame.interp.bit_depth as u32);
-                ifd.insert(
-                    tags::ifd::CFAPattern,
-                    match (frame.interp.cfa.red_in_first_row, frame.interp.cfa.red_in_first_col) {
-                        (true, true) => [0u8, 1, 1, 2],
-                        (true, false) => [1, 0, 2, 1],
-                        (false, true) => [1, 2, 0, 1],
-                        (false, false) => [2, 1, 1, 0],
-                    },
-                );
-
-                ifd.insert(
-                    tags::ifd::StripOffsets,
-                    IfdValue::Offsets(Arc::new(frame.storage.clone())),
-                );
-                ifd.insert(tags::ifd::StripByteCounts, frame.storage.len() as u32);
+                let ifd = frame_to_ifd(&base_ifd, &frame)?;
 
                 let mut buffer = Cursor::new(Vec::new());
                 DngWriter::write_dng(&mut buffer, true, FileType::Dng, vec![ifd])?;
@@ -179,7 +158,10 @@ impl SinkNode for CinemaDngFrameserver {
                 Ok::<_, hyper::Error>(service_fn(func))
             }
         });
-        let server = Server::bind(&self.address).serve(service);
+        // stopping the run finishes the open requests and ends the server
+        let server = Server::bind(&self.address)
+            .serve(service)
+            .with_graceful_shutdown(async move { run_control.stopped().await });
         println!("Listening on http://{}", self.address);
 
         server.await?;
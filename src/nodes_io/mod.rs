@@ -1,9 +1,15 @@
+pub mod axiom_container;
 pub mod frameserver_cinema_dng;
+pub mod reader_axiom_container;
 pub mod reader_cinema_dng;
 pub mod reader_raw;
 pub mod reader_tcp;
 #[cfg(target_os = "linux")]
 pub mod reader_webcam;
+pub mod sz3_blob;
+pub mod tcp_framing;
+pub mod writer_axiom_container;
 pub mod writer_cinema_dng;
 pub mod writer_ffmpeg;
 pub mod writer_raw;
+pub mod writer_tcp;
@@ -0,0 +1,85 @@
+use crate::{
+    nodes_io::axiom_container::{read_frame, read_index, ContainerInterpretation},
+    pipeline_processing::{
+        node::{Caps, NodeID, ProcessingNode, Request},
+        parametrizable::prelude::*,
+        payload::Payload,
+        processing_context::ProcessingContext,
+    },
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::{fs::File, io::BufReader, sync::Mutex};
+
+
+pub struct AxiomContainerReader {
+    file: Mutex<BufReader<File>>,
+    interp: ContainerInterpretation,
+    frame_offsets: Vec<u64>,
+    context: ProcessingContext,
+}
+impl Parameterizable for AxiomContainerReader {
+    const DESCRIPTION: Option<&'static str> = Some(
+        "read frames from an AXIOM raw container file, recovering incomplete recordings up to the \
+         last complete frame",
+    );
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new().with("file", Mandatory(PathParameter(PathCheck::Exists)))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_output(&[
+            FrameType::Raw,
+            FrameType::Rgb,
+            FrameType::Rgba,
+            FrameType::LosslessCompressed,
+        ])
+    }
+    fn from_parameters(
+        mut options: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let path: String = options.take("file")?;
+        let mut file = BufReader::new(File::open(&path)?);
+        let index = read_index(&mut file)?;
+        if !index.complete {
+            eprintln!(
+                "{path} has no valid index (was the recording interrupted?), recovered {} frames",
+                index.frame_offsets.len()
+            );
+        }
+
+        Ok(Self {
+            file: Mutex::new(file),
+            interp: index.interp,
+            frame_offsets: index.frame_offsets,
+            context: context.clone(),
+        })
+    }
+}
+#[async_trait]
+impl ProcessingNode for AxiomContainerReader {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let frame_number = request.frame_number();
+        let offset = *self.frame_offsets.get(frame_number as usize).ok_or_else(|| {
+            anyhow!(
+                "frame {} was requested but this stream only has a length of {}",
+                frame_number,
+                self.frame_offsets.len()
+            )
+        })?;
+
+        let (buffer, metadata) =
+            read_frame(&mut *self.file.lock().unwrap(), offset, &self.interp, &self.context)?;
+
+        Ok(self.interp.payload(buffer, metadata))
+    }
+
+    fn get_caps(&self) -> Caps {
+        Caps { frame_count: Some(self.frame_offsets.len() as u64), random_access: true }
+    }
+}
@@ -1,13 +1,17 @@
-use crate::pipeline_processing::{
-    frame::{CfaDescriptor, Frame, Raw},
-    node::{Caps, NodeID, ProcessingNode, Request},
-    parametrizable::prelude::*,
-    payload::Payload,
-    processing_context::ProcessingContext,
+use crate::{
+    nodes_io::writer_cinema_dng::timecode_from_smpte,
+    pipeline_processing::{
+        frame::{CfaDescriptor, Frame, Raw},
+        metadata::FrameMetadata,
+        node::{Caps, NodeID, ProcessingNode, Request},
+        parametrizable::prelude::*,
+        payload::Payload,
+        processing_context::ProcessingContext,
+    },
 };
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use dng::{tags, DngReader};
+use dng::{ifd::IfdValue, tags, DngReader};
 use glob::glob;
 use std::{fs::File, path::PathBuf, sync::Mutex};
 
@@ -29,6 +33,7 @@ impl Parameterizable for CinemaDngReader {
             .with("cache-frames", Optional(BoolParameter))
             .with("internal-loop", Optional(BoolParameter))
     }
+    fn describe_port_types() -> PortTypes { PortTypes::new().with_output(&[FrameType::Raw]) }
     fn from_parameters(
         mut options: Parameters,
         _is_input_to: &[NodeID],
@@ -108,10 +113,49 @@ impl ProcessingNode for CinemaDngReader {
                 .map(|x| x as u64)
         };
 
+        let get_tag_as_list = |tag| {
+            dng.get_entry_by_path(&main_ifd.chain_tag(tag))
+                .map(|x| {
+                    x.value
+                        .as_list()
+                        .map(|x| x.as_f64())
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or(anyhow!("couldnt interpret {tag:?} of DNG {path:?} as numbers"))
+                })
+                .transpose()
+        };
+
+        // the black level pattern is given for a `rows`x`cols` area, which we repeat to
+        // cover the 2x2 cfa pattern
+        let black_level = match get_tag_as_list(tags::ifd::BlackLevel)? {
+            Some(values) => {
+                let (rows, cols) = match get_tag_as_list(tags::ifd::BlackLevelRepeatDim)?.as_deref()
+                {
+                    Some(&[rows, cols]) => ((rows as usize).max(1), (cols as usize).max(1)),
+                    _ => (1, 1),
+                };
+                if values.len() < rows * cols {
+                    return Err(anyhow!(
+                        "DNG {path:?} has {} BlackLevel values, but {rows}x{cols} are needed",
+                        values.len()
+                    ));
+                }
+                [(0, 0), (0, 1), (1, 0), (1, 1)]
+                    .map(|(y, x)| values[(y % rows) * cols + (x % cols)].round() as u32)
+            }
+            None => [0; 4],
+        };
+
+        let bit_depth = get_tag_as_u32(tags::ifd::BitsPerSample)?;
+        let white_level = match dng.get_entry_by_path(&main_ifd.chain_tag(tags::ifd::WhiteLevel)) {
+            Some(_) => get_tag_as_u32(tags::ifd::WhiteLevel)? as u32,
+            None => Raw::default_white_level(bit_depth),
+        };
+
         let interp = Raw {
             width: get_tag_as_u32(tags::ifd::ImageWidth)?,
             height: get_tag_as_u32(tags::ifd::ImageLength)?,
-            bit_depth: get_tag_as_u32(tags::ifd::BitsPerSample)?,
+            bit_depth,
             fps: dng
                 .get_entry_by_path(&main_ifd.chain_tag(tags::ifd::FrameRate))
                 .map(|entry| {
@@ -125,9 +169,28 @@ impl ProcessingNode for CinemaDngReader {
                     Ok(24.0)
                 })?,
             cfa,
+            black_level,
+            white_level,
         };
 
-        let payload = Payload::from(Frame { storage: buffer, interp });
+        // restore the metadata written by `CinemaDngWriter`
+        let mut metadata = match dng
+            .get_entry_by_path(&main_ifd.chain_tag(tags::ifd::ImageDescription))
+            .map(|entry| entry.value.clone())
+        {
+            Some(IfdValue::Ascii(description)) => {
+                serde_yaml::from_str(&description).unwrap_or_default()
+            }
+            _ => FrameMetadata::default(),
+        };
+        let timecode = get_tag_as_list(tags::ifd::TimeCodes)?.and_then(|smpte| {
+            timecode_from_smpte(&smpte.iter().map(|v| *v as u8).collect::<Vec<_>>())
+        });
+        if let Some(timecode) = timecode {
+            metadata.insert(timecode);
+        }
+
+        let payload = Payload::from(Frame { storage: buffer, interp, metadata });
 
         if self.cache_frames {
             self.cache.lock().unwrap()[frame_number as usize] = Some(payload.clone());
@@ -1,5 +1,6 @@
 use crate::pipeline_processing::{
     frame::{Frame, FrameInterpretation, FrameInterpretations},
+    metadata::FrameMetadata,
     node::{Caps, NodeID, ProcessingNode, Request},
     parametrizable::prelude::*,
     payload::Payload,
@@ -19,21 +20,27 @@ use std::{
 pub struct RawBlobReader {
     file: Mutex<File>,
     interp: FrameInterpretations,
+    metadata: Vec<FrameMetadata>,
     cache_frames: bool,
     cache: Mutex<Vec<Option<Payload>>>,
     frame_count: u64,
     context: ProcessingContext,
 }
 impl Parameterizable for RawBlobReader {
-    const DESCRIPTION: Option<&'static str> =
-        Some("read packed binary frames from a single file without headers or metadata");
+    const DESCRIPTION: Option<&'static str> = Some(
+        "read packed binary frames from a single file without headers, the metadata of the \
+         frames is read from <file>.meta.yml if it exists",
+    );
 
     fn describe_parameters() -> ParametersDescriptor {
         ParametersDescriptor::new()
             .with_interpretation()
-            .with("file", Mandatory(StringParameter))
+            .with("file", Mandatory(PathParameter(PathCheck::Exists)))
             .with("cache-frames", Optional(BoolParameter))
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_output(&[FrameType::Raw, FrameType::Rgb])
+    }
     fn from_parameters(
         mut options: Parameters,
         _is_input_to: &[NodeID],
@@ -43,13 +50,15 @@ impl Parameterizable for RawBlobReader {
         Self: Sized,
     {
         let path: String = options.take("file")?;
-        let file = File::open(path)?;
+        let file = File::open(&path)?;
+        let metadata = FrameMetadata::read_sidecar(format!("{path}.meta.yml"))?;
 
         let interp = options.get_interpretation()?;
         let frame_count = file.metadata()?.len() / interp.required_bytes() as u64;
         Ok(Self {
             file: Mutex::new(file),
             interp,
+            metadata,
             frame_count,
             cache_frames: options.take("cache-frames")?,
             cache: Mutex::new((0..frame_count).map(|_| None).collect()),
@@ -83,10 +92,17 @@ impl ProcessingNode for RawBlobReader {
             }
         }
 
+        let metadata = self.metadata.get(frame_number as usize).cloned().unwrap_or_default();
         let payload = match self.interp {
-            FrameInterpretations::Raw(interp) => Payload::from(Frame { storage: buffer, interp }),
-            FrameInterpretations::Rgb(interp) => Payload::from(Frame { storage: buffer, interp }),
-            FrameInterpretations::Rgba(interp) => Payload::from(Frame { storage: buffer, interp }),
+            FrameInterpretations::Raw(interp) => {
+                Payload::from(Frame { storage: buffer, interp, metadata })
+            }
+            FrameInterpretations::Rgb(interp) => {
+                Payload::from(Frame { storage: buffer, interp, metadata })
+            }
+            FrameInterpretations::Rgba(interp) => {
+                Payload::from(Frame { storage: buffer, interp, metadata })
+            }
         };
 
         self.cache.lock().unwrap()[frame_number as usize] = Some(payload.clone());
@@ -108,8 +124,10 @@ pub struct RawDirectoryReader {
     context: ProcessingContext,
 }
 impl Parameterizable for RawDirectoryReader {
-    const DESCRIPTION: Option<&'static str> =
-        Some("read packed binary frames without headers or metadata from a directory");
+    const DESCRIPTION: Option<&'static str> = Some(
+        "read packed binary frames without headers from a directory, one file per frame, the \
+         metadata of a frame is read from a .meta.yml file next to it if it exists",
+    );
 
     fn describe_parameters() -> ParametersDescriptor {
         ParametersDescriptor::new()
@@ -118,6 +136,9 @@ impl Parameterizable for RawDirectoryReader {
             .with("cache-frames", Optional(BoolParameter))
             .with("internal-loop", Optional(BoolParameter))
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_output(&[FrameType::Raw, FrameType::Rgb])
+    }
     fn from_parameters(
         mut options: Parameters,
         _is_input_to: &[NodeID],
@@ -127,7 +148,9 @@ impl Parameterizable for RawDirectoryReader {
         Self: Sized,
     {
         let file_pattern: String = options.take("file-pattern")?;
-        let files = glob(&file_pattern)?.collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut files = glob(&file_pattern)?.collect::<std::result::Result<Vec<_>, _>>()?;
+        // RawDirectoryWriter puts the metadata sidecars next to the frames
+        files.retain(|file| !file.to_string_lossy().ends_with(".meta.yml"));
         let frame_count = files.len();
         if frame_count == 0 {
             return Err(anyhow!("no files matched the pattern {}", file_pattern));
@@ -172,10 +195,18 @@ impl ProcessingNode for RawDirectoryReader {
             .as_mut_slice(|buffer| file.read_exact(buffer).context("error while reading file"))?;
 
 
+        let metadata =
+            FrameMetadata::read_sidecar(path.with_extension("meta.yml"))?.pop().unwrap_or_default();
         let payload = match self.interp {
-            FrameInterpretations::Raw(interp) => Payload::from(Frame { storage: buffer, interp }),
-            FrameInterpretations::Rgb(interp) => Payload::from(Frame { storage: buffer, interp }),
-            FrameInterpretations::Rgba(interp) => Payload::from(Frame { storage: buffer, interp }),
+            FrameInterpretations::Raw(interp) => {
+                Payload::from(Frame { storage: buffer, interp, metadata })
+            }
+            FrameInterpretations::Rgb(interp) => {
+                Payload::from(Frame { storage: buffer, interp, metadata })
+            }
+            FrameInterpretations::Rgba(interp) => {
+                Payload::from(Frame { storage: buffer, interp, metadata })
+            }
         };
 
         if self.cache_frames {
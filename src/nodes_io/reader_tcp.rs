@@ -1,6 +1,8 @@
 use crate::{
+    nodes_io::tcp_framing::{read_frame, FramedRead},
     pipeline_processing::{
         frame::{Frame, FrameInterpretation, FrameInterpretations},
+        metadata::{DroppedFrames, FrameMetadata, SensorSequence},
         node::{Caps, EOFError, NodeID, ProcessingNode, Request},
         parametrizable::prelude::*,
         payload::Payload,
@@ -10,20 +12,39 @@ use crate::{
 };
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use std::{io::Read, net::TcpStream, sync::Mutex};
+use std::time::Duration;
+use tokio::{
+    io::{AsyncReadExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
 
 pub struct TcpReader {
-    tcp_connection: Mutex<TcpStream>,
+    frames: flume::Receiver<Result<(Vec<u8>, FrameMetadata)>>,
     interp: FrameInterpretations,
     notifier: AsyncNotifier<u64>,
     context: ProcessingContext,
 }
 impl Parameterizable for TcpReader {
+    const DESCRIPTION: Option<&'static str> = Some(
+        "read frames from a tcp connection, either connecting to the address or (with \
+         listen=true) accepting connections on it",
+    );
+
     fn describe_parameters() -> ParametersDescriptor {
         ParametersDescriptor::new()
             .with("address", Mandatory(StringParameter))
+            .with("listen", Optional(BoolParameter))
+            .with("framed", Optional(BoolParameter))
+            .with("reconnect", Optional(BoolParameter))
+            .with(
+                "reconnect-interval",
+                WithDefault(DurationParameter, DurationValue(Duration::from_secs(1))),
+            )
             .with_interpretation()
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_output(&[FrameType::Raw, FrameType::Rgb])
+    }
 
     fn from_parameters(
         mut parameters: Parameters,
@@ -33,12 +54,24 @@ impl Parameterizable for TcpReader {
     where
         Self: Sized,
     {
-        Ok(Self {
-            tcp_connection: Mutex::new(TcpStream::connect(parameters.take::<String>("address")?)?),
-            interp: parameters.get_interpretation()?,
-            notifier: Default::default(),
-            context: context.clone(),
-        })
+        let interp = parameters.get_interpretation()?;
+        let connection = Connection {
+            address: parameters.take("address")?,
+            listen: parameters.take("listen")?,
+            framed: parameters.take("framed")?,
+            reconnect: parameters.take("reconnect")?,
+            reconnect_interval: parameters.take("reconnect-interval")?,
+            frame_bytes: interp.required_bytes(),
+        };
+
+        let (tx, rx) = flume::bounded(context.num_threads());
+        context.spawn_io(async move {
+            if let Err(e) = connection.receive_frames(&tx).await {
+                let _ = tx.send_async(Err(e)).await;
+            }
+        });
+
+        Ok(Self { frames: rx, interp, notifier: Default::default(), context: context.clone() })
     }
 }
 
@@ -49,18 +82,24 @@ impl ProcessingNode for TcpReader {
 
         self.notifier.wait(move |x| *x >= frame_number).await;
 
-        let mut buffer =
-            unsafe { self.context.get_uninit_cpu_buffer(self.interp.required_bytes()) };
-        buffer
-            .as_mut_slice(|slice| self.tcp_connection.lock().unwrap().read_exact(slice))
-            .context(EOFError)?;
-
+        // the sender is dropped once the connection is closed for good
+        let received = self.frames.recv_async().await.context(EOFError);
         self.notifier.update(|x| *x = frame_number + 1);
+        let (data, metadata) = received??;
+
+        let mut buffer = unsafe { self.context.get_uninit_cpu_buffer(data.len()) };
+        buffer.as_mut_slice(|slice| slice.copy_from_slice(&data));
 
         let payload = match self.interp {
-            FrameInterpretations::Raw(interp) => Payload::from(Frame { storage: buffer, interp }),
-            FrameInterpretations::Rgb(interp) => Payload::from(Frame { storage: buffer, interp }),
-            FrameInterpretations::Rgba(interp) => Payload::from(Frame { storage: buffer, interp }),
+            FrameInterpretations::Raw(interp) => {
+                Payload::from(Frame { storage: buffer, interp, metadata })
+            }
+            FrameInterpretations::Rgb(interp) => {
+                Payload::from(Frame { storage: buffer, interp, metadata })
+            }
+            FrameInterpretations::Rgba(interp) => {
+                Payload::from(Frame { storage: buffer, interp, metadata })
+            }
         };
 
         Ok(payload)
@@ -68,3 +107,228 @@ impl ProcessingNode for TcpReader {
 
     fn get_caps(&self) -> Caps { Caps { frame_count: None, random_access: false } }
 }
+
+struct Connection {
+    address: String,
+    listen: bool,
+    framed: bool,
+    reconnect: bool,
+    reconnect_interval: Duration,
+    frame_bytes: usize,
+}
+
+impl Connection {
+    /// Receives frames until the connection is closed (and `reconnect` is not
+    /// set) or the reader is dropped. Frames that were missed or corrupted are
+    /// recorded in the metadata of the next frame.
+    async fn receive_frames(
+        &self,
+        tx: &flume::Sender<Result<(Vec<u8>, FrameMetadata)>>,
+    ) -> Result<()> {
+        let listener = if self.listen {
+            Some(
+                TcpListener::bind(&self.address)
+                    .await
+                    .with_context(|| format!("couldn't listen on {}", self.address))?,
+            )
+        } else {
+            None
+        };
+
+        let mut next_sequence = None;
+        let mut corrupted = 0;
+        loop {
+            if tx.is_disconnected() {
+                // the reader was dropped, nobody wants the frames anymore
+                return Ok(());
+            }
+            let stream = match &listener {
+                Some(listener) => {
+                    match tokio::time::timeout(self.reconnect_interval, listener.accept()).await {
+                        Ok(accepted) => accepted.map(|(stream, _)| stream),
+                        // check again whether the reader still exists
+                        Err(_) => continue,
+                    }
+                }
+                None => TcpStream::connect(&self.address).await,
+            };
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) if self.reconnect => {
+                    eprintln!("couldn't connect to {}: {e}, retrying", self.address);
+                    tokio::time::sleep(self.reconnect_interval).await;
+                    continue;
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("couldn't connect to {}", self.address))
+                }
+            };
+
+            let mut stream = BufReader::new(stream);
+            let lost_connection = loop {
+                let frame = if self.framed {
+                    read_frame(&mut stream, self.frame_bytes).await
+                } else {
+                    let mut data = vec![0u8; self.frame_bytes];
+                    stream.read_exact(&mut data).await.map(|_| FramedRead::Frame {
+                        sequence: next_sequence.unwrap_or(0),
+                        data,
+                        skipped_bytes: 0,
+                    })
+                };
+
+                match frame {
+                    Ok(FramedRead::Frame { sequence, data, skipped_bytes }) => {
+                        if skipped_bytes > 0 {
+                            eprintln!(
+                                "skipped {skipped_bytes} bytes to resync on frame {sequence}"
+                            );
+                        }
+                        let mut missing = 0;
+                        if let Some(expected) =
+                            next_sequence.filter(|expected| *expected < sequence)
+                        {
+                            eprintln!(
+                                "missed {} frames (from {expected} to {})",
+                                sequence - expected,
+                                sequence - 1
+                            );
+                            // the corrupted frames are part of the gap
+                            missing = (sequence - expected).saturating_sub(corrupted);
+                        }
+                        next_sequence = Some(sequence + 1);
+
+                        // without framing the sequence number only counts the received frames
+                        let mut metadata = if self.framed {
+                            FrameMetadata::new().with(SensorSequence(sequence))
+                        } else {
+                            FrameMetadata::default()
+                        };
+                        if missing > 0 || corrupted > 0 {
+                            metadata.insert(DroppedFrames { missing, corrupted });
+                        }
+                        corrupted = 0;
+                        if tx.send_async(Ok((data, metadata))).await.is_err() {
+                            // the reader was dropped, nobody wants the frames anymore
+                            return Ok(());
+                        }
+                    }
+                    Ok(FramedRead::Corrupted { sequence, .. }) => {
+                        eprintln!("dropped corrupted frame {sequence}");
+                        corrupted += 1;
+                    }
+                    Err(e) => break e,
+                }
+            };
+
+            if !self.reconnect {
+                return Ok(());
+            }
+            eprintln!("lost connection to {}: {lost_connection}, reconnecting", self.address);
+            if listener.is_none() {
+                tokio::time::sleep(self.reconnect_interval).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        nodes_io::tcp_framing::encode_header,
+        pipeline_processing::{frame::Raw, test_util::create},
+    };
+    use futures::executor::block_on;
+    use std::{io::Write, net};
+
+    fn tcp_reader(context: &ProcessingContext, address: &str, listen: bool) -> TcpReader {
+        create(
+            context,
+            [
+                ("address", StringValue(address.to_string())),
+                ("listen", BoolValue(listen)),
+                ("framed", BoolValue(true)),
+                ("reconnect", BoolValue(true)),
+                ("reconnect-interval", DurationValue(Duration::from_millis(10))),
+                ("width", IntRangeValue(4)),
+                ("height", IntRangeValue(2)),
+                ("bit-depth", IntRangeValue(8)),
+            ],
+        )
+    }
+
+    fn send(stream: &mut net::TcpStream, sequence: u64, corrupted: bool) {
+        let data = vec![sequence as u8; 8];
+        let mut header = encode_header(sequence, &data);
+        if corrupted {
+            // a wrong checksum
+            header[16] ^= 1;
+        }
+        stream.write_all(&[&header[..], &data].concat()).unwrap();
+    }
+
+    /// Returns the value of the pixels and the metadata of the frame.
+    fn pull(reader: &TcpReader, frame_number: u64) -> (u8, FrameMetadata) {
+        let payload = block_on(reader.pull(Request::new(0, frame_number))).unwrap();
+        let frame = reader.context.ensure_cpu_buffer::<Raw>(&payload).unwrap();
+        (frame.storage.as_slice(|data| data[0]), frame.metadata.clone())
+    }
+
+    fn sequence(sequence: u64) -> FrameMetadata {
+        FrameMetadata::new().with(SensorSequence(sequence))
+    }
+
+    fn wait_for<T>(mut f: impl FnMut() -> std::io::Result<T>) -> T {
+        for _ in 0..1000 {
+            if let Ok(value) = f() {
+                return value;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("timed out");
+    }
+
+    #[test]
+    fn test_listen() {
+        let context = ProcessingContext::default();
+        let address = net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let reader = tcp_reader(&context, &address.to_string(), true);
+
+        let mut client = wait_for(|| net::TcpStream::connect(address));
+        send(&mut client, 0, false);
+        send(&mut client, 1, true);
+        send(&mut client, 2, false);
+        send(&mut client, 5, false);
+        drop(client);
+        // the writer reconnects
+        let mut client = wait_for(|| net::TcpStream::connect(address));
+        send(&mut client, 6, false);
+
+        assert_eq!(pull(&reader, 0), (0, sequence(0)));
+        let dropped = |missing, corrupted| DroppedFrames { missing, corrupted };
+        assert_eq!(pull(&reader, 1), (2, sequence(2).with(dropped(0, 1))));
+        assert_eq!(pull(&reader, 2), (5, sequence(5).with(dropped(2, 0))));
+        assert_eq!(pull(&reader, 3), (6, sequence(6)));
+
+        // the reader stops listening once it is dropped
+        drop(client);
+        drop(reader);
+        wait_for(|| net::TcpListener::bind(address));
+    }
+
+    #[test]
+    fn test_reconnect() {
+        let context = ProcessingContext::default();
+        let server = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let reader = tcp_reader(&context, &server.local_addr().unwrap().to_string(), false);
+
+        // every connection is closed after one frame, so the reader has to reconnect
+        for i in 0..3 {
+            let (mut stream, _) = server.accept().unwrap();
+            send(&mut stream, i, false);
+            drop(stream);
+            assert_eq!(pull(&reader, i), (i as u8, sequence(i)));
+        }
+    }
+}
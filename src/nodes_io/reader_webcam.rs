@@ -1,6 +1,7 @@
 use crate::{
     pipeline_processing::{
         frame::{Frame, FrameInterpretation, Rgb},
+        metadata::{FrameMetadata, SensorSequence},
         node::{Caps, NodeID, ProcessingNode, Request},
         parametrizable::prelude::*,
         payload::Payload,
@@ -38,6 +39,7 @@ impl Parameterizable for WebcamInput {
     fn describe_parameters() -> ParametersDescriptor {
         ParametersDescriptor::new().with("device", Optional(NaturalWithZero()))
     }
+    fn describe_port_types() -> PortTypes { PortTypes::new().with_output(&[FrameType::Rgb]) }
     fn from_parameters(
         mut options: Parameters,
         _is_input_to: &[NodeID],
@@ -99,7 +101,8 @@ impl ProcessingNode for WebcamInput {
             }
         });
 
-        return Ok(Payload::from(Frame { storage: buffer, interp: self.interp }));
+        let metadata = FrameMetadata::new().with(SensorSequence(metadata.sequence as u64));
+        return Ok(Payload::from(Frame { storage: buffer, interp: self.interp, metadata }));
     }
 
     fn get_caps(&self) -> Caps { Caps { frame_count: None, random_access: false } }
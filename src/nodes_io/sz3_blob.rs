@@ -0,0 +1,241 @@
+//! A framed file format for SZ3 compressed frames.
+//!
+//! The file is a sequence of the records of the AXIOM raw container (see
+//! `axiom_container`), one per frame, without the file header and the index.
+//! The header of every record is yaml and contains the `SZ3Compressed`
+//! interpretation (so the inner interpretation, data type, dims and error
+//! bound) and the metadata of the frame. The reader finds the records by
+//! skipping from one to the next, so incomplete files can be read up to the
+//! last complete frame.
+
+use crate::{
+    nodes_io::axiom_container,
+    pipeline_processing::{
+        frame::{Frame, SZ3Compressed},
+        metadata::FrameMetadata,
+        node::{
+            Caps,
+            InputProcessingNode,
+            NodeID,
+            ProcessingNode,
+            ProgressUpdate,
+            Request,
+            SinkNode,
+        },
+        parametrizable::prelude::*,
+        payload::Payload,
+        processing_context::ProcessingContext,
+        puller::pull_ordered,
+    },
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct RecordHeader {
+    interp: SZ3Compressed,
+    metadata: FrameMetadata,
+}
+
+pub fn write_record(
+    writer: &mut impl Write,
+    interp: &SZ3Compressed,
+    metadata: &FrameMetadata,
+    data: &[u8],
+) -> Result<()> {
+    let header = serde_yaml::to_string(&RecordHeader {
+        interp: SZ3Compressed { compressed_size: data.len(), ..interp.clone() },
+        metadata: metadata.clone(),
+    })?;
+    axiom_container::write_record(writer, header.as_bytes(), data)?;
+    Ok(())
+}
+
+/// Finds the offsets of all complete records.
+pub fn find_records<R: Read + Seek>(reader: &mut R) -> Result<Vec<u64>> {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    axiom_container::scan_records(reader, 0, file_len)
+}
+
+/// Reads the record at `offset` and returns its interpretation, metadata and
+/// compressed data.
+pub fn read_record<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+) -> Result<(SZ3Compressed, FrameMetadata, Vec<u8>)> {
+    let (header, data_len) = axiom_container::read_record(reader, offset)?;
+    let header: RecordHeader = serde_yaml::from_slice(&header)
+        .with_context(|| format!("couldn't parse the header of the SZ3 frame at {offset}"))?;
+
+    let mut data = vec![0u8; data_len as usize];
+    reader.read_exact(&mut data)?;
+    Ok((header.interp, header.metadata, data))
+}
+
+pub struct SZ3BlobWriter {
+    file: Mutex<BufWriter<File>>,
+    input: InputProcessingNode,
+    number_of_frames: u64,
+    priority: u8,
+}
+impl Parameterizable for SZ3BlobWriter {
+    const DESCRIPTION: Option<&'static str> =
+        Some("writes SZ3 compressed frames together with everything needed to decompress them");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("path", Mandatory(PathParameter(PathCheck::Any)))
+            .with("input", Mandatory(NodeInputParameter))
+            .with("priority", Optional(U8()))
+            .with("number-of-frames", Optional(NaturalWithZero()))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::SZ3Compressed]).sink()
+    }
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        _context: &ProcessingContext,
+    ) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            file: Mutex::new(BufWriter::new(File::create(parameters.take::<String>("path")?)?)),
+            input: parameters.take("input")?,
+            number_of_frames: parameters.take("number-of-frames")?,
+            priority: parameters.take("priority")?,
+        })
+    }
+}
+
+#[async_trait]
+impl SinkNode for SZ3BlobWriter {
+    async fn run(
+        &self,
+        context: &ProcessingContext,
+        progress_callback: Arc<dyn Fn(ProgressUpdate) + Send + Sync>,
+    ) -> Result<()> {
+        let rx = pull_ordered(
+            context,
+            self.priority,
+            progress_callback,
+            self.input.clone_for_same_puller(),
+            self.number_of_frames,
+        );
+        while let Ok(payload) = rx.recv_async().await {
+            let frame = context
+                .ensure_cpu_buffer::<SZ3Compressed>(&payload)
+                .context("Wrong input format for SZ3BlobWriter")?;
+            let mut file = self.file.lock().unwrap();
+            frame
+                .storage
+                .as_slice(|data| write_record(&mut *file, &frame.interp, &frame.metadata, data))?;
+        }
+        self.file.lock().unwrap().flush()?;
+
+        Ok(())
+    }
+}
+
+pub struct SZ3BlobReader {
+    file: Mutex<BufReader<File>>,
+    offsets: Vec<u64>,
+    context: ProcessingContext,
+}
+impl Parameterizable for SZ3BlobReader {
+    const DESCRIPTION: Option<&'static str> = Some(
+        "read SZ3 compressed frames written by SZ3BlobWriter, use SZ3Decompress to decompress them",
+    );
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new().with("file", Mandatory(PathParameter(PathCheck::Exists)))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_output(&[FrameType::SZ3Compressed])
+    }
+    fn from_parameters(
+        mut options: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut file = BufReader::new(File::open(options.take::<String>("file")?)?);
+        let offsets = find_records(&mut file)?;
+        Ok(Self { file: Mutex::new(file), offsets, context: context.clone() })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for SZ3BlobReader {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let frame_number = request.frame_number();
+        let offset = *self.offsets.get(frame_number as usize).ok_or_else(|| {
+            anyhow!(
+                "frame {} was requested but this stream only has a length of {}",
+                frame_number,
+                self.offsets.len()
+            )
+        })?;
+
+        let (interp, metadata, data) = read_record(&mut *self.file.lock().unwrap(), offset)?;
+        let storage = unsafe {
+            let mut buffer = self.context.get_uninit_cpu_buffer(data.len());
+            buffer.as_mut_slice(|buffer| buffer.copy_from_slice(&data));
+            buffer
+        };
+
+        Ok(Payload::from(Frame { interp, storage, metadata }))
+    }
+
+    fn get_caps(&self) -> Caps {
+        Caps { frame_count: Some(self.offsets.len() as u64), random_access: true }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline_processing::{
+        frame::{FrameInterpretations, Rgb, SZ3DataType, SZ3ErrorBound},
+        metadata::SensorSequence,
+    };
+    use std::io::Cursor;
+
+    #[test]
+    fn test_record_roundtrip() {
+        let interp = SZ3Compressed {
+            inner: FrameInterpretations::Rgb(Rgb { width: 16, height: 8, fps: 24.0 }),
+            data_type: SZ3DataType::F32,
+            dims: vec![3, 16, 8],
+            error_bound: SZ3ErrorBound::Absolute(0.5),
+            compressed_size: 0,
+        };
+
+        let mut file = Cursor::new(Vec::new());
+        for i in 0..3u8 {
+            let metadata = FrameMetadata::new().with(SensorSequence(i as u64));
+            write_record(&mut file, &interp, &metadata, &vec![i; 10 + i as usize]).unwrap();
+        }
+        // a record that was only partially written
+        let len = file.get_ref().len();
+        file.get_mut().truncate(len - 3);
+
+        let offsets = find_records(&mut file).unwrap();
+        assert_eq!(offsets.len(), 2);
+        for (i, offset) in offsets.into_iter().enumerate() {
+            let (read_interp, metadata, data) = read_record(&mut file, offset).unwrap();
+            assert_eq!(read_interp, SZ3Compressed { compressed_size: 10 + i, ..interp.clone() });
+            assert_eq!(metadata.get::<SensorSequence>(), Some(SensorSequence(i as u64)));
+            assert_eq!(data, vec![i as u8; 10 + i]);
+        }
+    }
+}
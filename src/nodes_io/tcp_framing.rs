@@ -0,0 +1,127 @@
+//! The framed protocol used by `TcpReader` and `TcpWriter`.
+//!
+//! Every frame is preceded by a header consisting of `FRAME_MAGIC`, the
+//! sequence number of the frame as `u64`, the length of the frame data as
+//! `u32` and the CRC-32 (IEEE) of the frame data as `u32`, all little endian.
+//! The magic allows the reader to resynchronize if bytes get lost, the sequence
+//! number to detect dropped frames and the checksum to detect corrupted ones.
+
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+pub const FRAME_MAGIC: [u8; 4] = *b"AXFS";
+pub const HEADER_LEN: usize = 4 + 8 + 4 + 4;
+
+pub fn encode_header(sequence: u64, data: &[u8]) -> [u8; HEADER_LEN] {
+    let mut header = [0u8; HEADER_LEN];
+    header[0..4].copy_from_slice(&FRAME_MAGIC);
+    header[4..12].copy_from_slice(&sequence.to_le_bytes());
+    header[12..16].copy_from_slice(&(data.len() as u32).to_le_bytes());
+    header[16..20].copy_from_slice(&crc32(data).to_le_bytes());
+    header
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FramedRead {
+    Frame {
+        sequence: u64,
+        data: Vec<u8>,
+        skipped_bytes: usize,
+    },
+    /// the frame was received, but had the wrong length or checksum
+    Corrupted {
+        sequence: u64,
+        skipped_bytes: usize,
+    },
+}
+
+/// Reads the next frame, skipping everything until the next frame magic.
+pub async fn read_frame(
+    reader: &mut (impl AsyncRead + Unpin),
+    frame_bytes: usize,
+) -> io::Result<FramedRead> {
+    let mut window = [0u8; 4];
+    reader.read_exact(&mut window).await?;
+    let mut skipped_bytes = 0;
+    while window != FRAME_MAGIC {
+        window.copy_within(1.., 0);
+        window[3] = reader.read_u8().await?;
+        skipped_bytes += 1;
+    }
+
+    let sequence = reader.read_u64_le().await?;
+    let len = reader.read_u32_le().await? as usize;
+    let crc = reader.read_u32_le().await?;
+    if len != frame_bytes {
+        // the header itself is corrupted, so don't trust the length and resync
+        // at the next magic instead
+        return Ok(FramedRead::Corrupted { sequence, skipped_bytes });
+    }
+
+    let mut data = vec![0u8; len];
+    reader.read_exact(&mut data).await?;
+    if crc32(&data) != crc {
+        return Ok(FramedRead::Corrupted { sequence, skipped_bytes });
+    }
+    Ok(FramedRead::Frame { sequence, data, skipped_bytes })
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+pub fn crc32(data: &[u8]) -> u32 {
+    !data
+        .iter()
+        .fold(!0u32, |crc, byte| CRC32_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    }
+
+    #[test]
+    fn test_resync_after_corruption() {
+        let frames: Vec<Vec<u8>> = (0..3u8).map(|i| vec![i; 16]).collect();
+        let mut stream = Vec::new();
+        for (sequence, frame) in frames.iter().enumerate() {
+            stream.extend_from_slice(&encode_header(sequence as u64, frame));
+            stream.extend_from_slice(frame);
+        }
+        // a flipped bit in the data of the second frame and some garbage before the
+        // third
+        stream[2 * HEADER_LEN + 16 + 3] ^= 0x10;
+        stream.splice(2 * HEADER_LEN + 32..2 * HEADER_LEN + 32, [0xAA, 0xBB, 0xCC]);
+
+        let mut reader = &stream[..];
+        let mut read = || block_on(read_frame(&mut reader, 16));
+        assert_eq!(
+            read().unwrap(),
+            FramedRead::Frame { sequence: 0, data: frames[0].clone(), skipped_bytes: 0 }
+        );
+        assert_eq!(read().unwrap(), FramedRead::Corrupted { sequence: 1, skipped_bytes: 0 });
+        assert_eq!(
+            read().unwrap(),
+            FramedRead::Frame { sequence: 2, data: frames[2].clone(), skipped_bytes: 3 }
+        );
+        assert_eq!(read().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{
+    nodes_io::axiom_container::{container_frame_parts, ContainerWriter},
+    pipeline_processing::{
+        node::{InputProcessingNode, NodeID, ProgressUpdate, SinkNode},
+        parametrizable::prelude::*,
+        processing_context::ProcessingContext,
+        puller::pull_ordered,
+    },
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::{
+    fs::File,
+    io::BufWriter,
+    sync::{Arc, Mutex},
+};
+
+
+pub struct AxiomContainerWriter {
+    file: Mutex<Option<File>>,
+    input: InputProcessingNode,
+    number_of_frames: u64,
+    priority: u8,
+}
+impl Parameterizable for AxiomContainerWriter {
+    const DESCRIPTION: Option<&'static str> = Some(
+        "writes frames together with their interpretation and metadata into a single AXIOM raw \
+         container file",
+    );
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("path", Mandatory(PathParameter(PathCheck::Any)))
+            .with("input", Mandatory(NodeInputParameter))
+            .with("priority", Optional(U8()))
+            .with("number-of-frames", Optional(NaturalWithZero()))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new()
+            .with_input(
+                "input",
+                &[FrameType::Raw, FrameType::Rgb, FrameType::Rgba, FrameType::LosslessCompressed],
+            )
+            .sink()
+    }
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        _context: &ProcessingContext,
+    ) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            file: Mutex::new(Some(File::create(parameters.take::<String>("path")?)?)),
+            input: parameters.take("input")?,
+            number_of_frames: parameters.take("number-of-frames")?,
+            priority: parameters.take("priority")?,
+        })
+    }
+}
+
+#[async_trait]
+impl SinkNode for AxiomContainerWriter {
+    async fn run(
+        &self,
+        context: &ProcessingContext,
+        progress_callback: Arc<dyn Fn(ProgressUpdate) + Send + Sync>,
+    ) -> Result<()> {
+        let file = self
+            .file
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| anyhow!("AxiomContainerWriter can only be run once"))?;
+
+        let rx = pull_ordered(
+            context,
+            self.priority,
+            progress_callback,
+            self.input.clone_for_same_puller(),
+            self.number_of_frames,
+        );
+
+        // the header needs the interpretation, so it is written with the first frame
+        let mut file = Some(file);
+        let mut writer = None;
+        while let Ok(payload) = rx.recv_async().await {
+            let (interp, buffer, metadata) = container_frame_parts(context, &payload)?;
+            if writer.is_none() {
+                writer = Some(ContainerWriter::new(BufWriter::new(file.take().unwrap()), interp)?);
+            }
+            let writer = writer.as_mut().unwrap();
+            buffer.as_slice(|slice| writer.write_frame(&interp, slice, &metadata))?;
+        }
+
+        if let Some(writer) = writer {
+            writer.finish()?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use crate::pipeline_processing::{
     buffers::CpuBuffer,
-    frame::Raw,
+    frame::{Frame, Raw},
+    metadata::Timecode,
     node::{InputProcessingNode, NodeID, ProgressUpdate, SinkNode},
     parametrizable::prelude::*,
     processing_context::ProcessingContext,
@@ -41,12 +42,15 @@ impl Parameterizable for CinemaDngWriter {
     fn describe_parameters() -> ParametersDescriptor {
         ParametersDescriptor::new()
             .with("input", Mandatory(NodeInputParameter))
-            .with("path", Mandatory(StringParameter))
+            .with("path", Mandatory(PathParameter(PathCheck::Any)))
             .with("priority", Optional(U8()))
             .with("number-of-frames", Optional(NaturalWithZero()))
-            .with("dcp-yaml", Optional(StringParameter))
+            .with("dcp-yaml", Optional(PathParameter(PathCheck::Exists)))
             .with("exists-ok?", Optional(Bool()))
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Raw]).sink()
+    }
 
     fn from_parameters(
         mut parameters: Parameters,
@@ -110,32 +114,7 @@ impl SinkNode for CinemaDngWriter {
                     .context("Wrong input format for CinemaDngWriter")?;
 
 
-                let mut ifd = Ifd::new(IfdType::Ifd);
-                ifd.insert_from_other(base_ifd.clone());
-
-                ifd.insert(tags::ifd::ImageWidth, frame.interp.width as u32);
-                ifd.insert(tags::ifd::ImageLength, frame.interp.height as u32);
-                ifd.insert(tags::ifd::RowsPerStrip, frame.interp.height as u32);
-                ifd.insert(
-                    tags::ifd::FrameRate,
-                    IfdValue::SRational((frame.interp.fps * 10000.0) as i32, 10000),
-                );
-                ifd.insert(tags::ifd::BitsPerSample, frame.interp.bit_depth as u32);
-                ifd.insert(
-                    tags::ifd::CFAPattern,
-                    match (frame.interp.cfa.red_in_first_row, frame.interp.cfa.red_in_first_col) {
-                        (true, true) => [0u8, 1, 1, 2],
-                        (true, false) => [1, 0, 2, 1],
-                        (false, true) => [1, 2, 0, 1],
-                        (false, false) => [2, 1, 1, 0],
-                    },
-                );
-
-                ifd.insert(
-                    tags::ifd::StripOffsets,
-                    IfdValue::Offsets(Arc::new(frame.storage.clone())),
-                );
-                ifd.insert(tags::ifd::StripByteCounts, frame.storage.len() as u32);
+                let ifd = frame_to_ifd(&base_ifd, &frame)?;
 
                 let file = File::create(format!("{}/{:06}.dng", &dir_path, frame_number))?;
                 DngWriter::write_dng(file, true, FileType::Dng, vec![ifd])?;
@@ -147,6 +126,81 @@ impl SinkNode for CinemaDngWriter {
     }
 }
 
+/// Builds the ifd of a single DNG file for `frame` on top of `base_ifd`.
+/// The frame metadata is stored as yaml in the `ImageDescription` tag and the
+/// timecode additionally in the `TimeCodes` tag.
+pub(crate) fn frame_to_ifd(base_ifd: &Ifd, frame: &Frame<Raw, CpuBuffer>) -> Result<Ifd> {
+    let mut ifd = Ifd::new(IfdType::Ifd);
+    ifd.insert_from_other(base_ifd.clone());
+
+    ifd.insert(tags::ifd::ImageWidth, frame.interp.width as u32);
+    ifd.insert(tags::ifd::ImageLength, frame.interp.height as u32);
+    ifd.insert(tags::ifd::RowsPerStrip, frame.interp.height as u32);
+    ifd.insert(
+        tags::ifd::FrameRate,
+        IfdValue::SRational((frame.interp.fps * 10000.0) as i32, 10000),
+    );
+    ifd.insert(tags::ifd::BitsPerSample, frame.interp.bit_depth as u32);
+    ifd.insert(
+        tags::ifd::CFAPattern,
+        match (frame.interp.cfa.red_in_first_row, frame.interp.cfa.red_in_first_col) {
+            (true, true) => [0u8, 1, 1, 2],
+            (true, false) => [1, 0, 2, 1],
+            (false, true) => [1, 2, 0, 1],
+            (false, false) => [2, 1, 1, 0],
+        },
+    );
+    ifd.insert(tags::ifd::BlackLevelRepeatDim, [2u16, 2]);
+    ifd.insert(tags::ifd::BlackLevel, frame.interp.black_level);
+    ifd.insert(tags::ifd::WhiteLevel, frame.interp.white_level);
+
+    if !frame.metadata.is_empty() {
+        ifd.insert(
+            tags::ifd::ImageDescription,
+            IfdValue::Ascii(serde_yaml::to_string(&frame.metadata)?),
+        );
+    }
+    if let Some(timecode) = frame.metadata.get::<Timecode>() {
+        ifd.insert(tags::ifd::TimeCodes, timecode_to_smpte(timecode));
+    }
+
+    ifd.insert(tags::ifd::StripOffsets, IfdValue::Offsets(Arc::new(frame.storage.clone())));
+    ifd.insert(tags::ifd::StripByteCounts, frame.storage.len() as u32);
+
+    Ok(ifd)
+}
+
+fn to_bcd(value: u8) -> u8 { ((value / 10) << 4) | (value % 10) }
+fn from_bcd(value: u8) -> u8 { (value >> 4) * 10 + (value & 0xf) }
+
+/// The SMPTE 12M binary representation used by the DNG `TimeCodes` tag
+/// (frames, seconds, minutes, hours as BCD, followed by four bytes of user
+/// data)
+fn timecode_to_smpte(timecode: Timecode) -> [u8; 8] {
+    [
+        to_bcd(timecode.frames) & 0x3f,
+        to_bcd(timecode.seconds) & 0x7f,
+        to_bcd(timecode.minutes) & 0x7f,
+        to_bcd(timecode.hours) & 0x3f,
+        0,
+        0,
+        0,
+        0,
+    ]
+}
+
+pub(crate) fn timecode_from_smpte(smpte: &[u8]) -> Option<Timecode> {
+    match smpte {
+        [frames, seconds, minutes, hours, ..] => Some(Timecode {
+            hours: from_bcd(hours & 0x3f),
+            minutes: from_bcd(minutes & 0x7f),
+            seconds: from_bcd(seconds & 0x7f),
+            frames: from_bcd(frames & 0x3f),
+        }),
+        _ => None,
+    }
+}
+
 impl Offsets for CpuBuffer {
     fn size(&self) -> u32 { self.len() as u32 }
     fn write(&self, writer: &mut dyn Write) -> std::io::Result<()> {
@@ -27,6 +27,9 @@ impl Parameterizable for FfmpegWriter {
             .with("priority", Optional(U8()))
             .with("input-options", Optional(StringParameter))
     }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_input("input", &[FrameType::Rgb]).sink()
+    }
     fn from_parameters(
         mut parameters: Parameters,
         _is_input_to: &[NodeID],
@@ -1,4 +1,5 @@
 use crate::pipeline_processing::{
+    metadata::FrameMetadata,
     node::{InputProcessingNode, NodeID, ProgressUpdate, SinkNode},
     parametrizable::prelude::*,
     processing_context::ProcessingContext,
@@ -15,6 +16,7 @@ use std::{
 
 pub struct RawBlobWriter {
     file: Arc<Mutex<File>>,
+    metadata_file: Arc<Mutex<File>>,
     input: InputProcessingNode,
     number_of_frames: u64,
     priority: u8,
@@ -22,11 +24,12 @@ pub struct RawBlobWriter {
 impl Parameterizable for RawBlobWriter {
     fn describe_parameters() -> ParametersDescriptor {
         ParametersDescriptor::new()
-            .with("path", Mandatory(StringParameter))
+            .with("path", Mandatory(PathParameter(PathCheck::Any)))
             .with("input", Mandatory(NodeInputParameter))
             .with("priority", Optional(U8()))
             .with("number-of-frames", Optional(NaturalWithZero()))
     }
+    fn describe_port_types() -> PortTypes { PortTypes::new().sink() }
     fn from_parameters(
         mut parameters: Parameters,
         _is_input_to: &[NodeID],
@@ -35,8 +38,10 @@ impl Parameterizable for RawBlobWriter {
     where
         Self: Sized,
     {
+        let path = parameters.take::<String>("path")?;
         Ok(Self {
-            file: Arc::new(Mutex::new(File::create(parameters.take::<String>("path")?)?)),
+            file: Arc::new(Mutex::new(File::create(&path)?)),
+            metadata_file: Arc::new(Mutex::new(File::create(format!("{path}.meta.yml"))?)),
             input: parameters.take("input")?,
             number_of_frames: parameters.take("number-of-frames")?,
             priority: parameters.take("priority")?,
@@ -61,6 +66,10 @@ impl SinkNode for RawBlobWriter {
         while let Ok(payload) = rx.recv_async().await {
             let buffer = context.ensure_any_cpu_buffer(&payload)?;
             buffer.as_slice(|slice| self.file.lock().unwrap().write_all(slice))?;
+
+            // the sidecar gets one yaml document per frame, in the same order as the frames
+            let metadata = FrameMetadata::of_payload(&payload).to_yaml_document()?;
+            self.metadata_file.lock().unwrap().write_all(metadata.as_bytes())?;
         }
 
         Ok(())
@@ -76,11 +85,12 @@ pub struct RawDirectoryWriter {
 impl Parameterizable for RawDirectoryWriter {
     fn describe_parameters() -> ParametersDescriptor {
         ParametersDescriptor::new()
-            .with("path", Mandatory(StringParameter))
+            .with("path", Mandatory(PathParameter(PathCheck::NotExists)))
             .with("input", Mandatory(NodeInputParameter))
             .with("priority", Optional(U8()))
             .with("number-of-frames", Optional(NaturalWithZero()))
     }
+    fn describe_port_types() -> PortTypes { PortTypes::new().sink() }
 
     fn from_parameters(
         mut parameters: Parameters,
@@ -119,6 +129,13 @@ impl SinkNode for RawDirectoryWriter {
                 let buffer = context_clone.ensure_any_cpu_buffer(&payload)?;
                 let mut file = File::create(format!("{}/{:06}.data", &dir_path, frame_number))?;
                 buffer.as_slice(|slice| file.write_all(slice))?;
+
+                let metadata = FrameMetadata::of_payload(&payload);
+                if !metadata.is_empty() {
+                    let mut file =
+                        File::create(format!("{}/{:06}.meta.yml", &dir_path, frame_number))?;
+                    file.write_all(serde_yaml::to_string(&metadata)?.as_bytes())?;
+                }
                 Ok(())
             },
         )
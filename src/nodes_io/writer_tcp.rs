@@ -0,0 +1,283 @@
+use crate::{
+    nodes_io::tcp_framing::encode_header,
+    pipeline_processing::{
+        node::{InputProcessingNode, NodeID, ProgressUpdate, SinkNode},
+        parametrizable::prelude::*,
+        processing_context::ProcessingContext,
+        puller::pull_ordered,
+    },
+};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpListener, TcpStream},
+};
+
+/// A sink that serves its input to every client connected to it
+pub struct TcpWriter {
+    input: InputProcessingNode,
+    priority: u8,
+    number_of_frames: u64,
+    address: String,
+    framed: bool,
+    block_when_slow: bool,
+    queue_length: usize,
+}
+impl Parameterizable for TcpWriter {
+    const DESCRIPTION: Option<&'static str> = Some(
+        "serves frames to all clients connected to the address, for example a TcpReader on \
+         another machine",
+    );
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("input", Mandatory(NodeInputParameter))
+            .with("priority", Optional(U8()))
+            .with("number-of-frames", Optional(NaturalWithZero()))
+            .with("address", Mandatory(StringParameter))
+            .with("framed", Optional(BoolParameter))
+            .with("when-slow", Optional(ChoiceParameter(vec!["drop", "block"])))
+            .with("queue-length", WithDefault(NaturalGreaterZero(), IntRangeValue(4)))
+    }
+    fn describe_port_types() -> PortTypes { PortTypes::new().sink() }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        _context: &ProcessingContext,
+    ) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            input: parameters.take("input")?,
+            priority: parameters.take("priority")?,
+            number_of_frames: parameters.take("number-of-frames")?,
+            address: parameters.take("address")?,
+            framed: parameters.take("framed")?,
+            block_when_slow: parameters.take::<String>("when-slow")? == "block",
+            queue_length: parameters.take("queue-length")?,
+        })
+    }
+}
+
+#[async_trait]
+impl SinkNode for TcpWriter {
+    async fn run(
+        &self,
+        context: &ProcessingContext,
+        progress_callback: Arc<dyn Fn(ProgressUpdate) + Send + Sync>,
+    ) -> Result<()> {
+        let listener = TcpListener::bind(&self.address)
+            .await
+            .with_context(|| format!("couldn't listen on {}", self.address))?;
+        eprintln!("serving frames on {}", listener.local_addr()?);
+
+        let broadcaster = FrameBroadcaster::new(self.queue_length, self.block_when_slow);
+        let accept_task = context.spawn_io(broadcaster.clone().accept_clients(listener));
+
+        let rx = pull_ordered(
+            context,
+            self.priority,
+            progress_callback,
+            self.input.clone_for_same_puller(),
+            self.number_of_frames,
+        );
+        let mut sequence = 0;
+        while let Ok(payload) = rx.recv_async().await {
+            let buffer = context.ensure_any_cpu_buffer(&payload)?;
+            let message = buffer.as_slice(|data| {
+                if self.framed {
+                    [&encode_header(sequence, data)[..], data].concat()
+                } else {
+                    data.to_vec()
+                }
+            });
+            broadcaster.broadcast(sequence, Arc::new(message)).await;
+            sequence += 1;
+        }
+
+        accept_task.abort();
+        Ok(())
+    }
+}
+
+/// Sends every message to all connected clients. Every client has its own
+/// queue, if it is full the message is either dropped for this client or the
+/// broadcast waits until there is space again.
+#[derive(Clone)]
+pub struct FrameBroadcaster {
+    clients: Arc<Mutex<Vec<flume::Sender<Arc<Vec<u8>>>>>>,
+    queue_length: usize,
+    block_when_slow: bool,
+}
+
+impl FrameBroadcaster {
+    pub fn new(queue_length: usize, block_when_slow: bool) -> Self {
+        Self { clients: Default::default(), queue_length, block_when_slow }
+    }
+
+    pub fn client_count(&self) -> usize { self.clients.lock().unwrap().len() }
+
+    pub async fn accept_clients(self, listener: TcpListener) {
+        loop {
+            match listener.accept().await {
+                Ok((stream, address)) => {
+                    eprintln!("client {address} connected");
+                    let (tx, rx) = flume::bounded(self.queue_length);
+                    self.clients.lock().unwrap().push(tx);
+                    tokio::spawn(send_to_client(stream, address, rx));
+                }
+                Err(e) => eprintln!("couldn't accept client: {e}"),
+            }
+        }
+    }
+
+    pub async fn broadcast(&self, sequence: u64, message: Arc<Vec<u8>>) {
+        // the lock can't be held while waiting for a slow client
+        let clients = self.clients.lock().unwrap().clone();
+        for client in clients {
+            if self.block_when_slow {
+                let _ = client.send_async(message.clone()).await;
+            } else if let Err(flume::TrySendError::Full(_)) = client.try_send(message.clone()) {
+                eprintln!("client is too slow, dropped frame {sequence} for it");
+            }
+        }
+        self.clients.lock().unwrap().retain(|client| !client.is_disconnected());
+    }
+}
+
+async fn send_to_client(
+    mut stream: TcpStream,
+    address: SocketAddr,
+    rx: flume::Receiver<Arc<Vec<u8>>>,
+) {
+    while let Ok(message) = rx.recv_async().await {
+        if let Err(e) = stream.write_all(&message).await {
+            eprintln!("client {address} disconnected: {e}");
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        nodes_io::{
+            reader_tcp::TcpReader,
+            tcp_framing::{read_frame, FramedRead},
+        },
+        nodes_util::test_pattern::TestPattern,
+        pipeline_processing::{
+            frame::Raw,
+            metadata::SensorSequence,
+            node::{ProcessingNode, Request},
+            test_util::{create, spawn_graph},
+        },
+    };
+    use futures::executor::block_on;
+    use std::time::Duration;
+
+    #[test]
+    fn test_loopback() {
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            let broadcaster = FrameBroadcaster::new(2, true);
+            tokio::spawn(broadcaster.clone().accept_clients(listener));
+
+            let mut clients = [
+                TcpStream::connect(address).await.unwrap(),
+                TcpStream::connect(address).await.unwrap(),
+            ];
+            while broadcaster.client_count() < clients.len() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+
+            for sequence in 0..5u64 {
+                let data = vec![sequence as u8; 8];
+                let message = [&encode_header(sequence, &data)[..], &data].concat();
+                broadcaster.broadcast(sequence, Arc::new(message)).await;
+            }
+
+            for client in &mut clients {
+                for sequence in 0..5u64 {
+                    assert_eq!(
+                        read_frame(client, 8).await.unwrap(),
+                        FramedRead::Frame {
+                            sequence,
+                            data: vec![sequence as u8; 8],
+                            skipped_bytes: 0
+                        }
+                    );
+                }
+            }
+
+            // disconnected clients are removed
+            drop(clients);
+            let data = Arc::new(vec![0; 8]);
+            for sequence in 5.. {
+                broadcaster.broadcast(sequence, data.clone()).await;
+                if broadcaster.client_count() == 0 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+    }
+
+    fn pull_raw(
+        context: &ProcessingContext,
+        node: &dyn ProcessingNode,
+        frame: u64,
+    ) -> (Vec<u8>, Option<SensorSequence>) {
+        let payload = block_on(node.pull(Request::new(0, frame))).unwrap();
+        let frame = context.ensure_cpu_buffer::<Raw>(&payload).unwrap();
+        (frame.storage.as_slice(|data| data.to_vec()), frame.metadata.get())
+    }
+
+    #[test]
+    fn test_writer_to_reader() {
+        let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let (context, run) = spawn_graph(&format!(
+            "
+            pattern: {{ type: TestPattern, width: 16, height: 8 }}
+            writer: {{ type: TcpWriter, input: <pattern, address: '{address}', framed: true }}
+            "
+        ));
+
+        let size = || [("width", IntRangeValue(16)), ("height", IntRangeValue(8))];
+        let pattern: TestPattern = create(&context, size());
+        let reader: TcpReader = create(
+            &context,
+            size().into_iter().chain([
+                ("address", StringValue(address.to_string())),
+                ("framed", BoolValue(true)),
+                ("reconnect", BoolValue(true)),
+                ("reconnect-interval", DurationValue(Duration::from_millis(10))),
+                ("bit-depth", IntRangeValue(8)),
+            ]),
+        );
+
+        // the frames sent before the reader connected are lost, so the sequence
+        // number tells which frame of the pattern was received
+        let mut last_sequence = None;
+        for frame in 0..5 {
+            let (data, sequence) = pull_raw(&context, &reader, frame);
+            let SensorSequence(sequence) = sequence.unwrap();
+            assert!(last_sequence < Some(sequence));
+            assert_eq!(data, pull_raw(&context, &pattern, sequence).0);
+            last_sequence = Some(sequence);
+        }
+
+        drop(reader);
+        context.run_control().stop().unwrap();
+        run.join().unwrap().unwrap();
+    }
+}
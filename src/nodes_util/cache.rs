@@ -25,6 +25,7 @@ impl Parameterizable for Cache {
             .with("input", Mandatory(NodeInputParameter))
             .with("size", Optional(NaturalGreaterZero()))
     }
+    fn describe_port_types() -> PortTypes { PortTypes::new().with_output_of("input") }
 
     fn from_parameters(
         mut parameters: Parameters,
@@ -0,0 +1,150 @@
+use crate::pipeline_processing::{
+    node::{Caps, EOFError, InputProcessingNode, NodeID, ProcessingNode, Request},
+    parametrizable::prelude::*,
+    payload::Payload,
+    processing_context::ProcessingContext,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+
+/// Plays the inputs one after another. All inputs except the last one need a
+/// known number of frames.
+pub struct Concat {
+    inputs: Vec<InputProcessingNode>,
+    /// the output frame number at which each input starts
+    offsets: Vec<u64>,
+}
+
+impl Parameterizable for Concat {
+    const DESCRIPTION: Option<&'static str> = Some("play the inputs back to back");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("inputs", Mandatory(ListParameter(Box::new(NodeInputParameter))))
+    }
+    fn describe_port_types() -> PortTypes { PortTypes::new().with_output_of("inputs") }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        _context: &ProcessingContext,
+    ) -> Result<Self> {
+        let inputs = parameters.take_vec::<InputProcessingNode>("inputs")?;
+        if inputs.is_empty() {
+            return Err(anyhow!("Concat needs at least one input"));
+        }
+        let offsets = start_offsets(&frame_counts(&inputs))
+            .map_err(|i| anyhow!("input {i} of Concat needs a known number of frames"))?;
+        Ok(Self { inputs, offsets })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for Concat {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let frame_number = request.frame_number();
+        let (index, offset) = self
+            .offsets
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, offset)| **offset <= frame_number)
+            .ok_or(EOFError)?;
+        if let Some(frame_count) = self.inputs[index].get_caps().frame_count {
+            if frame_number - offset >= frame_count {
+                return Err(EOFError.into());
+            }
+        }
+        self.inputs[index].pull(request.with_frame_number(frame_number - offset)).await
+    }
+
+    fn get_caps(&self) -> Caps {
+        Caps {
+            frame_count: frame_counts(&self.inputs).into_iter().sum(),
+            random_access: self.inputs.iter().all(|input| input.get_caps().random_access),
+        }
+    }
+}
+
+fn frame_counts(inputs: &[InputProcessingNode]) -> Vec<Option<u64>> {
+    inputs.iter().map(|input| input.get_caps().frame_count).collect()
+}
+
+/// The frame number at which each input starts. Only the last input may have an
+/// unknown number of frames, otherwise the index of the first input without
+/// one is returned.
+fn start_offsets(frame_counts: &[Option<u64>]) -> Result<Vec<u64>, usize> {
+    let mut offset = 0;
+    let mut offsets = Vec::with_capacity(frame_counts.len());
+    for (i, frame_count) in frame_counts.iter().enumerate() {
+        offsets.push(offset);
+        match frame_count {
+            Some(frame_count) => offset += frame_count,
+            None if i == frame_counts.len() - 1 => {}
+            None => return Err(i),
+        }
+    }
+    Ok(offsets)
+}
+
+
+/// Takes one frame of every input in turn, frame `n` of the output is frame
+/// `n / number of inputs` of input `n % number of inputs`.
+pub struct Interleave {
+    inputs: Vec<InputProcessingNode>,
+}
+
+impl Parameterizable for Interleave {
+    const DESCRIPTION: Option<&'static str> = Some("alternate between the frames of the inputs");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("inputs", Mandatory(ListParameter(Box::new(NodeInputParameter))))
+    }
+    fn describe_port_types() -> PortTypes { PortTypes::new().with_output_of("inputs") }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        _context: &ProcessingContext,
+    ) -> Result<Self> {
+        let inputs = parameters.take_vec::<InputProcessingNode>("inputs")?;
+        if inputs.is_empty() {
+            return Err(anyhow!("Interleave needs at least one input"));
+        }
+        Ok(Self { inputs })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for Interleave {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let frame_number = request.frame_number();
+        let count = self.inputs.len() as u64;
+        let input = &self.inputs[(frame_number % count) as usize];
+        input.pull(request.with_frame_number(frame_number / count)).await
+    }
+
+    fn get_caps(&self) -> Caps {
+        // stops as soon as the shortest input is exhausted
+        let shortest = frame_counts(&self.inputs).into_iter().flatten().min();
+        Caps {
+            frame_count: shortest.map(|count| count * self.inputs.len() as u64),
+            random_access: self.inputs.iter().all(|input| input.get_caps().random_access),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_offsets() {
+        assert_eq!(start_offsets(&[Some(10), Some(5), Some(3)]), Ok(vec![0, 10, 15]));
+        assert_eq!(start_offsets(&[Some(10), None]), Ok(vec![0, 10]));
+        assert_eq!(start_offsets(&[Some(10), None, Some(3)]), Err(1));
+        assert_eq!(start_offsets(&[]), Ok(vec![]));
+    }
+}
@@ -1,2 +1,8 @@
 pub mod cache;
+pub mod concat;
+pub mod range;
+pub mod record_gate;
+pub mod remap;
+pub mod retime;
 pub mod split;
+pub mod test_pattern;
@@ -0,0 +1,273 @@
+use crate::{
+    nodes_util::remap::RemappedInput,
+    pipeline_processing::{
+        node::{Caps, EOFError, InputProcessingNode, NodeID, ProcessingNode, Request},
+        parametrizable::prelude::*,
+        payload::Payload,
+        processing_context::ProcessingContext,
+    },
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+
+/// Selects the frames `start..end` of the input, which are renumbered to start
+/// at zero.
+pub struct Trim {
+    input: RemappedInput,
+    start: u64,
+    end: Option<u64>,
+}
+
+impl Parameterizable for Trim {
+    const DESCRIPTION: Option<&'static str> =
+        Some("only pass the frames from start to end (exclusive, 0 for the end of the input)");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("input", Mandatory(NodeInputParameter))
+            .with("start", Optional(NaturalWithZero()))
+            .with("end", Optional(NaturalWithZero()))
+    }
+    fn describe_port_types() -> PortTypes { PortTypes::new().with_output_of("input") }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        _context: &ProcessingContext,
+    ) -> Result<Self> {
+        let start: u64 = parameters.take("start")?;
+        let end = match parameters.take::<u64>("end")? {
+            0 => None,
+            end if end <= start => {
+                return Err(anyhow!("the end ({end}) has to be after the start ({start})"))
+            }
+            end => Some(end),
+        };
+        Ok(Self { input: RemappedInput::new(parameters.take("input")?), start, end })
+    }
+}
+
+impl Trim {
+    fn frame_count(&self, input_frame_count: Option<u64>) -> Option<u64> {
+        let end = match (input_frame_count, self.end) {
+            (Some(count), Some(end)) => Some(count.min(end)),
+            (count, end) => count.or(end),
+        };
+        end.map(|end| end.saturating_sub(self.start))
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for Trim {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let frame_number = request.frame_number();
+        if let Some(frame_count) = self.frame_count(None) {
+            if frame_number >= frame_count {
+                return Err(EOFError.into());
+            }
+        }
+        self.input.pull(request, self.start + frame_number).await
+    }
+
+    fn get_caps(&self) -> Caps {
+        let caps = self.input.get_caps();
+        Caps { frame_count: self.frame_count(caps.frame_count), ..caps }
+    }
+}
+
+
+/// Passes every `step`th frame beginning with `offset`.
+pub struct Stride {
+    input: RemappedInput,
+    step: u64,
+    offset: u64,
+}
+
+impl Parameterizable for Stride {
+    const DESCRIPTION: Option<&'static str> = Some("only pass every n-th frame");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("input", Mandatory(NodeInputParameter))
+            .with("step", Mandatory(NaturalGreaterZero()))
+            .with("offset", Optional(NaturalWithZero()))
+    }
+    fn describe_port_types() -> PortTypes { PortTypes::new().with_output_of("input") }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        _context: &ProcessingContext,
+    ) -> Result<Self> {
+        Ok(Self {
+            input: RemappedInput::new(parameters.take("input")?),
+            step: parameters.take("step")?,
+            offset: parameters.take("offset")?,
+        })
+    }
+}
+
+impl Stride {
+    fn frame_count(&self, input_frame_count: u64) -> u64 {
+        let remaining = input_frame_count.saturating_sub(self.offset);
+        (remaining + self.step - 1) / self.step
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for Stride {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let input_frame = self.offset + request.frame_number() * self.step;
+        self.input.pull(request, input_frame).await
+    }
+
+    fn get_caps(&self) -> Caps {
+        let caps = self.input.get_caps();
+        Caps { frame_count: caps.frame_count.map(|count| self.frame_count(count)), ..caps }
+    }
+}
+
+
+/// Plays the input backwards, which needs random access to all its frames.
+pub struct Reverse {
+    input: InputProcessingNode,
+    frame_count: u64,
+}
+
+impl Parameterizable for Reverse {
+    const DESCRIPTION: Option<&'static str> = Some("play the input backwards");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new().with("input", Mandatory(NodeInputParameter))
+    }
+    fn describe_port_types() -> PortTypes { PortTypes::new().with_output_of("input") }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        _context: &ProcessingContext,
+    ) -> Result<Self> {
+        let input: InputProcessingNode = parameters.take("input")?;
+        let frame_count = random_access_frame_count(&input, "Reverse")?;
+        Ok(Self { input, frame_count })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for Reverse {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let frame_number = request.frame_number();
+        if frame_number >= self.frame_count {
+            return Err(EOFError.into());
+        }
+        let input_frame = self.frame_count - 1 - frame_number;
+        self.input.pull(request.with_frame_number(input_frame)).await
+    }
+
+    fn get_caps(&self) -> Caps {
+        Caps { frame_count: Some(self.frame_count), ..self.input.get_caps() }
+    }
+}
+
+
+/// Repeats the input `count` times (or forever for a count of zero), which
+/// needs random access to all its frames.
+pub struct Loop {
+    input: InputProcessingNode,
+    input_frame_count: u64,
+    count: u64,
+}
+
+impl Parameterizable for Loop {
+    const DESCRIPTION: Option<&'static str> = Some("repeat the input (0 times means forever)");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("input", Mandatory(NodeInputParameter))
+            .with("count", WithDefault(NaturalWithZero(), IntRangeValue(0)))
+    }
+    fn describe_port_types() -> PortTypes { PortTypes::new().with_output_of("input") }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        _context: &ProcessingContext,
+    ) -> Result<Self> {
+        let input: InputProcessingNode = parameters.take("input")?;
+        let input_frame_count = random_access_frame_count(&input, "Loop")?;
+        if input_frame_count == 0 {
+            return Err(anyhow!("Loop can't repeat an input without any frames"));
+        }
+        Ok(Self { input, input_frame_count, count: parameters.take("count")? })
+    }
+}
+
+impl Loop {
+    fn frame_count(&self) -> Option<u64> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(self.input_frame_count * self.count)
+        }
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for Loop {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let frame_number = request.frame_number();
+        if let Some(frame_count) = self.frame_count() {
+            if frame_number >= frame_count {
+                return Err(EOFError.into());
+            }
+        }
+        let input_frame = frame_number % self.input_frame_count;
+        self.input.pull(request.with_frame_number(input_frame)).await
+    }
+
+    fn get_caps(&self) -> Caps { Caps { frame_count: self.frame_count(), random_access: true } }
+}
+
+fn random_access_frame_count(input: &InputProcessingNode, node: &str) -> Result<u64> {
+    match input.get_caps() {
+        Caps { frame_count: Some(frame_count), random_access: true } => Ok(frame_count),
+        _ => Err(anyhow!("{node} needs an input with random access and a known number of frames")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_counts() {
+        let trim = |start, end| Trim { input: unreachable_input(), start, end };
+        assert_eq!(trim(10, None).frame_count(Some(100)), Some(90));
+        assert_eq!(trim(10, Some(20)).frame_count(Some(100)), Some(10));
+        assert_eq!(trim(10, Some(200)).frame_count(Some(100)), Some(90));
+        assert_eq!(trim(10, Some(20)).frame_count(None), Some(10));
+        assert_eq!(trim(10, None).frame_count(None), None);
+        assert_eq!(trim(10, None).frame_count(Some(5)), Some(0));
+
+        let stride = |step, offset| Stride { input: unreachable_input(), step, offset };
+        assert_eq!(stride(2, 0).frame_count(10), 5);
+        assert_eq!(stride(3, 0).frame_count(10), 4);
+        assert_eq!(stride(3, 1).frame_count(10), 3);
+        assert_eq!(stride(3, 20).frame_count(10), 0);
+    }
+
+    struct Unreachable;
+    #[async_trait]
+    impl ProcessingNode for Unreachable {
+        async fn pull(&self, _request: Request) -> Result<Payload> { unreachable!() }
+        fn get_caps(&self) -> Caps { Caps { frame_count: Some(100), random_access: true } }
+    }
+
+    fn unreachable_input() -> RemappedInput {
+        RemappedInput::new(InputProcessingNode::new(
+            NodeID::from(0),
+            std::sync::Arc::new(Unreachable),
+        ))
+    }
+}
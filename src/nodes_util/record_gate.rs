@@ -0,0 +1,446 @@
+use crate::{
+    nodes::{create_node_from_name, list_available_nodes},
+    pipeline_processing::{
+        live_parameters::LiveParameter,
+        node::{
+            Caps,
+            EOFError,
+            InputProcessingNode,
+            NodeID,
+            ProcessingNode,
+            ProcessingNodeIntoNode,
+            ProgressUpdate,
+            Request,
+            SinkNode,
+        },
+        parametrizable::prelude::*,
+        payload::Payload,
+        processing_context::ProcessingContext,
+        processing_graph::SerdeNodeParam,
+        puller::pull_ordered,
+    },
+    util::async_notifier::AsyncNotifier,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde_yaml::{Mapping, Value};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+// frames a take can be behind its live input (besides the pre-roll), further
+// frames are dropped
+const TAKE_QUEUE_FRAMES: usize = 64;
+
+/// Records takes of a live input with a new writer for every take. The
+/// `state` is a live parameter: while `armed` the last `pre-roll` of frames
+/// is kept, switching to `recording` starts a new take with them and leaving
+/// `recording` finishes it. Takes that fail don't end the run, their errors
+/// are reported in the status of the gate.
+pub struct RecordGate {
+    input: InputProcessingNode,
+    name: String,
+    writer: String,
+    writer_parameters: Mapping,
+    pre_roll: Duration,
+    state: LiveParameter<String>,
+    first_take: u64,
+    priority: u8,
+}
+
+impl Parameterizable for RecordGate {
+    const DESCRIPTION: Option<&'static str> = Some(
+        "record takes of a live input with a pre-roll, every take is written by a new writer \
+         node, {take} in its parameters is replaced by the number of the take",
+    );
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("input", Mandatory(NodeInputParameter))
+            .with("writer", Mandatory(StringParameter))
+            .with("writer-parameters", Optional(MapParameter))
+            .with("pre-roll", Optional(DurationParameter))
+            .with(
+                "state",
+                WithDefault(
+                    ChoiceParameter(vec!["idle", "armed", "recording"]),
+                    StringValue("armed".to_string()),
+                ),
+            )
+            .with("first-take", WithDefault(NaturalWithZero(), IntRangeValue(1)))
+            .with("priority", Optional(U8()))
+    }
+    fn describe_port_types() -> PortTypes { PortTypes::new().sink() }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> Result<Self> {
+        let writer = parameters.take::<String>("writer")?;
+        let descriptor = list_available_nodes()
+            .remove(&writer)
+            .ok_or_else(|| anyhow!("there is no node named {writer} to write the takes"))?;
+        if descriptor.port_types.output != OutputType::Nothing {
+            return Err(anyhow!("{writer} is no sink and can't write the takes"));
+        }
+        let other_inputs = descriptor
+            .parameters_descriptor
+            .0
+            .iter()
+            .any(|(name, parameter)| parameter.is_node_input() && name != "input");
+        if other_inputs {
+            return Err(anyhow!("{writer} needs other inputs than the frames of the takes"));
+        }
+
+        let gate = Self {
+            input: parameters.take("input")?,
+            name: parameters.node_name().to_string(),
+            writer,
+            writer_parameters: parameters.take("writer-parameters")?,
+            pre_roll: parameters.take("pre-roll")?,
+            state: parameters.take_live("state", context)?,
+            first_take: parameters.take("first-take")?,
+            priority: parameters.take("priority")?,
+        };
+        // fail now instead of at the first record press
+        gate.writer_parameters(gate.first_take)?
+            .check_values(&descriptor.parameters_descriptor)
+            .with_context(|| format!("invalid writer-parameters for {}", gate.writer))?;
+
+        Ok(gate)
+    }
+}
+
+/// A take that is being recorded
+struct Take {
+    number: u64,
+    frames: flume::Sender<Payload>,
+    writer: tokio::task::JoinHandle<Result<()>>,
+}
+
+impl Take {
+    /// Hands a frame to the writer without waiting for it, frames it has no
+    /// room for are dropped.
+    fn send(&self, context: &ProcessingContext, sink: NodeID, frame_number: u64, payload: Payload) {
+        match self.frames.try_send(payload) {
+            Ok(()) => {}
+            Err(flume::TrySendError::Full(_)) => context.run_control().frame_dropped(
+                sink,
+                frame_number,
+                &anyhow!("the writer of take {} is too slow", self.number),
+            ),
+            // the writer failed, which it reported itself
+            Err(flume::TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+#[async_trait]
+impl SinkNode for RecordGate {
+    async fn run(
+        &self,
+        context: &ProcessingContext,
+        progress_callback: Arc<dyn Fn(ProgressUpdate) + Send + Sync>,
+    ) -> Result<()> {
+        let rx = pull_ordered(
+            context,
+            self.priority,
+            progress_callback,
+            self.input.clone_for_same_puller(),
+            0,
+        );
+
+        let sink = self.input.puller_id();
+        let mut pre_roll = VecDeque::new();
+        let mut take = None;
+        let mut next_take = self.first_take;
+        let mut writers = vec![];
+
+        // the input ends when the run is stopped, the frames are numbered in the
+        // order they are received
+        for frame_number in 0.. {
+            let payload = match rx.recv_async().await {
+                Ok(payload) => payload,
+                Err(_) => break,
+            };
+            let mut state = self.state.get();
+            if state == "recording" && take.is_none() {
+                // take numbers are not reused, so a take whose files already exist is
+                // skipped at the next try
+                let number = next_take;
+                next_take += 1;
+                match self.start_take(context, number, &mut pre_roll) {
+                    Ok(started) => take = Some(started),
+                    Err(error) => {
+                        eprintln!("{}: {error:#}", self.name);
+                        context.run_control().error(sink, &error);
+                        context.live_parameters().set(
+                            &self.name,
+                            "state",
+                            StringValue("armed".to_string()),
+                        )?;
+                        state = "armed".to_string();
+                    }
+                }
+            }
+
+            if state == "recording" {
+                if let Some(take) = &take {
+                    take.send(context, sink, frame_number, payload);
+                }
+            } else {
+                if let Some(take) = take.take() {
+                    writers.push(self.finish_take(take));
+                }
+                if state == "armed" && !self.pre_roll.is_zero() {
+                    let now = Instant::now();
+                    pre_roll.push_back((now, payload));
+                    trim_pre_roll(&mut pre_roll, now, self.pre_roll);
+                } else {
+                    pre_roll.clear();
+                }
+            }
+        }
+
+        if let Some(take) = take {
+            writers.push(self.finish_take(take));
+        }
+        for writer in writers {
+            writer.await??;
+        }
+        Ok(())
+    }
+}
+
+impl RecordGate {
+    /// Creates the writer of a take and hands it the frames of the pre-roll.
+    /// The writer runs on its own, so the live input never waits for it.
+    fn start_take(
+        &self,
+        context: &ProcessingContext,
+        number: u64,
+        pre_roll: &mut VecDeque<(Instant, Payload)>,
+    ) -> Result<Take> {
+        let (tx, rx) = flume::bounded(pre_roll.len() + TAKE_QUEUE_FRAMES);
+        let input = TakeInput { frames: rx, notifier: Default::default() };
+        // the writer pulls with the id of the gate, so its dropped frames count
+        // for the gate
+        let writer = create_node_from_name(
+            &self.writer,
+            self.input.puller_id(),
+            &format!("{}-take-{number}", self.name),
+            self.writer_parameters(number)?,
+            HashMap::from([("input".to_string(), input.into_processing_element())]),
+            &[],
+            context,
+        )
+        .with_context(|| format!("couldn't start take {number}"))?
+        .assert_sink()?;
+
+        let pre_roll_frames = pre_roll.len();
+        for (_, payload) in pre_roll.drain(..) {
+            let _ = tx.send(payload);
+        }
+        eprintln!("{}: recording take {number} ({pre_roll_frames} frames of pre-roll)", self.name);
+
+        // a failed take is reported right away, the gate only returns its error at
+        // the end
+        let writer_context = context.clone();
+        let (name, sink) = (self.name.clone(), self.input.puller_id());
+        let writer = context.spawn_io(async move {
+            let result = writer
+                .run(&writer_context, Arc::new(|_: ProgressUpdate| {}))
+                .await
+                .with_context(|| format!("take {number} failed"));
+            if let Err(error) = &result {
+                eprintln!("{name}: {error:#}");
+                writer_context.run_control().error(sink, error);
+            }
+            result
+        });
+        Ok(Take { number, frames: tx, writer })
+    }
+
+    /// Ends the input of the writer, which finishes the take on its own.
+    fn finish_take(&self, take: Take) -> tokio::task::JoinHandle<Result<()>> {
+        eprintln!("{}: take {} finished", self.name, take.number);
+        take.writer
+    }
+
+    fn writer_parameters(&self, take: u64) -> Result<Parameters> {
+        let take = format!("{take:03}");
+        let mut values = HashMap::new();
+        for (key, value) in &self.writer_parameters {
+            let key = key.as_str().ok_or_else(|| anyhow!("writer-parameters need string keys"))?;
+            let value: SerdeNodeParam = serde_yaml::from_value(substitute_take(value, &take))?;
+            let value = ParameterValue::try_from(value)
+                .map_err(|_| anyhow!("{key} can't refer to a node, the writer gets the takes"))?;
+            values.insert(key.to_string(), value);
+        }
+        Ok(Parameters::new(values))
+    }
+}
+
+/// Replaces `{take}` in all strings of `value`
+fn substitute_take(value: &Value, take: &str) -> Value {
+    match value {
+        Value::String(string) => Value::String(string.replace("{take}", take)),
+        Value::Sequence(values) => {
+            Value::Sequence(values.iter().map(|value| substitute_take(value, take)).collect())
+        }
+        Value::Mapping(mapping) => Value::Mapping(
+            mapping
+                .iter()
+                .map(|(key, value)| (key.clone(), substitute_take(value, take)))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+/// Drops the frames that are older than the pre-roll
+fn trim_pre_roll<T>(pre_roll: &mut VecDeque<(Instant, T)>, now: Instant, length: Duration) {
+    while pre_roll.front().map_or(false, |(received, _)| now - *received > length) {
+        pre_roll.pop_front();
+    }
+}
+
+/// The input of the writer of one take, it ends when the take is finished.
+struct TakeInput {
+    frames: flume::Receiver<Payload>,
+    notifier: AsyncNotifier<u64>,
+}
+
+#[async_trait]
+impl ProcessingNode for TakeInput {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let frame_number = request.frame_number();
+
+        self.notifier.wait(move |x| *x >= frame_number).await;
+
+        let received = self.frames.recv_async().await.context(EOFError);
+        self.notifier.update(|x| *x = frame_number + 1);
+        received
+    }
+
+    fn get_caps(&self) -> Caps { Caps { frame_count: None, random_access: false } }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline_processing::test_util::spawn_graph;
+    use std::{fs, path::Path};
+
+    #[test]
+    fn test_substitute_take() {
+        let value: Value =
+            serde_yaml::from_str("{ path: 'take-{take}.dng', tags: ['{take}', 1], fps: 24 }")
+                .unwrap();
+        let expected: Value =
+            serde_yaml::from_str("{ path: take-007.dng, tags: ['007', 1], fps: 24 }").unwrap();
+        assert_eq!(substitute_take(&value, "007"), expected);
+    }
+
+    #[test]
+    fn test_trim_pre_roll() {
+        let now = Instant::now();
+        let mut pre_roll: VecDeque<_> =
+            [1500, 900, 500, 0].iter().map(|ms| (now - Duration::from_millis(*ms), *ms)).collect();
+        trim_pre_roll(&mut pre_roll, now, Duration::from_secs(1));
+        assert_eq!(pre_roll.iter().map(|(_, ms)| *ms).collect::<Vec<_>>(), vec![900, 500, 0]);
+        trim_pre_roll(&mut pre_roll, now, Duration::ZERO);
+        assert_eq!(pre_roll.len(), 1);
+    }
+
+    fn wait_for(mut condition: impl FnMut() -> bool) {
+        for _ in 0..1000 {
+            if condition() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("timed out");
+    }
+
+    fn frames_in(dir: &Path) -> usize { fs::read_dir(dir).map_or(0, |dir| dir.count()) }
+
+    #[test]
+    fn test_record_gate() {
+        let dir = std::env::temp_dir().join(format!("record-gate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (context, run) = spawn_graph(&format!(
+            "
+            pattern: {{ type: TestPattern, width: 16, height: 8, rgb: true }}
+            gate:
+              type: RecordGate
+              input: <pattern
+              writer: RawDirectoryWriter
+              writer-parameters: {{ path: '{}/take-{{take}}' }}
+              pre-roll: 1s
+            ",
+            dir.display()
+        ));
+
+        let (live, run_control) = (context.live_parameters(), context.run_control());
+        let set_state = |state: &str| {
+            live.set("gate", "state", StringValue(state.to_string())).unwrap();
+        };
+        wait_for(|| run_control.status().sinks[0].frames > 0);
+        for take in ["take-001", "take-002"] {
+            set_state("recording");
+            wait_for(|| frames_in(&dir.join(take)) > 0);
+            set_state("armed");
+        }
+        set_state("idle");
+        run_control.stop().unwrap();
+
+        run.join().unwrap().unwrap();
+        assert!(frames_in(&dir.join("take-001")) > 0);
+        assert!(frames_in(&dir.join("take-002")) > 0);
+        assert!(!dir.join("take-003").exists());
+        assert!(live.set("gate", "state", StringValue("paused".to_string())).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_failed_take() {
+        let dir = std::env::temp_dir().join(format!("record-gate-failed-{}", std::process::id()));
+        // the writer of the first take can't be created, because its directory exists
+        fs::create_dir_all(dir.join("take-001")).unwrap();
+        let (context, run) = spawn_graph(&format!(
+            "
+            pattern: {{ type: TestPattern, width: 16, height: 8, rgb: true }}
+            gate:
+              type: RecordGate
+              input: <pattern
+              writer: RawDirectoryWriter
+              writer-parameters: {{ path: '{}/take-{{take}}' }}
+            ",
+            dir.display()
+        ));
+
+        let (live, run_control) = (context.live_parameters(), context.run_control());
+        let armed = || {
+            let state = live.get("gate", "state").unwrap().value;
+            matches!(state, StringValue(state) if state == "armed")
+        };
+        live.set("gate", "state", StringValue("recording".to_string())).unwrap();
+        // the gate keeps running and is armed again
+        wait_for(armed);
+        let errors = run_control.status().sinks[0].errors.clone();
+        assert!(errors[0].contains("couldn't start take 1"), "{errors:?}");
+
+        live.set("gate", "state", StringValue("recording".to_string())).unwrap();
+        wait_for(|| frames_in(&dir.join("take-002")) > 0);
+        live.set("gate", "state", StringValue("idle".to_string())).unwrap();
+        run_control.stop().unwrap();
+
+        run.join().unwrap().unwrap();
+        assert_eq!(frames_in(&dir.join("take-001")), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{
+    pipeline_processing::{
+        node::{Caps, InputProcessingNode, Request},
+        payload::Payload,
+    },
+    util::async_notifier::AsyncNotifier,
+};
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+
+#[derive(Clone, Default)]
+struct Position {
+    next_output: u64,
+    next_input: u64,
+}
+
+/// An input whose frames are requested under different frame numbers than the
+/// ones of the output.
+///
+/// Random access inputs are simply pulled with the remapped frame number. For
+/// sequential inputs the output frames have to be requested in order and map
+/// to ascending input frames. Skipped input frames are pulled and thrown away
+/// and the last frame is kept, so that it can be repeated.
+pub struct RemappedInput {
+    input: InputProcessingNode,
+    random_access: bool,
+    position: AsyncNotifier<Position>,
+    last_frame: Mutex<Option<(u64, Payload)>>,
+}
+
+impl RemappedInput {
+    pub fn new(input: InputProcessingNode) -> Self {
+        let random_access = input.get_caps().random_access;
+        Self { input, random_access, position: Default::default(), last_frame: Mutex::new(None) }
+    }
+
+    pub fn get_caps(&self) -> Caps { self.input.get_caps() }
+
+    /// Pulls the first frame of the input to look at it, this has to happen
+    /// before any other frame is pulled. It is kept, so that the output frames
+    /// that map to it get it again.
+    pub async fn first_frame(&self, request: &Request) -> Result<Payload> {
+        if self.random_access {
+            return self.input.pull(request.with_frame_number(0)).await;
+        }
+        if let Some((0, payload)) = &*self.last_frame.lock() {
+            return Ok(payload.clone());
+        }
+
+        let payload = self.input.pull(request.with_frame_number(0)).await?;
+        *self.last_frame.lock() = Some((0, payload.clone()));
+        self.position.update(|position| position.next_input = position.next_input.max(1));
+        Ok(payload)
+    }
+
+    /// Pulls `input_frame` of the input for the output frame of the request.
+    pub async fn pull(&self, request: Request, input_frame: u64) -> Result<Payload> {
+        if self.random_access {
+            return self.input.pull(request.with_frame_number(input_frame)).await;
+        }
+
+        let output_frame = request.frame_number();
+        let Position { next_output, next_input } =
+            self.position.wait(move |position| position.next_output >= output_frame).await;
+        if output_frame < next_output {
+            return Err(anyhow!(
+                "frame {output_frame} was already served from the sequential input, it can only \
+                 be pulled once"
+            ));
+        }
+
+        let result = self.pull_sequential(&request, next_input, input_frame).await;
+        self.position.update(|position| {
+            *position = Position {
+                next_output: output_frame + 1,
+                next_input: next_input.max(input_frame + 1),
+            }
+        });
+        result
+    }
+
+    async fn pull_sequential(
+        &self,
+        request: &Request,
+        next_input: u64,
+        input_frame: u64,
+    ) -> Result<Payload> {
+        if let Some((frame_number, payload)) = &*self.last_frame.lock() {
+            if *frame_number == input_frame {
+                return Ok(payload.clone());
+            }
+        }
+        if input_frame < next_input {
+            return Err(anyhow!(
+                "frame {input_frame} was already pulled from the sequential input, going back needs \
+                 an input with random access"
+            ));
+        }
+
+        for skipped in next_input..input_frame {
+            self.input.pull(request.with_frame_number(skipped)).await?;
+        }
+        let payload = self.input.pull(request.with_frame_number(input_frame)).await?;
+        *self.last_frame.lock() = Some((input_frame, payload.clone()));
+        Ok(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline_processing::node::{NodeID, ProcessingNode};
+    use async_trait::async_trait;
+    use futures::executor::block_on;
+    use std::sync::Arc;
+
+    /// Serves its frame numbers and remembers the order they were pulled in
+    #[derive(Default)]
+    struct Sequential {
+        pulled: Mutex<Vec<u64>>,
+    }
+    #[async_trait]
+    impl ProcessingNode for Sequential {
+        async fn pull(&self, request: Request) -> Result<Payload> {
+            self.pulled.lock().push(request.frame_number());
+            Ok(Payload::from(request.frame_number()))
+        }
+        fn get_caps(&self) -> Caps { Caps { frame_count: None, random_access: false } }
+    }
+
+    #[test]
+    fn test_sequential_pull() {
+        let sequential = Arc::new(Sequential::default());
+        let input =
+            RemappedInput::new(InputProcessingNode::new(NodeID::from(0), sequential.clone()));
+        let pull = |output_frame, input_frame| {
+            let payload = block_on(input.pull(Request::new(0, output_frame), input_frame))?;
+            Ok::<_, anyhow::Error>(*payload.downcast::<u64>()?)
+        };
+
+        // repeated frames are served again, skipped ones are pulled and thrown away
+        assert_eq!(pull(0, 0).unwrap(), 0);
+        assert_eq!(pull(1, 0).unwrap(), 0);
+        assert_eq!(pull(2, 3).unwrap(), 3);
+        assert_eq!(*sequential.pulled.lock(), vec![0, 1, 2, 3]);
+
+        // frames that were already served or pulled are errors instead of waiting
+        // forever
+        assert!(pull(1, 0).is_err());
+        assert!(pull(3, 2).is_err());
+        assert_eq!(pull(4, 4).unwrap(), 4);
+    }
+}
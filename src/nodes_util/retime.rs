@@ -0,0 +1,206 @@
+use crate::{
+    nodes_util::remap::RemappedInput,
+    pipeline_processing::{
+        buffers::{CpuBuffer, GpuBuffer},
+        frame::{Frame, Raw, Rgb, Rgba},
+        node::{Caps, EOFError, NodeID, ProcessingNode, Request},
+        parametrizable::prelude::*,
+        payload::Payload,
+        processing_context::ProcessingContext,
+    },
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+
+// guards against frame numbers that are off by one because of rounding errors
+const EPSILON: f64 = 1e-9;
+
+/// Changes the frame rate by repeating or dropping frames, so that the
+/// playback speed stays the same. Without `input-fps` the frame rate of the
+/// input is taken from its first frame when the node is created.
+pub struct Retime {
+    input: RemappedInput,
+    fps: f64,
+    input_fps: f64,
+}
+
+impl Parameterizable for Retime {
+    const DESCRIPTION: Option<&'static str> =
+        Some("change the frame rate by repeating or dropping frames");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("input", Mandatory(NodeInputParameter))
+            .with("fps", Mandatory(PositiveReal()))
+            .with("input-fps", Optional(PositiveReal()))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new()
+            .with_input("input", &[FrameType::Raw, FrameType::Rgb, FrameType::Rgba])
+            .with_output_of("input")
+    }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> Result<Self> {
+        let fps: f64 = parameters.take("fps")?;
+        if fps <= 0.0 {
+            return Err(anyhow!("the frame rate has to be greater than zero"));
+        }
+        let input = RemappedInput::new(parameters.take("input")?);
+        // 0 (the default) means the frame rate of the input, it is needed for the
+        // frame count right away
+        let input_fps = match parameters.take::<f64>("input-fps")? {
+            input_fps if input_fps > 0.0 => input_fps,
+            _ => {
+                let first_frame = context
+                    .block_on(input.first_frame(&Request::new(0, 0)))
+                    .context("couldn't get the frame rate of the input from its first frame")?;
+                frame_fps(&first_frame)?
+            }
+        };
+        Ok(Self { input, fps, input_fps })
+    }
+}
+
+/// The input frame that is shown at the time of the output frame.
+fn input_frame(frame_number: u64, fps: f64, input_fps: f64) -> u64 {
+    (frame_number as f64 * input_fps / fps + EPSILON).floor() as u64
+}
+
+fn frame_count(input_frame_count: u64, fps: f64, input_fps: f64) -> u64 {
+    (input_frame_count as f64 * fps / input_fps - EPSILON).ceil().max(0.0) as u64
+}
+
+#[async_trait]
+impl ProcessingNode for Retime {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let input_fps = self.input_fps;
+        let input_frame = input_frame(request.frame_number(), self.fps, input_fps);
+        if let Some(frame_count) = self.input.get_caps().frame_count {
+            if input_frame >= frame_count {
+                return Err(EOFError.into());
+            }
+        }
+        let payload = self.input.pull(request, input_frame).await?;
+        let frame_fps = frame_fps(&payload)?;
+        if (frame_fps - input_fps).abs() > EPSILON * input_fps {
+            return Err(anyhow!(
+                "frame {input_frame} of the input has {frame_fps} fps, but Retime expects \
+                 {input_fps} fps"
+            ));
+        }
+        with_fps(&payload, self.fps)
+    }
+
+    fn get_caps(&self) -> Caps {
+        let caps = self.input.get_caps();
+        Caps {
+            frame_count: caps.frame_count.map(|count| frame_count(count, self.fps, self.input_fps)),
+            ..caps
+        }
+    }
+}
+
+/// The frame rate of the frame in the payload
+fn frame_fps(payload: &Payload) -> Result<f64> {
+    macro_rules! fps {
+        ($($ty:ident),*) => {
+            $(
+                if let Ok(frame) = payload.downcast::<Frame<$ty, CpuBuffer>>() {
+                    return Ok(frame.interp.fps);
+                } else if let Ok(frame) = payload.downcast::<Frame<$ty, GpuBuffer>>() {
+                    return Ok(frame.interp.fps);
+                }
+            )*
+        };
+    }
+    fps!(Raw, Rgb, Rgba);
+
+    Err(anyhow!("Retime can only change the frame rate of Raw, Rgb and Rgba frames"))
+}
+
+/// Copies the frame in the payload with a different frame rate. The frame data
+/// itself is shared.
+fn with_fps(payload: &Payload, fps: f64) -> Result<Payload> {
+    macro_rules! conv {
+        ($($ty:ident),*) => {
+            $(
+                if let Ok(frame) = payload.downcast::<Frame<$ty, CpuBuffer>>() {
+                    return Ok(Payload::from(Frame {
+                        interp: $ty { fps, ..frame.interp },
+                        storage: frame.storage.clone(),
+                        metadata: frame.metadata.clone(),
+                    }));
+                } else if let Ok(frame) = payload.downcast::<Frame<$ty, GpuBuffer>>() {
+                    return Ok(Payload::from(Frame {
+                        interp: $ty { fps, ..frame.interp },
+                        storage: frame.storage.clone(),
+                        metadata: frame.metadata.clone(),
+                    }));
+                }
+            )*
+        };
+    }
+    conv!(Raw, Rgb, Rgba);
+
+    Err(anyhow!("Retime can only change the frame rate of Raw, Rgb and Rgba frames"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        nodes_util::test_pattern::TestPattern,
+        pipeline_processing::{node::InputProcessingNode, test_util::create},
+    };
+    use futures::executor::block_on;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_retime_mapping() {
+        let input_frames =
+            |fps, input_fps, n| (0..n).map(|i| input_frame(i, fps, input_fps)).collect::<Vec<_>>();
+        // doubling repeats every frame, halving drops every second one
+        assert_eq!(input_frames(48.0, 24.0, 6), vec![0, 0, 1, 1, 2, 2]);
+        assert_eq!(input_frames(12.0, 24.0, 3), vec![0, 2, 4]);
+        assert_eq!(input_frames(30.0, 24.0, 6), vec![0, 0, 1, 2, 3, 4]);
+
+        assert_eq!(frame_count(10, 48.0, 24.0), 20);
+        assert_eq!(frame_count(10, 12.0, 24.0), 5);
+        assert_eq!(frame_count(11, 12.0, 24.0), 6);
+        assert_eq!(frame_count(24, 30.0, 24.0), 30);
+    }
+
+    #[test]
+    fn test_input_fps() {
+        let context = ProcessingContext::default();
+        // the frame rate of the test pattern is 24 fps
+        let retime = |input_fps: Option<f64>| {
+            let pattern: TestPattern = create(
+                &context,
+                [
+                    ("width", IntRangeValue(4)),
+                    ("height", IntRangeValue(2)),
+                    ("number-of-frames", IntRangeValue(10)),
+                ],
+            );
+            let input = InputProcessingNode::new(NodeID::from(0), Arc::new(pattern));
+            let values = [("input", NodeInputValue(input)), ("fps", FloatRangeValue(48.0))];
+            let input_fps = input_fps.map(|fps| ("input-fps", FloatRangeValue(fps)));
+            create::<Retime>(&context, values.into_iter().chain(input_fps))
+        };
+        let pull = |retime: &Retime, frame| block_on(retime.pull(Request::new(0, frame)));
+
+        // the frame count is known before any frame is pulled
+        let retime_from_frames = retime(None);
+        assert_eq!(retime_from_frames.get_caps().frame_count, Some(20));
+        assert_eq!(frame_fps(&pull(&retime_from_frames, 1).unwrap()).unwrap(), 48.0);
+
+        assert_eq!(retime(Some(24.0)).get_caps().frame_count, Some(20));
+        assert!(pull(&retime(Some(24.0)), 1).is_ok());
+        assert!(pull(&retime(Some(25.0)), 1).is_err());
+    }
+}
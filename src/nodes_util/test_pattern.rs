@@ -0,0 +1,147 @@
+use crate::pipeline_processing::{
+    frame::{CfaColor, CfaDescriptor, Frame, FrameInterpretation, Raw, Rgb},
+    metadata::FrameMetadata,
+    node::{Caps, EOFError, NodeID, ProcessingNode, Request},
+    parametrizable::prelude::*,
+    payload::Payload,
+    processing_context::ProcessingContext,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// the 75% color bars from white to black
+const BARS: [[u8; 3]; 8] = [
+    [191, 191, 191],
+    [191, 191, 0],
+    [0, 191, 191],
+    [0, 191, 0],
+    [191, 0, 191],
+    [191, 0, 0],
+    [0, 0, 191],
+    [0, 0, 0],
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Pattern {
+    Bars,
+    /// a horizontal gray ramp
+    Ramp,
+}
+
+/// A synthetic source to try out pipelines without a camera or footage. A
+/// white line moves by one pixel every frame, so consecutive frames differ.
+pub struct TestPattern {
+    pattern: Pattern,
+    width: u64,
+    height: u64,
+    fps: f64,
+    rgb: bool,
+    number_of_frames: u64,
+    context: ProcessingContext,
+}
+
+impl Parameterizable for TestPattern {
+    const DESCRIPTION: Option<&'static str> =
+        Some("generate color bars or a gray ramp with a moving line as 8 bit raw or rgb frames");
+
+    fn describe_parameters() -> ParametersDescriptor {
+        ParametersDescriptor::new()
+            .with("pattern", Optional(ChoiceParameter(vec!["bars", "ramp"])))
+            .with("width", WithDefault(NaturalGreaterZero(), IntRangeValue(1920)))
+            .with("height", WithDefault(NaturalGreaterZero(), IntRangeValue(1080)))
+            .with("fps", WithDefault(PositiveReal(), FloatRangeValue(24.0)))
+            .with("rgb", Optional(BoolParameter))
+            .with("number-of-frames", Optional(NaturalWithZero()))
+    }
+    fn describe_port_types() -> PortTypes {
+        PortTypes::new().with_output(&[FrameType::Raw, FrameType::Rgb])
+    }
+
+    fn from_parameters(
+        mut parameters: Parameters,
+        _is_input_to: &[NodeID],
+        context: &ProcessingContext,
+    ) -> Result<Self> {
+        let pattern = match &*parameters.take::<String>("pattern")? {
+            "ramp" => Pattern::Ramp,
+            _ => Pattern::Bars,
+        };
+        Ok(Self {
+            pattern,
+            width: parameters.take("width")?,
+            height: parameters.take("height")?,
+            fps: parameters.take("fps")?,
+            rgb: parameters.take("rgb")?,
+            number_of_frames: parameters.take("number-of-frames")?,
+            context: context.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl ProcessingNode for TestPattern {
+    async fn pull(&self, request: Request) -> Result<Payload> {
+        let frame_number = request.frame_number();
+        if self.number_of_frames != 0 && frame_number >= self.number_of_frames {
+            return Err(EOFError.into());
+        }
+
+        let (width, height, fps) = (self.width, self.height, self.fps);
+        let metadata = FrameMetadata::default();
+        if self.rgb {
+            let interp = Rgb { width, height, fps };
+            let mut storage =
+                unsafe { self.context.get_uninit_cpu_buffer(interp.required_bytes()) };
+            storage.as_mut_slice(|storage| {
+                for (i, pixel) in storage.chunks_exact_mut(3).enumerate() {
+                    pixel.copy_from_slice(&self.pixel(i as u64 % width, frame_number));
+                }
+            });
+            Ok(Payload::from(Frame { interp, storage, metadata }))
+        } else {
+            let cfa = CfaDescriptor::from_first_red(true, true);
+            let interp = Raw {
+                width,
+                height,
+                bit_depth: 8,
+                cfa,
+                fps,
+                black_level: [0; 4],
+                white_level: Raw::default_white_level(8),
+            };
+            let mut storage =
+                unsafe { self.context.get_uninit_cpu_buffer(interp.required_bytes()) };
+            storage.as_mut_slice(|storage| {
+                for (i, value) in storage.iter_mut().enumerate() {
+                    let (x, y) = (i as u64 % width, i as u64 / width);
+                    let [r, g, b] = self.pixel(x, frame_number);
+                    *value = match cfa.color_at(x as usize, y as usize) {
+                        CfaColor::Red => r,
+                        CfaColor::GreenInRedRow | CfaColor::GreenInBlueRow => g,
+                        CfaColor::Blue => b,
+                    };
+                }
+            });
+            Ok(Payload::from(Frame { interp, storage, metadata }))
+        }
+    }
+
+    fn get_caps(&self) -> Caps {
+        let frame_count =
+            if self.number_of_frames == 0 { None } else { Some(self.number_of_frames) };
+        Caps { frame_count, random_access: true }
+    }
+}
+
+impl TestPattern {
+    /// The color of column `x`, the pattern is the same in every row
+    fn pixel(&self, x: u64, frame_number: u64) -> [u8; 3] {
+        if x == frame_number % self.width {
+            return [255; 3];
+        }
+        match self.pattern {
+            Pattern::Bars => BARS[(x * BARS.len() as u64 / self.width) as usize],
+            Pattern::Ramp => [(x * 255 / (self.width - 1).max(1)) as u8; 3],
+        }
+    }
+}
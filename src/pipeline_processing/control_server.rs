@@ -0,0 +1,237 @@
+//! A small http/json api to inspect and control a running graph:
+//!
+//! * `GET /status` returns the state of the run, the nodes of the graph and the
+//!   progress, fps, dropped frames and errors of every sink
+//! * `POST /pause`, `POST /resume` and `POST /stop` pause the run, resume it or
+//!   stop it gracefully (the sinks finish their files)
+//! * `GET /parameters` lists the live parameters of all nodes
+//! * `GET /parameters/<node>/<name>` returns one of them
+//! * `PUT /parameters/<node>/<name>` sets one, the body is the new value as
+//!   json (or yaml), the new value is used from the next frame on
+
+use crate::pipeline_processing::{
+    live_parameters::{LiveParameterState, LiveParameters},
+    node_catalogue::{describe_type, json_value},
+    parametrizable::prelude::*,
+    processing_context::ProcessingContext,
+    processing_graph::SerdeNodeParam,
+    run_control::{RunControl, RunState},
+};
+use anyhow::{anyhow, Context, Result};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body,
+    Method,
+    Request,
+    Response,
+    Server,
+    StatusCode,
+};
+use serde_json::{json, Value};
+use std::{convert::Infallible, net::SocketAddr};
+
+/// Serves the control api on the tokio runtime of the context. Returns the
+/// address the server listens on, which differs from `address` if its port
+/// is 0.
+pub fn serve_control(context: &ProcessingContext, address: SocketAddr) -> Result<SocketAddr> {
+    let listener = std::net::TcpListener::bind(address)
+        .with_context(|| format!("couldn't listen on {address} for the control api"))?;
+    listener.set_nonblocking(true)?;
+    let address = listener.local_addr()?;
+
+    // only the parts of the context that are needed, the server must not keep
+    // the runtime it runs on alive
+    let run_control = context.run_control().clone();
+    let live_parameters = context.live_parameters().clone();
+    context.spawn_io(async move {
+        let service = make_service_fn(move |_| {
+            let (run_control, live_parameters) = (run_control.clone(), live_parameters.clone());
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    handle(run_control.clone(), live_parameters.clone(), request)
+                }))
+            }
+        });
+        let result = match Server::from_tcp(listener) {
+            Ok(server) => server.serve(service).await,
+            Err(error) => Err(error),
+        };
+        if let Err(error) = result {
+            eprintln!("the control api stopped: {error}");
+        }
+    });
+
+    Ok(address)
+}
+
+async fn handle(
+    run_control: RunControl,
+    live_parameters: LiveParameters,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let method = request.method().clone();
+    let path: Vec<String> = request
+        .uri()
+        .path()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(String::from)
+        .collect();
+    let path: Vec<&str> = path.iter().map(String::as_str).collect();
+
+    Ok(match (method, &path[..]) {
+        (Method::GET, ["status"]) => match serde_json::to_value(run_control.status()) {
+            Ok(status) => json_response(StatusCode::OK, status),
+            Err(error) => error_response(StatusCode::INTERNAL_SERVER_ERROR, error.to_string()),
+        },
+        (Method::POST, ["pause"]) => state_response(run_control.pause()),
+        (Method::POST, ["resume"]) => state_response(run_control.resume()),
+        (Method::POST, ["stop"]) => state_response(run_control.stop()),
+        (Method::GET, ["parameters"]) => {
+            let parameters = live_parameters.list().iter().map(describe_parameter).collect();
+            json_response(StatusCode::OK, Value::Array(parameters))
+        }
+        (Method::GET, ["parameters", node, name]) => match live_parameters.get(node, name) {
+            Some(state) => json_response(StatusCode::OK, describe_parameter(&state)),
+            None => error_response(StatusCode::NOT_FOUND, unknown_parameter(node, name)),
+        },
+        (Method::PUT | Method::POST, ["parameters", node, name]) => {
+            if live_parameters.get(node, name).is_none() {
+                return Ok(error_response(StatusCode::NOT_FOUND, unknown_parameter(node, name)));
+            }
+            let result = match hyper::body::to_bytes(request.into_body()).await {
+                Ok(body) => {
+                    parse_value(&body).and_then(|value| live_parameters.set(node, name, value)).map(
+                        |value| json!({ "node": node, "name": name, "value": json_value(&value) }),
+                    )
+                }
+                Err(error) => Err(error.into()),
+            };
+            match result {
+                Ok(response) => json_response(StatusCode::OK, response),
+                Err(error) => error_response(StatusCode::BAD_REQUEST, format!("{error:#}")),
+            }
+        }
+        _ => error_response(StatusCode::NOT_FOUND, "unknown endpoint".to_string()),
+    })
+}
+
+fn state_response(state: Result<RunState>) -> Response<Body> {
+    match state {
+        Ok(state) => json_response(StatusCode::OK, json!({ "state": state })),
+        Err(error) => error_response(StatusCode::CONFLICT, format!("{error:#}")),
+    }
+}
+
+fn unknown_parameter(node: &str, name: &str) -> String {
+    format!("node {node} has no live parameter {name}")
+}
+
+/// Parses a value like the value of a parameter in a pipeline file.
+fn parse_value(body: &[u8]) -> Result<ParameterValue> {
+    let value: SerdeNodeParam =
+        serde_yaml::from_slice(body).context("the body has to be a json value")?;
+    ParameterValue::try_from(value).map_err(|_| anyhow!("live parameters can't refer to nodes"))
+}
+
+fn describe_parameter(state: &LiveParameterState) -> Value {
+    json!({
+        "node": state.node,
+        "name": state.name,
+        "type": describe_type(&state.ty),
+        "value": json_value(&state.value),
+    })
+}
+
+fn json_response(status: StatusCode, value: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(value.to_string()))
+        .unwrap()
+}
+
+fn error_response(status: StatusCode, error: String) -> Response<Body> {
+    json_response(status, json!({ "error": error }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline_processing::test_util::spawn_graph;
+    use hyper::Client;
+    use std::time::Duration;
+
+    async fn request(
+        address: SocketAddr,
+        method: Method,
+        path: &str,
+        body: &str,
+    ) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(format!("http://{address}{path}"))
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = Client::new().request(request).await.unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[test]
+    fn test_control_server() {
+        let (context, run) = spawn_graph(
+            "
+            pattern: { type: TestPattern, width: 64, height: 32, rgb: true }
+            voodoo: { type: ColorVoodoo, input: <pattern }
+            sink: { type: BenchmarkSink, input: <voodoo }
+            ",
+        );
+        let address = serve_control(&context, "127.0.0.1:0".parse().unwrap()).unwrap();
+
+        let client_runtime = tokio::runtime::Runtime::new().unwrap();
+        client_runtime.block_on(async {
+            // the pattern is endless, wait until the sink got some frames
+            let mut status = Value::Null;
+            for _ in 0..1000 {
+                status = request(address, Method::GET, "/status", "").await.1;
+                if status["sinks"][0]["frames"].as_u64().unwrap() > 0 {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            assert!(status["sinks"][0]["frames"].as_u64().unwrap() > 0);
+            assert_eq!(status["state"], "running");
+            assert_eq!(status["graph"].as_array().unwrap().len(), 3);
+            assert_eq!(status["sinks"][0]["name"], "sink");
+
+            let (code, response) =
+                request(address, Method::PUT, "/parameters/voodoo/s_gamma", "2").await;
+            assert_eq!((code, &response["value"]), (StatusCode::OK, &json!(2.0)));
+            let (code, _) =
+                request(address, Method::PUT, "/parameters/voodoo/s_gamma", "1000").await;
+            assert_eq!(code, StatusCode::BAD_REQUEST);
+            let (code, _) = request(address, Method::GET, "/parameters/voodoo/gamma", "").await;
+            assert_eq!(code, StatusCode::NOT_FOUND);
+
+            let (_, response) = request(address, Method::POST, "/pause", "").await;
+            assert_eq!(response["state"], "paused");
+            let (_, status) = request(address, Method::GET, "/status", "").await;
+            assert_eq!(status["state"], "paused");
+            let (_, response) = request(address, Method::POST, "/resume", "").await;
+            assert_eq!(response["state"], "running");
+
+            let (_, response) = request(address, Method::POST, "/stop", "").await;
+            assert_eq!(response["state"], "stopped");
+            let (code, _) = request(address, Method::POST, "/resume", "").await;
+            assert_eq!(code, StatusCode::CONFLICT);
+        });
+
+        // stopping lets the sink finish like at the end of its input
+        run.join().unwrap().unwrap();
+        let status = context.run_control().status();
+        assert!(status.sinks[0].finished);
+        assert_eq!(status.sinks[0].error, None);
+    }
+}
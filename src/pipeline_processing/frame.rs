@@ -1,4 +1,5 @@
-use std::sync::Arc;
+use crate::pipeline_processing::metadata::FrameMetadata;
+use serde::{Deserialize, Serialize};
 
 pub trait ToAny: 'static {
     fn as_any(&self) -> &dyn std::any::Any;
@@ -23,9 +24,10 @@ pub trait FrameInterpretation: ToAny {
 pub struct Frame<Interpretation, Storage> {
     pub interp: Interpretation,
     pub storage: Storage,
+    pub metadata: FrameMetadata,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CfaDescriptor {
     pub red_in_first_col: bool,
     pub red_in_first_row: bool,
@@ -35,16 +37,87 @@ impl CfaDescriptor {
     pub fn from_first_red(red_in_first_col: bool, red_in_first_row: bool) -> Self {
         CfaDescriptor { red_in_first_col, red_in_first_row }
     }
+
+    pub fn color_at(&self, x: usize, y: usize) -> CfaColor {
+        let red_col = (x % 2 == 0) == self.red_in_first_col;
+        let red_row = (y % 2 == 0) == self.red_in_first_row;
+        match (red_col, red_row) {
+            (true, true) => CfaColor::Red,
+            (false, true) => CfaColor::GreenInRedRow,
+            (true, false) => CfaColor::GreenInBlueRow,
+            (false, false) => CfaColor::Blue,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CfaColor {
+    Red,
+    GreenInRedRow,
+    GreenInBlueRow,
+    Blue,
 }
 
-// TODO(robin): this needs black level!!!
-#[derive(Clone, Copy, Debug)]
+impl CfaColor {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CfaColor::Red => "red",
+            CfaColor::GreenInRedRow => "green-in-red-row",
+            CfaColor::GreenInBlueRow => "green-in-blue-row",
+            CfaColor::Blue => "blue",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Raw {
     pub width: u64,
     pub height: u64,
     pub bit_depth: u64,
     pub cfa: CfaDescriptor,
     pub fps: f64,
+    /// the black level for every position of the 2x2 cfa pattern in row major
+    /// order (independent of the colors of the pattern)
+    pub black_level: [u32; 4],
+    /// the value of a saturated pixel
+    pub white_level: u32,
+}
+
+impl Raw {
+    pub fn default_white_level(bit_depth: u64) -> u32 { ((1u64 << bit_depth.min(32)) - 1) as u32 }
+
+    /// The black level of 12 bit AXIOM footage (which `RowNoiseRemoval` used to
+    /// assume), other bit depths have no black level unless one is given.
+    pub fn default_black_level(bit_depth: u64) -> [u32; 4] {
+        if bit_depth == 12 {
+            [128; 4]
+        } else {
+            [0; 4]
+        }
+    }
+
+    pub fn black_level_at(&self, x: usize, y: usize) -> u32 {
+        self.black_level[(y % 2) * 2 + (x % 2)]
+    }
+
+    /// Changes the bit depth and scales the black and white levels
+    /// accordingly, for conversions that shift the values by the difference
+    /// of the bit depths.
+    pub fn with_shifted_bit_depth(self, bit_depth: u64) -> Self {
+        let shift = |v: u32| {
+            if bit_depth < self.bit_depth {
+                v >> (self.bit_depth - bit_depth)
+            } else {
+                v << (bit_depth - self.bit_depth)
+            }
+        };
+        Raw {
+            bit_depth,
+            black_level: self.black_level.map(shift),
+            white_level: shift(self.white_level),
+            ..self
+        }
+    }
 }
 
 impl FrameInterpretation for Raw {
@@ -56,7 +129,7 @@ impl FrameInterpretation for Raw {
     fn fps(&self) -> Option<f64> { Some(self.fps) }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Rgb {
     pub width: u64,
     pub height: u64,
@@ -70,7 +143,7 @@ impl FrameInterpretation for Rgb {
     fn fps(&self) -> Option<f64> { Some(self.fps) }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Rgba {
     pub width: u64,
     pub height: u64,
@@ -84,19 +157,33 @@ impl FrameInterpretation for Rgba {
     fn fps(&self) -> Option<f64> { Some(self.fps) }
 }
 
-#[derive(Clone)]
-pub struct SZ3Compressed {
-    inner: Arc<dyn FrameInterpretation + Send + Sync>,
-    compressed_size: usize,
+/// the type the frame data is interpreted as by the SZ3 compressor
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SZ3DataType {
+    F32,
+    F64,
+    I32,
+    I64,
 }
-impl SZ3Compressed {
-    pub fn new(inner: Arc<dyn FrameInterpretation + Send + Sync>, compressed_size: usize) -> Self {
-        Self { inner, compressed_size }
-    }
-    pub fn downcast_inner<T: 'static>(&self) -> Option<&T> {
-        let v: &dyn std::any::Any = self.inner.as_any();
-        v.downcast_ref()
-    }
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SZ3ErrorBound {
+    Absolute(f64),
+    Relative(f64),
+    L2Norm(f64),
+    Psnr(f64),
+}
+
+/// A frame compressed with SZ3. Everything except the compressed data that is
+/// needed to decompress the frame again is kept here, so that it can be written
+/// alongside the data.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SZ3Compressed {
+    pub inner: FrameInterpretations,
+    pub data_type: SZ3DataType,
+    pub dims: Vec<i64>,
+    pub error_bound: SZ3ErrorBound,
+    pub compressed_size: usize,
 }
 impl FrameInterpretation for SZ3Compressed {
     fn required_bytes(&self) -> usize { self.compressed_size }
@@ -105,7 +192,20 @@ impl FrameInterpretation for SZ3Compressed {
     fn fps(&self) -> Option<f64> { self.inner.fps() }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// A raw frame compressed by the lossless codec of `LosslessCompress`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LosslessCompressed {
+    pub inner: Raw,
+    pub compressed_size: usize,
+}
+impl FrameInterpretation for LosslessCompressed {
+    fn required_bytes(&self) -> usize { self.compressed_size }
+    fn width(&self) -> u64 { self.inner.width }
+    fn height(&self) -> u64 { self.inner.height }
+    fn fps(&self) -> Option<f64> { Some(self.inner.fps) }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum FrameInterpretations {
     Raw(Raw),
     Rgb(Rgb),
@@ -141,3 +241,14 @@ impl FrameInterpretation for FrameInterpretations {
         }
     }
 }
+
+/// The kinds of frames that can flow between two nodes, used to describe and
+/// check which nodes can be connected to each other
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FrameType {
+    Raw,
+    Rgb,
+    Rgba,
+    SZ3Compressed,
+    LosslessCompressed,
+}
@@ -57,7 +57,14 @@ pub fn to_immutable_buffer<Interpretation: Clone + Send + Sync + 'static>(
         (buffer, future)
     };
 
-    (Frame { interp: frame.interp.clone(), storage: buffer.into() }, fut)
+    (
+        Frame {
+            interp: frame.interp.clone(),
+            storage: buffer.into(),
+            metadata: frame.metadata.clone(),
+        },
+        fut,
+    )
 }
 
 pub fn ensure_gpu_buffer<Interpretation: Clone + Send + Sync + 'static>(
@@ -0,0 +1,244 @@
+//! Parameters that can be changed while the graph runs. Nodes take them with
+//! `Parameters::take_live` and read them for every frame, the control server
+//! (see `control_server`) lists and sets them.
+
+use crate::pipeline_processing::parametrizable::prelude::*;
+use anyhow::{anyhow, Context, Error, Result};
+use parking_lot::{Mutex, RwLock};
+use std::{collections::BTreeMap, convert::TryInto, marker::PhantomData, sync::Arc};
+
+/// The current value of a live parameter together with a version, that is
+/// incremented every time the value is set.
+type VersionedValue = Arc<RwLock<(u64, ParameterValue)>>;
+
+/// A check of the node that new values have to pass before they are set
+type ValueCheck = Arc<dyn Fn(&ParameterValue) -> Result<()> + Send + Sync>;
+
+struct LiveEntry {
+    ty: ParameterType,
+    value: VersionedValue,
+    check: Option<ValueCheck>,
+}
+
+/// A snapshot of one live parameter, as returned by `LiveParameters::list`
+#[derive(Debug)]
+pub struct LiveParameterState {
+    pub node: String,
+    pub name: String,
+    pub ty: ParameterType,
+    pub value: ParameterValue,
+}
+
+/// The live parameters of all nodes of a `ProcessingContext` by the name of
+/// their node and their own name.
+#[derive(Clone, Default)]
+pub struct LiveParameters {
+    entries: Arc<Mutex<BTreeMap<(String, String), LiveEntry>>>,
+}
+
+impl LiveParameters {
+    /// Registers a parameter of a node, which replaces an earlier registration
+    /// with the same name (if a graph is built again with the same context).
+    pub fn register<T>(
+        &self,
+        node: &str,
+        name: &str,
+        ty: ParameterType,
+        value: ParameterValue,
+    ) -> Result<LiveParameter<T>>
+    where
+        ParameterValue: TryInto<T, Error = Error>,
+    {
+        self.register_entry(node, name, ty, value, None)
+    }
+
+    /// Like `register`, but new values are only set if they pass `check`, e.g.
+    /// a lut file has to be readable.
+    pub fn register_checked<T: 'static>(
+        &self,
+        node: &str,
+        name: &str,
+        ty: ParameterType,
+        value: ParameterValue,
+        check: impl Fn(T) -> Result<()> + Send + Sync + 'static,
+    ) -> Result<LiveParameter<T>>
+    where
+        ParameterValue: TryInto<T, Error = Error>,
+    {
+        let check: ValueCheck = Arc::new(move |value: &ParameterValue| {
+            check(value.clone_for_same_puller().try_into()?)
+        });
+        self.register_entry(node, name, ty, value, Some(check))
+    }
+
+    fn register_entry<T>(
+        &self,
+        node: &str,
+        name: &str,
+        ty: ParameterType,
+        value: ParameterValue,
+        check: Option<ValueCheck>,
+    ) -> Result<LiveParameter<T>>
+    where
+        ParameterValue: TryInto<T, Error = Error>,
+    {
+        let _: T = value.clone_for_same_puller().try_into()?;
+        let value = Arc::new(RwLock::new((0, value)));
+        self.entries.lock().insert(
+            (node.to_string(), name.to_string()),
+            LiveEntry { ty, value: value.clone(), check },
+        );
+        Ok(LiveParameter { value, _type: PhantomData })
+    }
+
+    pub fn list(&self) -> Vec<LiveParameterState> {
+        self.entries
+            .lock()
+            .iter()
+            .map(|((node, name), entry)| LiveParameterState {
+                node: node.clone(),
+                name: name.clone(),
+                ty: entry.ty.clone(),
+                value: entry.value.read().1.clone_for_same_puller(),
+            })
+            .collect()
+    }
+
+    pub fn get(&self, node: &str, name: &str) -> Option<LiveParameterState> {
+        self.list().into_iter().find(|state| state.node == node && state.name == name)
+    }
+
+    /// Checks the value against the type of the parameter like the values of a
+    /// pipeline file (and with the check of the node, if it has one) and sets
+    /// it. Returns the value as the node sees it.
+    pub fn set(&self, node: &str, name: &str, value: ParameterValue) -> Result<ParameterValue> {
+        // the check of the node might read files, so the other parameters are not
+        // locked meanwhile
+        let (ty, current, check) = {
+            let entries = self.entries.lock();
+            let entry = entries
+                .get(&(node.to_string(), name.to_string()))
+                .ok_or_else(|| anyhow!("node {node} has no live parameter {name}"))?;
+            (entry.ty.clone(), entry.value.clone(), entry.check.clone())
+        };
+
+        // whole numbers are valid floats here, json doesn't keep 1.0 apart from 1
+        let value = match (&ty, value) {
+            (FloatRange(..), IntRangeValue(v)) => FloatRangeValue(v as f64),
+            (_, value) => value,
+        };
+        let value = ty
+            .value_is_of_type(value)
+            .and_then(|value| ty.check_path(&value).map(|_| value))
+            .and_then(|value| match &check {
+                Some(check) => check(&value).map(|_| value),
+                None => Ok(value),
+            })
+            .with_context(|| format!("invalid value for {name} of node {node}"))?;
+
+        let mut current = current.write();
+        // `LiveParameter::get` relies on the variant staying the same
+        if std::mem::discriminant(&current.1) != std::mem::discriminant(&value) {
+            return Err(anyhow!("{name} of node {node} can't be set to {value:?}"));
+        }
+        *current = (current.0 + 1, value.clone_for_same_puller());
+        Ok(value)
+    }
+}
+
+/// The handle a node keeps to read the current value of one of its live
+/// parameters.
+pub struct LiveParameter<T> {
+    value: VersionedValue,
+    _type: PhantomData<fn() -> T>,
+}
+
+// not derived, that would need `T: Clone`
+impl<T> Clone for LiveParameter<T> {
+    fn clone(&self) -> Self { Self { value: self.value.clone(), _type: PhantomData } }
+}
+
+impl<T> LiveParameter<T>
+where
+    ParameterValue: TryInto<T, Error = Error>,
+{
+    pub fn get(&self) -> T { self.get_versioned().1 }
+
+    /// The value with its version, to only recompute things derived from the
+    /// value (like a lut read from a file) after it was set.
+    pub fn get_versioned(&self) -> (u64, T) {
+        let current = self.value.read();
+        let value = current
+            .1
+            .clone_for_same_puller()
+            .try_into()
+            .expect("live parameters keep the type they were registered with");
+        (current.0, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_live_parameters() {
+        let live = LiveParameters::default();
+        let gamma = live
+            .register::<f64>("voodoo", "s_gamma", FloatRange(0.0, 100.0), FloatRangeValue(1.0))
+            .unwrap();
+        let mode = live
+            .register::<String>(
+                "debayer",
+                "mode",
+                ChoiceParameter(vec!["bilinear", "edge-aware"]),
+                StringValue("bilinear".to_string()),
+            )
+            .unwrap();
+        assert_eq!(gamma.get_versioned(), (0, 1.0));
+
+        live.set("voodoo", "s_gamma", FloatRangeValue(2.5)).unwrap();
+        assert_eq!(gamma.get_versioned(), (1, 2.5));
+        live.set("voodoo", "s_gamma", IntRangeValue(3)).unwrap();
+        assert_eq!(gamma.get_versioned(), (2, 3.0));
+
+        // invalid values are rejected and leave the value alone
+        assert!(live.set("voodoo", "s_gamma", FloatRangeValue(200.0)).is_err());
+        assert!(live.set("voodoo", "s_gamma", StringValue("a".to_string())).is_err());
+        assert!(live.set("voodoo", "v_gamma", FloatRangeValue(2.0)).is_err());
+        assert_eq!(gamma.get_versioned(), (2, 3.0));
+
+        live.set("debayer", "mode", StringValue("Edge-Aware".to_string())).unwrap();
+        assert_eq!(mode.get(), "edge-aware");
+
+        let names: Vec<_> =
+            live.list().into_iter().map(|state| format!("{}.{}", state.node, state.name)).collect();
+        assert_eq!(names, vec!["debayer.mode", "voodoo.s_gamma"]);
+        assert!(live.get("voodoo", "mode").is_none());
+    }
+
+    #[test]
+    fn test_checked_live_parameters() {
+        let live = LiveParameters::default();
+        let file = live
+            .register_checked::<String>(
+                "lut",
+                "file",
+                StringParameter,
+                StringValue("a.cube".to_string()),
+                |file| {
+                    if file.ends_with(".cube") {
+                        Ok(())
+                    } else {
+                        Err(anyhow!("{file} is no cube file"))
+                    }
+                },
+            )
+            .unwrap();
+
+        live.set("lut", "file", StringValue("b.cube".to_string())).unwrap();
+        let error = live.set("lut", "file", StringValue("c.txt".to_string())).unwrap_err();
+        assert!(format!("{error:#}").contains("c.txt is no cube file"), "{error:#}");
+        assert_eq!(file.get_versioned(), (1, "b.cube".to_string()));
+    }
+}
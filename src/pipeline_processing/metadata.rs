@@ -0,0 +1,182 @@
+use crate::pipeline_processing::{
+    buffers::{CpuBuffer, GpuBuffer},
+    frame::{Frame, LosslessCompressed, Raw, Rgb, Rgba, SZ3Compressed},
+    payload::Payload,
+};
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::BTreeMap, io::ErrorKind, path::Path};
+
+/// A typed entry of the `FrameMetadata` map. Every entry type is stored under
+/// its own `KEY`.
+pub trait MetadataEntry: Serialize + DeserializeOwned {
+    const KEY: &'static str;
+}
+
+/// Metadata that is carried along with a frame through the pipeline.
+///
+/// Internally this is a map from the entry keys to their yaml representation,
+/// so that writers can serialize all entries without knowing about their
+/// types and entries unknown to this version survive a roundtrip.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FrameMetadata(BTreeMap<String, serde_yaml::Value>);
+
+impl FrameMetadata {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn get<T: MetadataEntry>(&self) -> Option<T> {
+        self.0.get(T::KEY).and_then(|value| serde_yaml::from_value(value.clone()).ok())
+    }
+
+    pub fn insert<T: MetadataEntry>(&mut self, entry: T) {
+        self.0.insert(
+            T::KEY.to_string(),
+            serde_yaml::to_value(entry).expect("metadata entries have to be representable in yaml"),
+        );
+    }
+
+    pub fn with<T: MetadataEntry>(mut self, entry: T) -> Self {
+        self.insert(entry);
+        self
+    }
+
+    pub fn remove<T: MetadataEntry>(&mut self) -> Option<T> {
+        self.0.remove(T::KEY).and_then(|value| serde_yaml::from_value(value).ok())
+    }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Serializes the metadata as a single yaml document (including the
+    /// leading `---`), so that multiple frames can be written into one file.
+    pub fn to_yaml_document(&self) -> Result<String> {
+        Ok(format!("---\n{}", serde_yaml::to_string(self)?))
+    }
+
+    /// Reads all yaml documents of a metadata sidecar file. A missing file
+    /// is treated as a file without any documents.
+    pub fn read_sidecar(path: impl AsRef<Path>) -> Result<Vec<FrameMetadata>> {
+        let path = path.as_ref();
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        if contents.trim().is_empty() {
+            return Ok(vec![]);
+        }
+        serde_yaml::Deserializer::from_str(&contents)
+            .map(FrameMetadata::deserialize)
+            .collect::<Result<_, _>>()
+            .with_context(|| format!("couldn't parse metadata sidecar {path:?}"))
+    }
+
+    /// Gets the metadata of any frame type contained in the payload. Payloads
+    /// that are not frames have no metadata.
+    pub fn of_payload(payload: &Payload) -> FrameMetadata {
+        macro_rules! conv {
+            ($($ty:ty),*) => {
+                $(
+                    if let Ok(frame) = payload.downcast::<Frame<$ty, CpuBuffer>>() {
+                        return frame.metadata.clone();
+                    } else if let Ok(frame) = payload.downcast::<Frame<$ty, GpuBuffer>>() {
+                        return frame.metadata.clone();
+                    }
+                )*
+            };
+        }
+        conv!(Raw, Rgb, Rgba, SZ3Compressed, LosslessCompressed);
+
+        FrameMetadata::default()
+    }
+}
+
+/// SMPTE timecode of the frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timecode {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+}
+impl MetadataEntry for Timecode {
+    const KEY: &'static str = "timecode";
+}
+
+/// exposure time in seconds
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExposureTime(pub f64);
+impl MetadataEntry for ExposureTime {
+    const KEY: &'static str = "exposure-time";
+}
+
+/// analog gain as a linear factor
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnalogGain(pub f64);
+impl MetadataEntry for AnalogGain {
+    const KEY: &'static str = "analog-gain";
+}
+
+/// time of capture in nanoseconds since the unix epoch
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureTimestamp(pub u64);
+impl MetadataEntry for CaptureTimestamp {
+    const KEY: &'static str = "capture-timestamp";
+}
+
+/// the sequence number the frame was given by the sensor or capture device
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SensorSequence(pub u64);
+impl MetadataEntry for SensorSequence {
+    const KEY: &'static str = "sensor-sequence";
+}
+
+/// the frames that were lost right before this one, because they never arrived
+/// or arrived corrupted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DroppedFrames {
+    pub missing: u64,
+    pub corrupted: u64,
+}
+impl MetadataEntry for DroppedFrames {
+    const KEY: &'static str = "dropped-frames";
+}
+
+/// a dump of the sensor registers (address -> value) at the time of capture
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SensorRegisters(pub BTreeMap<u16, u16>);
+impl MetadataEntry for SensorRegisters {
+    const KEY: &'static str = "sensor-registers";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_yaml_roundtrip() {
+        let metadata = FrameMetadata::new()
+            .with(Timecode { hours: 1, minutes: 2, seconds: 3, frames: 4 })
+            .with(ExposureTime(1.0 / 48.0))
+            .with(SensorSequence(42))
+            .with(SensorRegisters([(80, 0x1234)].into_iter().collect()));
+
+        let yaml = serde_yaml::to_string(&metadata).unwrap();
+        let parsed: FrameMetadata = serde_yaml::from_str(&yaml).unwrap();
+
+        assert_eq!(parsed, metadata);
+        assert_eq!(parsed.get::<SensorSequence>(), Some(SensorSequence(42)));
+        assert_eq!(parsed.get::<AnalogGain>(), None);
+
+        let documents = [metadata.clone(), FrameMetadata::new(), metadata]
+            .iter()
+            .map(|metadata| metadata.to_yaml_document().unwrap())
+            .collect::<String>();
+        let parsed = serde_yaml::Deserializer::from_str(&documents)
+            .map(FrameMetadata::deserialize)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(parsed.len(), 3);
+        assert!(parsed[1].is_empty());
+    }
+}
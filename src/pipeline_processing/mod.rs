@@ -1,10 +1,18 @@
 pub mod buffers;
+pub mod control_server;
 pub mod frame;
 pub mod gpu_util;
+pub mod live_parameters;
+pub mod metadata;
 pub mod node;
+pub mod node_catalogue;
 pub mod parametrizable;
+pub mod pipeline_file;
 pub mod payload;
 pub mod prioritized_executor;
 pub mod processing_context;
 pub mod processing_graph;
 pub mod puller;
+pub mod run_control;
+#[cfg(test)]
+pub mod test_util;
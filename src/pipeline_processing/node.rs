@@ -110,6 +110,9 @@ impl InputProcessingNode {
 
     pub(crate) fn clone_for_same_puller(&self) -> Self { self.copy_with(self.node_id) }
 
+    /// The node that pulls from this input
+    pub(crate) fn puller_id(&self) -> NodeID { self.node_id }
+
     pub fn get_caps(&self) -> Caps { self.node.get_caps() }
 }
 
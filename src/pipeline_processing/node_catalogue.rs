@@ -0,0 +1,237 @@
+//! Machine readable descriptions of the available nodes, for editors and other
+//! tools that want to autocomplete or validate pipeline configurations.
+
+use crate::pipeline_processing::parametrizable::prelude::*;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Describes every node with its parameters, inputs and output as json.
+pub fn node_catalogue(nodes: &HashMap<String, ParameterizableDescriptor>) -> Value {
+    let mut names: Vec<_> = nodes.keys().collect();
+    names.sort();
+    Value::Array(names.into_iter().map(|name| describe_node(&nodes[name])).collect())
+}
+
+fn describe_node(node: &ParameterizableDescriptor) -> Value {
+    let mut parameters = Map::new();
+    let mut inputs = Map::new();
+    for (name, descriptor) in &node.parameters_descriptor.0 {
+        match descriptor {
+            descriptor if descriptor.is_node_input() => {
+                let frame_types =
+                    node.port_types.inputs.get(name).map(|types| frame_type_names(types));
+                inputs.insert(
+                    name.clone(),
+                    json!({
+                        "mandatory": matches!(descriptor, Mandatory(_)),
+                        "list": matches!(descriptor.parameter_type(), ListParameter(_)),
+                        "frame_types": frame_types,
+                    }),
+                );
+            }
+            Mandatory(ty) => {
+                parameters
+                    .insert(name.clone(), json!({ "mandatory": true, "type": describe_type(ty) }));
+            }
+            WithDefault(ty, default) => {
+                parameters.insert(
+                    name.clone(),
+                    json!({
+                        "mandatory": false,
+                        "type": describe_type(ty),
+                        "default": json_value(default),
+                    }),
+                );
+            }
+        }
+    }
+
+    let output = match &node.port_types.output {
+        OutputType::Nothing => json!({ "sink": true }),
+        OutputType::OneOf(types) => json!({ "frame_types": frame_type_names(types) }),
+        OutputType::SameAsInput(input) => json!({ "same_as_input": input }),
+        OutputType::Unknown => json!({}),
+    };
+
+    json!({
+        "name": node.name,
+        "description": node.description,
+        "parameters": parameters,
+        "inputs": inputs,
+        "output": output,
+    })
+}
+
+pub(crate) fn describe_type(ty: &ParameterType) -> Value {
+    match ty {
+        FloatRange(min, max) => json!({ "kind": "float", "min": min, "max": max }),
+        IntRange(min, max) => json!({ "kind": "int", "min": min, "max": max }),
+        ListParameter(item) => json!({ "kind": "list", "items": describe_type(item) }),
+        StringParameter => json!({ "kind": "string" }),
+        ChoiceParameter(choices) => json!({ "kind": "choice", "choices": choices }),
+        PathParameter(check) => json!({ "kind": "path", "check": format!("{check:?}") }),
+        DurationParameter => json!({ "kind": "duration" }),
+        MapParameter => json!({ "kind": "map" }),
+        BoolParameter => json!({ "kind": "bool" }),
+        NodeInputParameter => json!({ "kind": "node-input" }),
+    }
+}
+
+fn frame_type_names(types: &[FrameType]) -> Vec<String> {
+    types.iter().map(|ty| format!("{ty:?}")).collect()
+}
+
+pub(crate) fn json_value(value: &ParameterValue) -> Value {
+    match value {
+        FloatRangeValue(v) => json!(v),
+        IntRangeValue(v) => json!(v),
+        StringValue(v) => json!(v),
+        BoolValue(v) => json!(v),
+        DurationValue(_) => json!(value.to_string()),
+        MapValue(v) => serde_json::to_value(v).unwrap_or(Value::Null),
+        ListValue(v) => Value::Array(v.iter().map(json_value).collect()),
+        NodeInputValue(_) => Value::Null,
+    }
+}
+
+/// A json schema for pipeline configuration files using the given nodes.
+pub fn pipeline_schema(nodes: &HashMap<String, ParameterizableDescriptor>) -> Value {
+    let mut names: Vec<_> = nodes.keys().collect();
+    names.sort();
+
+    let definitions: Map<_, _> =
+        names.iter().map(|name| (name.to_string(), node_schema(&nodes[*name]))).collect();
+    let mut references: Vec<_> =
+        names.iter().map(|name| json!({ "$ref": format!("#/definitions/{name}") })).collect();
+    // macros are used like nodes, their variables are only known in the files
+    references.push(json!({
+        "description": "an instance of a macro",
+        "type": "object",
+        "properties": { "type": { "type": "string", "not": { "enum": names } } },
+        "required": ["type"],
+    }));
+
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "axiom-recorder pipeline",
+        "description": "the nodes by their name, inputs refer to other nodes as <name",
+        "type": "object",
+        "properties": {
+            "$variables": {
+                "description": "the default values of the variables, ~ if they have to be set",
+                "type": "object",
+            },
+            "$include": {
+                "description": "files whose nodes and macros are added to this file",
+                "type": ["string", "array"],
+                "items": { "type": "string" },
+            },
+            "$macros": {
+                "description": "sub-graphs that are used like a node by setting their name as type",
+                "type": "object",
+                "additionalProperties": macro_schema(),
+            },
+        },
+        "additionalProperties": { "oneOf": references },
+        "definitions": definitions,
+    })
+}
+
+fn macro_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "inputs": { "type": "array", "items": { "type": "string" } },
+            "output": { "type": "string" },
+            "variables": { "type": "object" },
+            "nodes": { "type": "object" },
+        },
+        "required": ["output", "nodes"],
+        "additionalProperties": false,
+    })
+}
+
+fn node_schema(node: &ParameterizableDescriptor) -> Value {
+    let mut properties = Map::new();
+    properties.insert("type".to_string(), json!({ "const": node.name }));
+    let mut required = vec!["type".to_string()];
+    for (name, descriptor) in &node.parameters_descriptor.0 {
+        let schema = match descriptor {
+            Mandatory(ty) => type_schema(ty),
+            WithDefault(ty, default) => {
+                let mut schema = type_schema(ty);
+                schema["default"] = json_value(default);
+                schema
+            }
+        };
+        properties.insert(name.clone(), schema);
+        if let Mandatory(_) = descriptor {
+            required.push(name.clone());
+        }
+    }
+    required.sort();
+
+    let mut schema = json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    });
+    if let Some(description) = &node.description {
+        schema["description"] = json!(description);
+    }
+    schema
+}
+
+fn type_schema(ty: &ParameterType) -> Value {
+    match ty {
+        FloatRange(min, max) => json!({ "type": "number", "minimum": min, "maximum": max }),
+        IntRange(min, max) => json!({ "type": "integer", "minimum": min, "maximum": max }),
+        ListParameter(item) => json!({ "type": "array", "items": type_schema(item) }),
+        StringParameter => json!({ "type": "string" }),
+        ChoiceParameter(choices) => json!({ "type": "string", "enum": choices }),
+        PathParameter(_) => json!({ "type": "string" }),
+        DurationParameter => json!({ "type": ["string", "number"] }),
+        MapParameter => json!({ "type": "object" }),
+        BoolParameter => json!({ "type": "boolean" }),
+        NodeInputParameter => json!({ "type": "string", "pattern": "^<" }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::list_available_nodes;
+
+    #[test]
+    fn test_catalogue_and_schema() {
+        let nodes = list_available_nodes();
+
+        let catalogue = node_catalogue(&nodes);
+        let debayer =
+            catalogue.as_array().unwrap().iter().find(|node| node["name"] == "CpuDebayer").unwrap();
+        assert_eq!(debayer["inputs"]["input"]["frame_types"], json!(["Raw"]));
+        assert_eq!(debayer["output"]["frame_types"], json!(["Rgb"]));
+        assert_eq!(
+            debayer["parameters"]["mode"]["type"]["choices"],
+            json!(["bilinear", "edge-aware"])
+        );
+
+        let schema = pipeline_schema(&nodes);
+        let reader = &schema["definitions"]["RawBlobReader"];
+        assert_eq!(reader["properties"]["fps"]["default"], json!(24.0));
+        assert!(reader["required"].as_array().unwrap().contains(&json!("file")));
+        assert!(!reader["required"].as_array().unwrap().contains(&json!("fps")));
+
+        // the special keys are no nodes and macro instances can have any type but the
+        // ones of the nodes
+        assert_eq!(
+            schema["properties"]["$macros"]["additionalProperties"]["required"],
+            json!(["output", "nodes"])
+        );
+        let instance = schema["additionalProperties"]["oneOf"].as_array().unwrap().last().unwrap();
+        let node_types = instance["properties"]["type"]["not"]["enum"].as_array().unwrap();
+        assert_eq!(node_types.len(), nodes.len());
+        assert!(node_types.contains(&json!("RawBlobReader")));
+    }
+}
@@ -1,7 +1,11 @@
-use crate::pipeline_processing::{
-    frame::{CfaDescriptor, FrameInterpretations, Raw, Rgb},
-    node::{InputProcessingNode, Node, NodeID},
-    processing_context::ProcessingContext,
+use crate::{
+    pipeline_processing::{
+        frame::{CfaDescriptor, FrameInterpretations, FrameType, Raw, Rgb},
+        live_parameters::LiveParameter,
+        node::{InputProcessingNode, Node, NodeID},
+        processing_context::ProcessingContext,
+    },
+    util::edit_distance::closest_match,
 };
 use anyhow::{anyhow, Context, Error, Result};
 use prelude::*;
@@ -10,6 +14,8 @@ use std::{
     collections::HashMap,
     convert::TryInto,
     fmt::{Debug, Formatter},
+    path::Path,
+    time::Duration,
 };
 
 pub enum ParameterValue {
@@ -17,16 +23,20 @@ pub enum ParameterValue {
     IntRangeValue(i64),
     StringValue(String),
     BoolValue(bool),
+    DurationValue(Duration),
+    MapValue(serde_yaml::Mapping),
     NodeInputValue(InputProcessingNode),
     ListValue(Vec<ParameterValue>),
 }
 impl ParameterValue {
-    fn clone_for_same_puller(&self) -> Self {
+    pub(crate) fn clone_for_same_puller(&self) -> Self {
         match self {
             FloatRangeValue(f) => Self::FloatRangeValue(*f),
             IntRangeValue(i) => Self::IntRangeValue(*i),
             BoolValue(b) => Self::BoolValue(*b),
             StringValue(s) => Self::StringValue(s.clone()),
+            DurationValue(d) => Self::DurationValue(*d),
+            MapValue(m) => Self::MapValue(m.clone()),
             ListValue(l) => {
                 Self::ListValue(l.iter().map(ParameterValue::clone_for_same_puller).collect())
             }
@@ -42,6 +52,9 @@ impl ToString for ParameterValue {
             IntRangeValue(v) => v.to_string(),
             StringValue(v) => v.to_string(),
             BoolValue(v) => v.to_string(),
+            DurationValue(v) => format!("{}s", v.as_secs_f64()),
+            // json is a valid single line yaml mapping
+            MapValue(v) => serde_json::to_string(v).unwrap_or_default(),
             ListValue(v) => v.iter().map(ParameterValue::to_string).collect::<Vec<_>>().join(","),
             NodeInputValue(_) => "<NodeInput>".to_string(),
         }
@@ -132,6 +145,28 @@ impl TryInto<bool> for ParameterValue {
     }
 }
 
+impl TryInto<Duration> for ParameterValue {
+    type Error = Error;
+
+    fn try_into(self) -> Result<Duration, Self::Error> {
+        match self {
+            DurationValue(v) => Ok(v),
+            _ => Err(anyhow!("cant convert a non Duration ParameterValue to Duration")),
+        }
+    }
+}
+
+impl TryInto<serde_yaml::Mapping> for ParameterValue {
+    type Error = Error;
+
+    fn try_into(self) -> Result<serde_yaml::Mapping, Self::Error> {
+        match self {
+            MapValue(v) => Ok(v),
+            _ => Err(anyhow!("cant convert a non MapParameter ParameterValue to a mapping")),
+        }
+    }
+}
+
 impl TryInto<InputProcessingNode> for ParameterValue {
     type Error = Error;
 
@@ -147,10 +182,23 @@ impl TryInto<InputProcessingNode> for ParameterValue {
 #[derive(Debug)]
 pub struct Parameters {
     values: HashMap<String, ParameterValue>,
+    /// the name of the node in the graph and the types of its parameters, to
+    /// register live parameters
+    node_name: String,
+    types: HashMap<String, ParameterType>,
 }
 
 impl Parameters {
-    pub fn new(values: HashMap<String, ParameterValue>) -> Self { Self { values } }
+    pub fn new(values: HashMap<String, ParameterValue>) -> Self {
+        Self { values, node_name: String::new(), types: HashMap::new() }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> { self.values.keys() }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &ParameterValue)> { self.values.iter() }
+
+    /// The name of the node in the graph (empty outside of a graph)
+    pub fn node_name(&self) -> &str { &self.node_name }
 
     pub fn take<T>(&mut self, key: &str) -> Result<T>
     where
@@ -163,6 +211,51 @@ impl Parameters {
         parameter_value.try_into()
     }
 
+    /// Like `take`, but the value can be changed while the graph runs (see
+    /// `LiveParameters`). The node has to read it again for every frame.
+    pub fn take_live<T>(
+        &mut self,
+        key: &str,
+        context: &ProcessingContext,
+    ) -> Result<LiveParameter<T>>
+    where
+        ParameterValue: TryInto<T, Error = anyhow::Error>,
+    {
+        let ty = self
+            .types
+            .get(key)
+            .cloned()
+            .ok_or_else(|| anyhow!("the type of the live parameter {} is unknown", key))?;
+        let parameter_value = self
+            .values
+            .remove(key)
+            .ok_or_else(|| anyhow!("key {} not present in parameter storage", key))?;
+        context.live_parameters().register(&self.node_name, key, ty, parameter_value)
+    }
+
+    /// Like `take_live`, but new values are only accepted (and the control
+    /// server only answers with success) if they pass `check`.
+    pub fn take_live_checked<T: 'static>(
+        &mut self,
+        key: &str,
+        context: &ProcessingContext,
+        check: impl Fn(T) -> Result<()> + Send + Sync + 'static,
+    ) -> Result<LiveParameter<T>>
+    where
+        ParameterValue: TryInto<T, Error = anyhow::Error>,
+    {
+        let ty = self
+            .types
+            .get(key)
+            .cloned()
+            .ok_or_else(|| anyhow!("the type of the live parameter {} is unknown", key))?;
+        let parameter_value = self
+            .values
+            .remove(key)
+            .ok_or_else(|| anyhow!("key {} not present in parameter storage", key))?;
+        context.live_parameters().register_checked(&self.node_name, key, ty, parameter_value, check)
+    }
+
     // FIXME(robin): workaround to https://github.com/rust-lang/rust/issues/96634
     pub fn take_vec<T>(&mut self, key: &str) -> Result<Vec<T>>
     where
@@ -185,21 +278,59 @@ impl Parameters {
         puller_id: NodeID,
         inputs: HashMap<String, Node>,
     ) -> Result<Self> {
+        let mut lists = HashMap::<String, Vec<_>>::new();
         for (name, node) in inputs {
-            self.values.insert(
-                name.clone(),
-                ParameterValue::NodeInputValue(InputProcessingNode::new(
-                    puller_id,
-                    node.assert_input_node().with_context(|| {
-                        format!("could not convert input {name} to a input node")
-                    })?,
-                )),
-            );
+            let value = ParameterValue::NodeInputValue(InputProcessingNode::new(
+                puller_id,
+                node.assert_input_node()
+                    .with_context(|| format!("could not convert input {name} to a input node"))?,
+            ));
+            match split_list_input_key(&name) {
+                Some((list, index)) => {
+                    lists.entry(list.to_string()).or_default().push((index, value))
+                }
+                None => {
+                    self.values.insert(name, value);
+                }
+            }
+        }
+        for (name, mut values) in lists {
+            values.sort_by_key(|(index, _)| *index);
+            self.values.insert(name, ListValue(values.into_iter().map(|(_, v)| v).collect()));
+        }
+
+        Ok(self)
+    }
+
+    /// Converts the values to the types of the description (for example
+    /// choices to their spelling in the description and strings to durations)
+    /// and checks the paths, before the values are handed to the node. The
+    /// types are kept for `take_live`.
+    pub(crate) fn check_values(mut self, description: &ParametersDescriptor) -> Result<Self> {
+        for (name, descriptor) in &description.0 {
+            if descriptor.is_node_input() {
+                continue;
+            }
+            let ty = descriptor.parameter_type();
+            self.types.insert(name.clone(), ty.clone());
+            if let Some(value) = self.values.remove(name) {
+                let value = ty
+                    .value_is_of_type(value)
+                    .and_then(|value| ty.check_path(&value).map(|_| value))
+                    .with_context(|| format!("invalid value for {name}"))?;
+                self.values.insert(name.clone(), value);
+            }
         }
 
         Ok(self)
     }
 
+    /// Sets the name under which the live parameters of the node are
+    /// registered.
+    pub(crate) fn for_node(self, node_name: &str) -> Self {
+        Self { node_name: node_name.to_string(), ..self }
+    }
+
     pub(crate) fn add_defaults(mut self, description: ParametersDescriptor) -> Self {
         for (name, value) in description.0 {
             if let WithDefault(_, value) = value {
@@ -219,11 +350,35 @@ impl Parameters {
             self.take("red-in-first-row")?,
         );
         let fps = self.take("fps")?;
+        let black_level = match self.take_vec::<u64>("black-level")?[..] {
+            [] => Raw::default_black_level(bit_depth),
+            [level] => [level as u32; 4],
+            [a, b, c, d] => [a as u32, b as u32, c as u32, d as u32],
+            ref levels => {
+                return Err(anyhow!(
+                    "black-level needs to be one value or one value for each of the four cfa \
+                     positions, but {} values were given",
+                    levels.len()
+                ))
+            }
+        };
+        let white_level = match self.take::<u64>("white-level")? {
+            0 => Raw::default_white_level(bit_depth),
+            level => level as u32,
+        };
 
         if self.take("rgb")? {
             Ok(FrameInterpretations::Rgb(Rgb { width, height, fps }))
         } else {
-            Ok(FrameInterpretations::Raw(Raw { bit_depth, width, height, cfa, fps }))
+            Ok(FrameInterpretations::Raw(Raw {
+                bit_depth,
+                width,
+                height,
+                cfa,
+                fps,
+                black_level,
+                white_level,
+            }))
         }
     }
 }
@@ -234,15 +389,66 @@ pub enum ParameterType {
     IntRange(i64, i64),
     ListParameter(Box<ParameterType>),
     StringParameter,
+    /// a string that has to be one of the choices (ignoring the case)
+    ChoiceParameter(Vec<&'static str>),
+    /// a file or directory, an empty string is no path
+    PathParameter(PathCheck),
+    /// a number with a unit (ns, us, ms, s, min or h), plain numbers are
+    /// seconds
+    DurationParameter,
+    /// a yaml mapping (or json object) for structured configuration
+    MapParameter,
     BoolParameter,
     NodeInputParameter,
 }
 
+/// What has to be true for the path of a `PathParameter` when the node is
+/// created
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathCheck {
+    Any,
+    Exists,
+    NotExists,
+}
+
 impl ParameterType {
     pub fn value_is_of_type(&self, value: ParameterValue) -> Result<ParameterValue> {
         match (self, &value) {
             (StringParameter, ParameterValue::StringValue(_)) => Ok(value),
+            (ChoiceParameter(choices), ParameterValue::StringValue(v)) => {
+                match find_choice(choices, v) {
+                    Some(choice) => Ok(ParameterValue::StringValue(choice.to_string())),
+                    None => Err(anyhow!(
+                        "value {v} is not one of {}{}",
+                        choices.join(", "),
+                        closest_match(v, choices.iter().copied())
+                            .map(|choice| format!(", did you mean {choice}?"))
+                            .unwrap_or_default()
+                    )),
+                }
+            }
             (BoolParameter, ParameterValue::BoolValue(_)) => Ok(value),
+            (PathParameter(_), ParameterValue::StringValue(_)) => Ok(value),
+            (MapParameter, ParameterValue::MapValue(_)) => Ok(value),
+            (DurationParameter, ParameterValue::DurationValue(_)) => Ok(value),
+            (DurationParameter, ParameterValue::StringValue(v)) => {
+                Ok(ParameterValue::DurationValue(parse_duration(v)?))
+            }
+            (DurationParameter, ParameterValue::IntRangeValue(v)) if *v >= 0 => {
+                Ok(ParameterValue::DurationValue(Duration::from_secs(*v as u64)))
+            }
+            (DurationParameter, ParameterValue::FloatRangeValue(v)) => {
+                Ok(ParameterValue::DurationValue(
+                    Duration::try_from_secs_f64(*v)
+                        .map_err(|_| anyhow!("{v} is no positive duration"))?,
+                ))
+            }
+            (ListParameter(ty), ParameterValue::ListValue(values)) => Ok(ListValue(
+                values
+                    .iter()
+                    .map(|v| ty.value_is_of_type(v.clone_for_same_puller()))
+                    .collect::<Result<_>>()?,
+            )),
             (FloatRange(min, max), ParameterValue::FloatRangeValue(v)) => {
                 if (v >= min) && (v <= max) {
                     Ok(value)
@@ -263,6 +469,15 @@ impl ParameterType {
     pub fn parse(&self, string: &str) -> Result<ParameterValue> {
         match self {
             StringParameter => Ok(ParameterValue::StringValue(string.to_string())),
+            ChoiceParameter(_) | PathParameter(_) | DurationParameter => {
+                self.value_is_of_type(ParameterValue::StringValue(string.to_string()))
+            }
+            MapParameter if string.trim().is_empty() => {
+                Ok(ParameterValue::MapValue(Default::default()))
+            }
+            MapParameter => Ok(ParameterValue::MapValue(
+                serde_yaml::from_str(string).context("expected a mapping like {a: 1, b: 2}")?,
+            )),
             BoolParameter => Ok(ParameterValue::BoolValue(string.parse()?)),
             IntRange(..) => self.value_is_of_type(ParameterValue::IntRangeValue(string.parse()?)),
             FloatRange(..) => {
@@ -285,10 +500,69 @@ impl ParameterType {
             IntRange(min, _) => IntRangeValue(*min),
             ListParameter(_) => ListValue(vec![]),
             StringParameter => StringValue("".to_string()),
+            ChoiceParameter(choices) => StringValue(choices[0].to_string()),
+            PathParameter(_) => StringValue("".to_string()),
+            DurationParameter => DurationValue(Duration::ZERO),
+            MapParameter => MapValue(Default::default()),
             BoolParameter => BoolValue(false),
             NodeInputParameter => panic!("no default value for node input"),
         }
     }
+
+    /// Checks that the path of a `PathParameter` fulfills its `PathCheck`,
+    /// this is done when the node is created and not when a graph is validated
+    pub fn check_path(&self, value: &ParameterValue) -> Result<()> {
+        let path = match (self, value) {
+            (PathParameter(_), StringValue(path)) if !path.is_empty() => Path::new(path),
+            _ => return Ok(()),
+        };
+        match self {
+            PathParameter(PathCheck::Exists) if !path.exists() => {
+                Err(anyhow!("{} doesn't exist", path.display()))
+            }
+            PathParameter(PathCheck::NotExists) if path.exists() => {
+                Err(anyhow!("{} already exists", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parses durations like `1.5s`, `40ms` or `2min`, plain numbers are seconds
+pub fn parse_duration(string: &str) -> Result<Duration> {
+    let string = string.trim();
+    let split = string
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == 'e' || c == '-' || c == '+'))
+        .unwrap_or(string.len());
+    let (number, unit) = string.split_at(split);
+    let number: f64 =
+        number.parse().with_context(|| format!("{string} is no duration like 1.5s or 40ms"))?;
+    let seconds = match unit.trim() {
+        "ns" => number / 1e9,
+        "us" | "µs" => number / 1e6,
+        "ms" => number / 1e3,
+        "" | "s" => number,
+        "min" => number * 60.0,
+        "h" => number * 3600.0,
+        unit => {
+            return Err(anyhow!("unknown unit {unit} of {string}, use ns, us, ms, s, min or h"))
+        }
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|_| anyhow!("{string} is no positive duration"))
+}
+
+fn find_choice(choices: &[&'static str], value: &str) -> Option<&'static str> {
+    choices.iter().copied().find(|choice| choice.eq_ignore_ascii_case(value))
+}
+
+/// The key under which the input with the given index of a list of inputs
+/// (`ListParameter(NodeInputParameter)`) is stored in the graph
+pub fn list_input_key(name: &str, index: usize) -> String { format!("{name}[{index}]") }
+
+/// The name of the list and the index for keys created by `list_input_key`
+pub fn split_list_input_key(key: &str) -> Option<(&str, usize)> {
+    let (name, index) = key.strip_suffix(']')?.split_once('[')?;
+    Some((name, index.parse().ok()?))
 }
 
 #[derive(Debug)]
@@ -307,6 +581,20 @@ impl Clone for ParameterTypeDescriptor {
 }
 
 impl ParameterTypeDescriptor {
+    pub fn parameter_type(&self) -> &ParameterType {
+        match self {
+            Mandatory(ty) | WithDefault(ty, _) => ty,
+        }
+    }
+    /// Whether this refers to other nodes (a single one or a list of them)
+    /// instead of being a plain value
+    pub fn is_node_input(&self) -> bool {
+        match self.parameter_type() {
+            NodeInputParameter => true,
+            ListParameter(ty) => matches!(**ty, NodeInputParameter),
+            _ => false,
+        }
+    }
     pub fn parse(&self, string: Option<&str>) -> Result<ParameterValue> {
         match self {
             Mandatory(parameter_type) => {
@@ -342,14 +630,57 @@ impl ParametersDescriptor {
             .with("red-in-first-row", WithDefault(BoolParameter, BoolValue(true)))
             .with("rgb", Optional(BoolParameter))
             .with("fps", WithDefault(PositiveReal(), FloatRangeValue(24.0)))
+            .with("black-level", Optional(ListParameter(Box::new(NaturalWithZero()))))
+            .with("white-level", Optional(NaturalWithZero()))
     }
 }
 
+/// What a node produces on its output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputType {
+    /// the node is a sink and can't be used as the input of other nodes
+    Nothing,
+    /// the node produces one of these frame types
+    OneOf(Vec<FrameType>),
+    /// the node passes through whatever it gets on the input with this name
+    SameAsInput(String),
+    /// the output type is only known at runtime
+    Unknown,
+}
+
+/// The frame types a node accepts on its inputs and produces on its output.
+/// Inputs that are not listed accept every frame type.
+#[derive(Debug, Clone)]
+pub struct PortTypes {
+    pub inputs: HashMap<String, Vec<FrameType>>,
+    pub output: OutputType,
+}
+
+impl Default for PortTypes {
+    fn default() -> Self { Self::new() }
+}
+
+impl PortTypes {
+    pub fn new() -> Self { PortTypes { inputs: HashMap::new(), output: OutputType::Unknown } }
+    pub fn with_input(mut self, name: &str, types: &[FrameType]) -> Self {
+        self.inputs.insert(name.to_string(), types.to_vec());
+        self
+    }
+    pub fn with_output(self, types: &[FrameType]) -> Self {
+        PortTypes { output: OutputType::OneOf(types.to_vec()), ..self }
+    }
+    pub fn with_output_of(self, input: &str) -> Self {
+        PortTypes { output: OutputType::SameAsInput(input.to_string()), ..self }
+    }
+    pub fn sink(self) -> Self { PortTypes { output: OutputType::Nothing, ..self } }
+}
+
 #[derive(Debug)]
 pub struct ParameterizableDescriptor {
     pub name: String,
     pub description: Option<String>,
     pub parameters_descriptor: ParametersDescriptor,
+    pub port_types: PortTypes,
 }
 
 pub trait Parameterizable {
@@ -357,6 +688,9 @@ pub trait Parameterizable {
     const DESCRIPTION: Option<&'static str> = None;
 
     fn describe_parameters() -> ParametersDescriptor;
+    /// The frame types of the inputs and the output, nodes that don't
+    /// describe them are only checked at runtime
+    fn describe_port_types() -> PortTypes { PortTypes::new() }
     fn from_parameters(
         parameters: Parameters,
         is_input_to: &[NodeID],
@@ -375,6 +709,7 @@ pub trait Parameterizable {
             name: Self::get_name(),
             description: Self::DESCRIPTION.map(|s| s.to_string()),
             parameters_descriptor: Self::describe_parameters(),
+            port_types: Self::describe_port_types(),
         }
     }
 }
@@ -383,6 +718,7 @@ pub trait Parameterizable {
 #[allow(non_snake_case)]
 pub mod prelude {
     pub use super::{
+        OutputType,
         ParameterType::{self, *},
         ParameterTypeDescriptor::{self, *},
         ParameterValue::{self, *},
@@ -390,7 +726,10 @@ pub mod prelude {
         ParameterizableDescriptor,
         Parameters,
         ParametersDescriptor,
+        PathCheck,
+        PortTypes,
     };
+    pub use crate::pipeline_processing::frame::FrameType;
 
     pub fn Optional(ty: ParameterType) -> ParameterTypeDescriptor {
         WithDefault(ty.clone(), ty.default_value())
@@ -401,3 +740,38 @@ pub mod prelude {
     pub fn Bool() -> ParameterType { BoolParameter }
     pub fn PositiveReal() -> ParameterType { FloatRange(0.0, f64::MAX) }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_duration("40ms").unwrap(), Duration::from_millis(40));
+        assert_eq!(parse_duration("2 min").unwrap(), Duration::from_secs(120));
+        assert_eq!(parse_duration("3").unwrap(), Duration::from_secs(3));
+        assert!(parse_duration("-1s").is_err());
+        assert!(parse_duration("1 fortnight").is_err());
+        assert!(parse_duration("ms").is_err());
+    }
+
+    #[test]
+    fn test_parse_parameter_types() {
+        let duration = DurationParameter.parse("250us").unwrap();
+        assert_eq!(duration.to_string(), "0.00025s");
+        assert_eq!(DurationParameter.parse(&duration.to_string()).unwrap().to_string(), "0.00025s");
+
+        let map = MapParameter.parse("{ a: 1, b: [2, 3] }").unwrap();
+        assert_eq!(map.to_string(), r#"{"a":1,"b":[2,3]}"#);
+        assert!(MapParameter.parse("[1, 2]").is_err());
+
+        let directory = std::env::temp_dir().to_string_lossy().to_string();
+        let path = PathParameter(PathCheck::Exists).parse(&directory).unwrap();
+        PathParameter(PathCheck::Exists).check_path(&path).unwrap();
+        assert!(PathParameter(PathCheck::NotExists).check_path(&path).is_err());
+        let missing = StringValue(format!("{directory}/does-not-exist"));
+        assert!(PathParameter(PathCheck::Exists).check_path(&missing).is_err());
+        PathParameter(PathCheck::Exists).check_path(&StringValue("".to_string())).unwrap();
+    }
+}
@@ -0,0 +1,420 @@
+//! Loading of pipeline configuration files.
+//!
+//! A pipeline file is a yaml mapping from node names to node configs. Before it
+//! is parsed, it is rendered as a handlebars template with the variables given
+//! on the cli. Besides the nodes, a file can contain these special entries:
+//!
+//! * `$variables`: the default values of variables. Variables without a default
+//!   (`~`) have to be set. This block is read before the file is rendered, so
+//!   it can't use variables itself.
+//! * `$include`: a list of files (relative to the including file) whose nodes
+//!   and macros are added to this file. They are rendered with the variables of
+//!   the including file.
+//! * `$macros`: named sub-graphs, which are used like a node by setting their
+//!   name as `type`:
+//!
+//! ```yaml
+//! $macros:
+//!   calibrate:
+//!     inputs: [input]
+//!     output: convert
+//!     variables:
+//!       darkframe: ~
+//!       to: 8
+//!     nodes:
+//!       subtract: { type: DarkframeSubtract, input: <input, darkframe: $darkframe }
+//!       convert: { type: BitDepthConverter, input: <subtract, to: $to }
+//!
+//! calibrated:
+//!   type: calibrate
+//!   input: <reader
+//!   darkframe: dark.raw
+//! ```
+//!
+//! The nodes of a macro are named `<instance>.<node>` (here
+//! `calibrated.subtract` and `calibrated.convert`) and references to the
+//! instance refer to its output node. Inside the macro, values of the form
+//! `$name` are replaced by the variables of the macro and `<name` can refer to
+//! the inputs of the macro or its other nodes.
+
+use crate::pipeline_processing::processing_graph::{ProcessingGraphBuilder, SerdeNodeConfig};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+const VARIABLES_KEY: &str = "$variables";
+const INCLUDE_KEY: &str = "$include";
+const MACROS_KEY: &str = "$macros";
+const MAX_MACRO_DEPTH: usize = 16;
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Macro {
+    #[serde(default)]
+    inputs: Vec<String>,
+    output: String,
+    #[serde(default)]
+    variables: BTreeMap<String, Value>,
+    nodes: Mapping,
+}
+
+#[derive(Default)]
+struct PipelineFile {
+    nodes: Mapping,
+    macros: HashMap<String, Macro>,
+}
+
+/// Reads the pipeline file at `path` (and everything it includes) and expands
+/// all macros in it.
+pub fn load_pipeline(
+    path: &Path,
+    variables: &BTreeMap<String, Value>,
+) -> Result<ProcessingGraphBuilder<String>> {
+    let mut pipeline = PipelineFile::default();
+    read_file(path, variables, &mut pipeline, &mut vec![])?;
+
+    let mut builder = ProcessingGraphBuilder::new();
+    for (name, node) in expand_macros(pipeline.nodes, &pipeline.macros)? {
+        let name = key_to_string(name)?;
+        let config = serde_yaml::from_value::<SerdeNodeConfig>(node)
+            .map_err(anyhow::Error::from)
+            .and_then(|config| config.try_into())
+            .with_context(|| format!("invalid config for node {name:?}"))?;
+        builder.add(name, config)?;
+    }
+    Ok(builder)
+}
+
+fn read_file(
+    path: &Path,
+    inherited_variables: &BTreeMap<String, Value>,
+    pipeline: &mut PipelineFile,
+    include_stack: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let canonical_path =
+        path.canonicalize().with_context(|| format!("couldn't open {}", path.display()))?;
+    if include_stack.contains(&canonical_path) {
+        return Err(anyhow!("{} (indirectly) includes itself", path.display()));
+    }
+
+    let template = std::fs::read_to_string(path)?;
+    let mut variables = declared_variables(&template)
+        .with_context(|| format!("invalid {VARIABLES_KEY} in {}", path.display()))?;
+    variables.extend(inherited_variables.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+    let mut handlebars = handlebars::Handlebars::new();
+    handlebars.set_strict_mode(true);
+    let rendered = handlebars
+        .render_template(&template, &variables)
+        .with_context(|| format!("couldn't render {}", path.display()))?;
+    let mut config = match serde_yaml::from_str(&rendered)
+        .with_context(|| format!("couldn't parse {}", path.display()))?
+    {
+        Value::Mapping(config) => config,
+        Value::Null => Mapping::new(),
+        _ => return Err(anyhow!("{} has to contain a mapping of nodes", path.display())),
+    };
+    config.remove(VARIABLES_KEY);
+
+    if let Some(includes) = config.remove(INCLUDE_KEY) {
+        let includes: Vec<String> = match includes {
+            Value::String(include) => vec![include],
+            includes => serde_yaml::from_value(includes).with_context(|| {
+                format!("{INCLUDE_KEY} in {} has to be a list of files", path.display())
+            })?,
+        };
+        let directory = path.parent().unwrap_or_else(|| Path::new("."));
+        include_stack.push(canonical_path);
+        for include in includes {
+            read_file(&directory.join(include), &variables, pipeline, include_stack)?;
+        }
+        include_stack.pop();
+    }
+
+    if let Some(macros) = config.remove(MACROS_KEY) {
+        let macros: BTreeMap<String, Macro> = serde_yaml::from_value(macros)
+            .with_context(|| format!("invalid {MACROS_KEY} in {}", path.display()))?;
+        for (name, definition) in macros {
+            if pipeline.macros.insert(name.clone(), definition).is_some() {
+                return Err(anyhow!("macro {name} is defined twice (again in {})", path.display()));
+            }
+        }
+    }
+
+    for (name, node) in config {
+        insert_node(&mut pipeline.nodes, key_to_string(name)?, node)
+            .with_context(|| format!("while reading {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// The defaults of the variables declared in the `$variables` block. They are
+/// needed to render the file, so it is first rendered with all variables left
+/// empty to parse this block.
+fn declared_variables(template: &str) -> Result<BTreeMap<String, Value>> {
+    #[derive(Deserialize)]
+    struct Declarations {
+        #[serde(rename = "$variables", default)]
+        variables: Option<BTreeMap<String, Value>>,
+    }
+
+    let rendered = handlebars::Handlebars::new().render_template(template, &())?;
+    let declarations: Option<Declarations> = serde_yaml::from_str(&rendered)?;
+    let variables = declarations.and_then(|declarations| declarations.variables);
+    Ok(variables.unwrap_or_default().into_iter().filter(|(_, value)| !value.is_null()).collect())
+}
+
+fn expand_macros(mut nodes: Mapping, macros: &HashMap<String, Macro>) -> Result<Mapping> {
+    // every round expands one level of macros, that might use other macros
+    for _ in 0..MAX_MACRO_DEPTH {
+        let mut expanded = Mapping::new();
+        let mut instance_outputs = HashMap::new();
+        for (name, node) in nodes {
+            let name = key_to_string(name)?;
+            let definition = node.get("type").and_then(Value::as_str).and_then(|ty| macros.get(ty));
+            match definition {
+                Some(definition) => {
+                    instantiate_macro(&name, node, definition, &mut expanded)
+                        .with_context(|| format!("couldn't expand the macro node {name:?}"))?;
+                    instance_outputs.insert(name.clone(), format!("{name}.{}", definition.output));
+                }
+                None => insert_node(&mut expanded, name, node)?,
+            }
+        }
+
+        if instance_outputs.is_empty() {
+            return Ok(expanded);
+        }
+        for node in expanded.values_mut() {
+            for value in node.as_mapping_mut().into_iter().flat_map(|node| node.values_mut()) {
+                redirect_references(value, &instance_outputs);
+            }
+        }
+        nodes = expanded;
+    }
+
+    Err(anyhow!(
+        "macros are nested more than {MAX_MACRO_DEPTH} levels deep, does a macro use itself?"
+    ))
+}
+
+/// Replaces references to macro instances with references to their outputs
+fn redirect_references(value: &mut Value, instance_outputs: &HashMap<String, String>) {
+    match value {
+        Value::String(string) => {
+            let output =
+                string.strip_prefix('<').and_then(|reference| instance_outputs.get(reference));
+            if let Some(output) = output {
+                *value = Value::String(format!("<{output}"));
+            }
+        }
+        Value::Sequence(values) => {
+            for value in values {
+                redirect_references(value, instance_outputs);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn instantiate_macro(
+    name: &str,
+    node: Value,
+    definition: &Macro,
+    expanded: &mut Mapping,
+) -> Result<()> {
+    if !definition.nodes.contains_key(definition.output.as_str()) {
+        return Err(anyhow!("the output {} is no node of the macro", definition.output));
+    }
+
+    let mut inputs = HashMap::new();
+    let mut variables = definition.variables.clone();
+    for (key, value) in node.as_mapping().into_iter().flatten() {
+        let key = key_to_string(key.clone())?;
+        if key == "type" {
+            continue;
+        } else if definition.inputs.contains(&key) {
+            let reference = value
+                .as_str()
+                .filter(|value| value.starts_with('<'))
+                .ok_or_else(|| anyhow!("the input {key} has to refer to a node (<name)"))?;
+            inputs.insert(key, reference.to_string());
+        } else if variables.contains_key(&key) {
+            variables.insert(key, value.clone());
+        } else {
+            return Err(anyhow!("the macro has no input or variable {key}"));
+        }
+    }
+    if let Some(input) = definition.inputs.iter().find(|input| !inputs.contains_key(*input)) {
+        return Err(anyhow!("the input {input} is missing"));
+    }
+    if let Some((variable, _)) = variables.iter().find(|(_, value)| value.is_null()) {
+        return Err(anyhow!("the variable {variable} has no default and is missing"));
+    }
+
+    for (node_name, node) in &definition.nodes {
+        let node_name = key_to_string(node_name.clone())?;
+        let mut node = node.clone();
+        for value in node.as_mapping_mut().into_iter().flat_map(|node| node.values_mut()) {
+            substitute(value, name, &inputs, &variables, definition)
+                .with_context(|| format!("in the macro node {node_name}"))?;
+        }
+        insert_node(expanded, format!("{name}.{node_name}"), node)?;
+    }
+
+    Ok(())
+}
+
+/// Replaces the `$variable` and `<name` references in a value of a macro node
+fn substitute(
+    value: &mut Value,
+    instance: &str,
+    inputs: &HashMap<String, String>,
+    variables: &BTreeMap<String, Value>,
+    definition: &Macro,
+) -> Result<()> {
+    match value {
+        Value::String(string) => {
+            if let Some(variable) = string.strip_prefix('$') {
+                *value = variables
+                    .get(variable)
+                    .ok_or_else(|| anyhow!("there is no variable {variable}"))?
+                    .clone();
+            } else if let Some(reference) = string.strip_prefix('<') {
+                *value = if let Some(input) = inputs.get(reference) {
+                    Value::String(input.clone())
+                } else if definition.nodes.contains_key(reference) {
+                    Value::String(format!("<{instance}.{reference}"))
+                } else {
+                    return Err(anyhow!("{reference} is neither an input nor a node of the macro"));
+                };
+            }
+        }
+        Value::Sequence(values) => {
+            for value in values {
+                substitute(value, instance, inputs, variables, definition)?;
+            }
+        }
+        Value::Mapping(values) => {
+            for value in values.values_mut() {
+                substitute(value, instance, inputs, variables, definition)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn insert_node(nodes: &mut Mapping, name: String, node: Value) -> Result<()> {
+    if nodes.contains_key(name.as_str()) {
+        return Err(anyhow!("there are multiple nodes named {name:?}"));
+    }
+    nodes.insert(Value::String(name), node);
+    Ok(())
+}
+
+fn key_to_string(key: Value) -> Result<String> {
+    match key {
+        Value::String(key) => Ok(key),
+        key => Err(anyhow!("node names have to be strings, but {key:?} is not")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(directory: &Path, name: &str, content: &str) -> PathBuf {
+        let path = directory.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_includes_and_macros() {
+        let directory = std::env::temp_dir().join(format!("pipeline-file-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        write(
+            &directory,
+            "calibrate.yml",
+            "
+$macros:
+  calibrate:
+    inputs: [input]
+    output: convert
+    variables:
+      darkframe: ~
+      to: 8
+    nodes:
+      subtract: { type: CpuDarkframeSubtract, input: <input, darkframe: $darkframe, width: 16, height: 16 }
+      convert: { type: BitDepthConverter, input: <subtract, to: $to }
+",
+        );
+        let path = write(
+            &directory,
+            "pipeline.yml",
+            "
+$variables:
+  width: 16
+  file: ~
+
+$include: [calibrate.yml]
+
+reader: { type: RawBlobReader, file: '{{file}}', width: {{width}}, height: 16 }
+first: { type: calibrate, input: <reader, darkframe: a.raw }
+second: { type: calibrate, input: <first, darkframe: b.raw, to: 12 }
+writer: { type: RawBlobWriter, path: out.raw, input: <second }
+",
+        );
+
+        let variables = [("file".to_string(), Value::from("in.raw"))].into_iter().collect();
+        let builder = load_pipeline(&path, &variables).unwrap();
+        builder.validate().unwrap();
+        let yaml: Mapping = serde_yaml::from_str(&builder.to_yaml().unwrap()).unwrap();
+        assert_eq!(yaml["reader"]["file"], Value::from("in.raw"));
+        assert_eq!(yaml["first.subtract"]["input"], Value::from("<reader"));
+        assert_eq!(yaml["first.subtract"]["darkframe"], Value::from("a.raw"));
+        assert_eq!(yaml["first.convert"]["to"], Value::from(8));
+        assert_eq!(yaml["second.subtract"]["input"], Value::from("<first.convert"));
+        assert_eq!(yaml["second.convert"]["to"], Value::from(12));
+        assert_eq!(yaml["writer"]["input"], Value::from("<second.convert"));
+
+        // file has no default
+        assert!(load_pipeline(&path, &BTreeMap::new()).is_err());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_declared_variables() {
+        let variables = declared_variables(
+            "
+reader: { type: RawDirectoryReader, file-pattern: {{dir}}/*.raw12, width: {{width}} }
+$variables:   # after the nodes
+  dir: ~
+  width: 4096
+",
+        )
+        .unwrap();
+        assert_eq!(variables, BTreeMap::from([("width".to_string(), Value::from(4096))]));
+        assert!(declared_variables("reader: { type: RawBlobReader }").unwrap().is_empty());
+        assert!(declared_variables("").unwrap().is_empty());
+        assert!(declared_variables("$variables: [width]").is_err());
+    }
+
+    #[test]
+    fn test_shipped_pipelines() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR"));
+        for file in ["calibrate.yml", "capture_calibrated.yml", "darkframe.yml", "config.yaml"] {
+            load_pipeline(&directory.join(file), &BTreeMap::new())
+                .and_then(|builder| builder.validate())
+                .with_context(|| format!("{file} is invalid"))
+                .unwrap();
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use crate::pipeline_processing::{
     buffers::{CpuBuffer, GpuBuffer},
-    frame::{Frame, Raw, Rgb, Rgba, SZ3Compressed},
+    frame::{Frame, LosslessCompressed, Raw, Rgb, Rgba, SZ3Compressed},
+    live_parameters::LiveParameters,
     payload::Payload,
     prioritized_executor::PrioritizedReactor,
+    run_control::RunControl,
 };
 use anyhow::{anyhow, Result};
 use parking_lot::lock_api::RwLock;
@@ -74,6 +76,8 @@ pub struct ProcessingContext {
     vulkan_device: Option<VulkanContext>,
     prioritized_reactor: PrioritizedReactor<Priority>,
     tokio_rt_handle: Arc<tokio::runtime::Runtime>,
+    live_parameters: LiveParameters,
+    run_control: RunControl,
 }
 impl Default for ProcessingContext {
     fn default() -> Self {
@@ -163,6 +167,8 @@ impl ProcessingContext {
             vulkan_device: vulkan_context,
             prioritized_reactor: PrioritizedReactor::start(threads),
             tokio_rt_handle: Arc::new(tokio::runtime::Runtime::new().unwrap()),
+            live_parameters: LiveParameters::default(),
+            run_control: RunControl::default(),
         }
     }
 
@@ -229,7 +235,11 @@ impl ProcessingContext {
         // dropping this future blocks this thread until the gpu finished the work
         drop(future);
 
-        Ok(Frame { interp: frame.interp.clone(), storage: buffer })
+        Ok(Frame {
+            interp: frame.interp.clone(),
+            storage: buffer,
+            metadata: frame.metadata.clone(),
+        })
     }
     pub fn ensure_cpu_buffer<Interpretation: Clone + Send + Sync + 'static>(
         &self,
@@ -259,7 +269,7 @@ impl ProcessingContext {
                 )*
             };
         }
-        conv!(Raw, Rgb, Rgba, SZ3Compressed);
+        conv!(Raw, Rgb, Rgba, SZ3Compressed, LosslessCompressed);
 
         return Err(anyhow!(
             "wanted to convert frame {} to a byte array, but this was not possible",
@@ -267,6 +277,8 @@ impl ProcessingContext {
         ));
     }
 
+    pub fn has_vulkan(&self) -> bool { self.vulkan_device.is_some() }
+
     pub fn require_vulkan(&self) -> Result<(Arc<Device>, Vec<Arc<Queue>>)> {
         if let Some(vulkan_context) = &self.vulkan_device {
             Ok((vulkan_context.device.clone(), vulkan_context.queues.clone()))
@@ -287,5 +299,61 @@ impl ProcessingContext {
         self.tokio_rt_handle.block_on(fut)
     }
 
+    /// Spawns a future on the tokio runtime, which is needed for async io.
+    pub fn spawn_io<O: Send + 'static>(
+        &self,
+        fut: impl Future<Output = O> + Send + 'static,
+    ) -> tokio::task::JoinHandle<O> {
+        self.tokio_rt_handle.spawn(fut)
+    }
+
     pub fn num_threads(&self) -> usize { self.prioritized_reactor.num_threads }
+
+    /// The parameters the nodes of this context allow to change while they run
+    pub fn live_parameters(&self) -> &LiveParameters { &self.live_parameters }
+
+    /// Pauses, resumes or stops the graph running with this context and
+    /// collects the status of its sinks
+    pub fn run_control(&self) -> &RunControl { &self.run_control }
+
+    /// Splits `input` into chunks of whole rows and processes them in parallel
+    /// on the executor threads. `func` is called with the index of the first
+    /// row of the chunk, the input rows and the output rows to fill.
+    pub async fn map_rows_parallel(
+        &self,
+        priority: Priority,
+        input: &CpuBuffer,
+        rows: usize,
+        (in_row_bytes, out_row_bytes): (usize, usize),
+        func: impl Fn(usize, &[u8], &mut [u8]) + Send + Sync + 'static,
+    ) -> CpuBuffer {
+        let func = Arc::new(func);
+        let rows_per_chunk = ((rows + self.num_threads() - 1) / self.num_threads()).max(1);
+
+        let chunks = (0..rows).step_by(rows_per_chunk).map(|first_row| {
+            let last_row = (first_row + rows_per_chunk).min(rows);
+            let input = input.clone();
+            let func = func.clone();
+            self.spawn(priority, async move {
+                let mut chunk = vec![0u8; (last_row - first_row) * out_row_bytes];
+                input.as_slice(|input| {
+                    func(
+                        first_row,
+                        &input[first_row * in_row_bytes..last_row * in_row_bytes],
+                        &mut chunk,
+                    )
+                });
+                chunk
+            })
+        });
+        let chunks = futures::future::join_all(chunks).await;
+
+        let mut output = unsafe { self.get_uninit_cpu_buffer(rows * out_row_bytes) };
+        output.as_mut_slice(|output| {
+            for (output, chunk) in output.chunks_mut(rows_per_chunk * out_row_bytes).zip(chunks) {
+                output.copy_from_slice(&chunk);
+            }
+        });
+        output
+    }
 }
@@ -1,18 +1,21 @@
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use futures::StreamExt;
 use serde::{Deserialize, Deserializer};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
     sync::Arc,
 };
 
 use crate::{
-    nodes::create_node_from_name,
+    nodes::{create_node_from_name, list_available_nodes},
     pipeline_processing::{
         node::{Node, NodeID, ProgressUpdate},
-        parametrizable::{ParameterValue, Parameters},
+        parametrizable::{list_input_key, prelude::*, split_list_input_key},
         processing_context::ProcessingContext,
+        run_control::GraphNode,
     },
+    util::edit_distance::closest_match,
 };
 
 #[derive(Debug)]
@@ -44,6 +47,7 @@ pub enum SerdeNodeParam {
     String(String),
     NodeInput(String),
     List(Vec<SerdeNodeParam>),
+    Map(serde_yaml::Mapping),
 }
 
 impl<'de> Deserialize<'de> for SerdeNodeParam {
@@ -59,6 +63,7 @@ impl<'de> Deserialize<'de> for SerdeNodeParam {
             Bool(bool),
             String(String),
             List(Vec<SerdeNodeParam>),
+            Map(serde_yaml::Mapping),
         }
 
         let param = NodeParamSimple::deserialize(deserializer)?;
@@ -74,6 +79,7 @@ impl<'de> Deserialize<'de> for SerdeNodeParam {
             }
             NodeParamSimple::Bool(b) => SerdeNodeParam::Bool(b),
             NodeParamSimple::List(l) => SerdeNodeParam::List(l),
+            NodeParamSimple::Map(m) => SerdeNodeParam::Map(m),
         })
     }
 }
@@ -87,6 +93,7 @@ impl TryFrom<SerdeNodeParam> for ParameterValue {
             SerdeNodeParam::Int(i) => Ok(ParameterValue::IntRangeValue(i)),
             SerdeNodeParam::String(s) => Ok(ParameterValue::StringValue(s)),
             SerdeNodeParam::Bool(b) => Ok(ParameterValue::BoolValue(b)),
+            SerdeNodeParam::Map(m) => Ok(ParameterValue::MapValue(m)),
             SerdeNodeParam::NodeInput(_) => Err(()),
             SerdeNodeParam::List(l) => Ok(ParameterValue::ListValue(
                 l.into_iter().map(ParameterValue::try_from).collect::<Result<_, Self::Error>>()?,
@@ -103,30 +110,43 @@ pub struct SerdeNodeConfig {
     parameters: HashMap<String, SerdeNodeParam>,
 }
 
-impl From<SerdeNodeConfig> for ProcessingNodeConfig<String> {
-    fn from(node_config: SerdeNodeConfig) -> Self {
-        Self {
-            name: node_config.ty,
-            parameters: Parameters::new(
-                node_config
-                    .parameters
-                    .clone()
-                    .into_iter()
-                    .filter_map(|(name, param)| match param {
-                        SerdeNodeParam::NodeInput(_) => None,
-                        param => param.try_into().ok().map(|v| (name, v)),
-                    })
-                    .collect(),
-            ),
-            inputs: node_config
-                .parameters
-                .into_iter()
-                .filter_map(|(name, param)| match param {
-                    SerdeNodeParam::NodeInput(i) => Some((name, i)),
-                    _ => None,
-                })
-                .collect(),
+impl TryFrom<SerdeNodeConfig> for ProcessingNodeConfig<String> {
+    type Error = anyhow::Error;
+
+    fn try_from(node_config: SerdeNodeConfig) -> Result<Self> {
+        let mut parameters = HashMap::new();
+        let mut inputs = HashMap::new();
+        for (name, param) in node_config.parameters {
+            match param {
+                SerdeNodeParam::NodeInput(input) => {
+                    inputs.insert(name, input);
+                }
+                // a list of inputs is stored as one input per element
+                SerdeNodeParam::List(list)
+                    if !list.is_empty()
+                        && list
+                            .iter()
+                            .all(|param| matches!(param, SerdeNodeParam::NodeInput(_))) =>
+                {
+                    for (index, param) in list.into_iter().enumerate() {
+                        if let SerdeNodeParam::NodeInput(input) = param {
+                            inputs.insert(list_input_key(&name, index), input);
+                        }
+                    }
+                }
+                param => {
+                    let value = param.try_into().map_err(|_| {
+                        anyhow!(
+                            "invalid value for {name}: a list can't mix node inputs (<node) with \
+                             other values"
+                        )
+                    })?;
+                    parameters.insert(name, value);
+                }
+            }
         }
+
+        Ok(Self { name: node_config.ty, parameters: Parameters::new(parameters), inputs })
     }
 }
 
@@ -163,75 +183,382 @@ where
         }
     }
 
-    pub fn build(mut self, ctx: &ProcessingContext) -> Result<ProcessingGraph> {
-        if self.nodes.is_empty() {
-            Ok(ProcessingGraph { nodes: HashMap::new(), sinks: vec![] })
-        } else {
-            let mut is_input_to = HashMap::<_, Vec<_>>::new();
-
-            for (id, node) in self.nodes.iter() {
-                let idx = self.node_ids[id];
-                for input in node.inputs.values() {
-                    is_input_to.entry(self.node_ids[input]).or_default().push(idx);
+    /// Checks that every node exists, gets only parameters it knows about,
+    /// that all inputs refer to existing nodes, the graph contains no cycles
+    /// and the connected nodes agree on the frame types they exchange. No
+    /// nodes are created for this, so no files or devices are opened.
+    pub fn validate(&self) -> Result<()> {
+        let available_nodes = list_available_nodes();
+        // the ids are only known to be Debug, so they are compared by their debug
+        // representation to find suggestions
+        let node_names: Vec<_> = self.nodes.keys().map(|id| format!("{id:?}")).collect();
+        let mut port_types = HashMap::new();
+        for (id, node) in &self.nodes {
+            let descriptor = available_nodes.get(&node.name).ok_or_else(|| {
+                anyhow!(
+                    "node {id:?} has the unknown type {}{}",
+                    node.name,
+                    did_you_mean(&node.name, available_nodes.keys().map(String::as_str))
+                )
+            })?;
+            check_parameters(node, &descriptor.parameters_descriptor)
+                .with_context(|| format!("invalid parameters for node {id:?} ({})", node.name))?;
+            for (input_name, input) in &node.inputs {
+                if !self.nodes.contains_key(input) {
+                    return Err(anyhow!(
+                        "input {input_name} of node {id:?} refers to {input:?}, but there is no \
+                         node with that name{}",
+                        did_you_mean(&format!("{input:?}"), node_names.iter().map(String::as_str))
+                    ));
                 }
             }
+            port_types.insert(id, &descriptor.port_types);
+        }
 
-            let mut built_nodes = HashMap::<NodeID, Node>::new();
-            let mut sinks = vec![];
-
-            let mut avail: HashSet<IdTy> = self.node_ids.keys().cloned().collect();
-            let mut queue = vec![];
-            queue.push({
-                let v = avail.iter().next().cloned().unwrap();
-                avail.take(&v).unwrap()
-            });
-
-            while !queue.is_empty() {
-                let id = queue.last().unwrap().clone();
-                let idx = self.node_ids[&id];
-                let mut missing = vec![];
-                let mut finished = HashMap::new();
-
-                let node = self.nodes.remove(&id).unwrap();
-
-                for (name, input_id) in &node.inputs {
-                    if let Some(node) = built_nodes.get(&self.node_ids[input_id]) {
-                        finished.insert(name.clone(), node.clone());
-                    } else {
-                        missing.push(input_id.clone())
+        let mut output_types = HashMap::<&IdTy, OutputType>::new();
+        for id in self.topological_order()? {
+            let node = &self.nodes[id];
+            let ports: &PortTypes = port_types[id];
+            for (input_name, input) in &node.inputs {
+                let input_node = &self.nodes[input];
+                let port =
+                    split_list_input_key(input_name).map_or(input_name.as_str(), |(list, _)| list);
+                match (&output_types[input], ports.inputs.get(port)) {
+                    (OutputType::Nothing, _) => {
+                        return Err(anyhow!(
+                            "node {input:?} ({}) is a sink and can't be the input {input_name} \
+                             of node {id:?}",
+                            input_node.name
+                        ))
+                    }
+                    (OutputType::OneOf(produced), Some(accepted))
+                        if !produced.iter().any(|ty| accepted.contains(ty)) =>
+                    {
+                        return Err(anyhow!(
+                            "node {id:?} ({}) expects {accepted:?} frames on its input \
+                             {input_name}, but node {input:?} ({}) produces {produced:?} frames",
+                            node.name,
+                            input_node.name
+                        ))
                     }
+                    _ => {}
                 }
+            }
 
-                if !missing.is_empty() {
-                    queue.append(&mut missing);
-                    self.nodes.insert(id, node);
-                } else {
-                    let built_node = create_node_from_name(
-                        &node.name,
-                        idx,
-                        node.parameters,
-                        finished,
-                        is_input_to.entry(idx).or_default(),
-                        ctx,
-                    )?;
-                    if built_node.is_sink() {
-                        sinks.push(idx);
+            let output = match &ports.output {
+                // for a list of inputs the first one is used
+                OutputType::SameAsInput(input_name) => node
+                    .inputs
+                    .get(input_name)
+                    .or_else(|| node.inputs.get(&list_input_key(input_name, 0)))
+                    .map(|input| output_types[input].clone())
+                    .unwrap_or(OutputType::Unknown),
+                output => output.clone(),
+            };
+            output_types.insert(id, output);
+        }
+
+        Ok(())
+    }
+
+    /// Orders the nodes so that every node comes after all of its inputs.
+    fn topological_order(&self) -> Result<Vec<&IdTy>> {
+        // `path` is the chain of nodes that are currently visited, every node in
+        // it is an input of the node before it
+        fn visit<'a, Id: Eq + std::hash::Hash + std::fmt::Debug>(
+            nodes: &'a HashMap<Id, ProcessingNodeConfig<Id>>,
+            id: &'a Id,
+            path: &mut Vec<&'a Id>,
+            visited: &mut HashSet<&'a Id>,
+            order: &mut Vec<&'a Id>,
+        ) -> Result<()> {
+            if let Some(start) = path.iter().position(|node| *node == id) {
+                let cycle = path[start..]
+                    .iter()
+                    .chain([&id])
+                    .rev()
+                    .map(|id| format!("{id:?}"))
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(anyhow!(
+                    "the graph contains a cycle, every node is the input of the next one: {cycle}"
+                ));
+            }
+            if !visited.insert(id) {
+                return Ok(());
+            }
+
+            path.push(id);
+            let mut inputs: Vec<_> = nodes[id].inputs.iter().collect();
+            inputs.sort_by_key(|(name, _)| *name);
+            for (_, input) in inputs {
+                visit(nodes, input, path, visited, order)?;
+            }
+            path.pop();
+            order.push(id);
+            Ok(())
+        }
+
+        // visit the nodes in a fixed order, so that the order is the same for every run
+        let mut ids: Vec<_> = self.nodes.keys().collect();
+        ids.sort_by_cached_key(|id| format!("{id:?}"));
+
+        let mut order = Vec::new();
+        let mut visited = HashSet::new();
+        for id in ids {
+            visit(&self.nodes, id, &mut vec![], &mut visited, &mut order)?;
+        }
+        Ok(order)
+    }
+
+    /// The validated nodes in topological order, with the defaults filled in
+    /// for all parameters that were not given.
+    pub fn resolved_nodes(&self) -> Result<Vec<ResolvedNode<IdTy>>> {
+        self.validate()?;
+        let available_nodes = list_available_nodes();
+
+        Ok(self
+            .topological_order()?
+            .into_iter()
+            .map(|id| {
+                let node = &self.nodes[id];
+                let mut parameters: BTreeMap<_, _> = node
+                    .parameters
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone_for_same_puller()))
+                    .collect();
+                for (key, descriptor) in &available_nodes[&node.name].parameters_descriptor.0 {
+                    match descriptor {
+                        descriptor if descriptor.is_node_input() => {}
+                        WithDefault(_, value) => {
+                            parameters
+                                .entry(key.clone())
+                                .or_insert_with(|| value.clone_for_same_puller());
+                        }
+                        Mandatory(_) => {}
                     }
-                    built_nodes.insert(idx, built_node);
+                }
 
+                ResolvedNode {
+                    id: id.clone(),
+                    node_id: self.node_ids[id],
+                    ty: node.name.clone(),
+                    parameters,
+                    inputs: node
+                        .inputs
+                        .iter()
+                        .map(|(name, input)| (name.clone(), input.clone()))
+                        .collect(),
+                }
+            })
+            .collect())
+    }
 
-                    avail.remove(&id);
-                    queue.pop().unwrap();
+    /// The graph in the graphviz dot format, with an edge from every input to
+    /// the nodes using it.
+    pub fn to_dot(&self) -> Result<String>
+    where
+        IdTy: Display,
+    {
+        let mut dot = "digraph pipeline {\n".to_string();
+        for node in self.resolved_nodes()? {
+            let name = dot_string(&node.id.to_string());
+            let label = dot_string(&format!("{}\n{}", node.id, node.ty));
+            dot += &format!("    {name} [label={label}];\n");
+            for (input_name, input) in &node.inputs {
+                let (input, input_name) = (dot_string(&input.to_string()), dot_string(input_name));
+                dot += &format!("    {input} -> {name} [label={input_name}];\n");
+            }
+        }
+        Ok(dot + "}\n")
+    }
 
-                    if queue.is_empty() && !avail.is_empty() {
-                        queue.push(avail.iter().next().unwrap().clone())
+    /// The graph as a pipeline configuration file with the nodes in
+    /// topological order, the parameters sorted and all defaults filled in.
+    pub fn to_yaml(&self) -> Result<String>
+    where
+        IdTy: Display,
+    {
+        let mut nodes = serde_yaml::Mapping::new();
+        for node in self.resolved_nodes()? {
+            let mut config = serde_yaml::Mapping::new();
+            config.insert("type".into(), node.ty.into());
+            let mut lists = BTreeMap::<_, Vec<_>>::new();
+            for (name, input) in &node.inputs {
+                let reference = serde_yaml::Value::from(format!("<{input}"));
+                match split_list_input_key(name) {
+                    Some((list, index)) => lists.entry(list).or_default().push((index, reference)),
+                    None => {
+                        config.insert(name.as_str().into(), reference);
                     }
                 }
             }
+            for (name, mut references) in lists {
+                references.sort_by_key(|(index, _)| *index);
+                let references = references.into_iter().map(|(_, reference)| reference).collect();
+                config.insert(name.into(), serde_yaml::Value::Sequence(references));
+            }
+            for (name, value) in &node.parameters {
+                config.insert(name.as_str().into(), yaml_value(value));
+            }
+            nodes.insert(node.id.to_string().into(), config.into());
+        }
+        Ok(serde_yaml::to_string(&nodes)?)
+    }
+
+    pub fn build(mut self, ctx: &ProcessingContext) -> Result<ProcessingGraph>
+    where
+        IdTy: Display,
+    {
+        self.validate()?;
+        let order: Vec<IdTy> = self.topological_order()?.into_iter().cloned().collect();
+
+        let mut is_input_to = HashMap::<_, Vec<_>>::new();
+        for (id, node) in self.nodes.iter() {
+            let idx = self.node_ids[id];
+            for input in node.inputs.values() {
+                is_input_to.entry(self.node_ids[input]).or_default().push(idx);
+            }
+        }
+
+        let mut built_nodes = HashMap::<NodeID, Node>::new();
+        let mut sinks = vec![];
+        let mut graph = vec![];
+        for id in order {
+            let idx = self.node_ids[&id];
+            let node = self.nodes.remove(&id).unwrap();
+            let name = id.to_string();
+            let mut graph_node = GraphNode {
+                name: name.clone(),
+                ty: node.name.clone(),
+                inputs: node
+                    .inputs
+                    .iter()
+                    .map(|(input_name, input)| (input_name.clone(), input.to_string()))
+                    .collect(),
+                sink: false,
+            };
+            // all inputs come before this node in the topological order
+            let inputs = node
+                .inputs
+                .iter()
+                .map(|(name, input)| (name.clone(), built_nodes[&self.node_ids[input]].clone()))
+                .collect();
+
+            let built_node = create_node_from_name(
+                &node.name,
+                idx,
+                &name,
+                node.parameters,
+                inputs,
+                is_input_to.entry(idx).or_default(),
+                ctx,
+            )
+            .with_context(|| format!("could not create node {id:?} ({})", node.name))?;
+            if built_node.is_sink() {
+                sinks.push((idx, name));
+                graph_node.sink = true;
+            }
+            built_nodes.insert(idx, built_node);
+            graph.push(graph_node);
+        }
+
+        ctx.run_control().set_graph(graph);
+        for (idx, name) in &sinks {
+            ctx.run_control().add_sink(*idx, name);
+        }
+        let sinks = sinks.into_iter().map(|(idx, _)| idx).collect();
+        Ok(ProcessingGraph { nodes: built_nodes, sinks })
+    }
+}
+
+/// A node of a graph with all parameters resolved, see
+/// `ProcessingGraphBuilder::resolved_nodes`
+#[derive(Debug)]
+pub struct ResolvedNode<IdTy> {
+    pub id: IdTy,
+    pub node_id: NodeID,
+    pub ty: String,
+    pub parameters: BTreeMap<String, ParameterValue>,
+    pub inputs: BTreeMap<String, IdTy>,
+}
+
+fn yaml_value(value: &ParameterValue) -> serde_yaml::Value {
+    match value {
+        FloatRangeValue(v) => (*v).into(),
+        IntRangeValue(v) => (*v).into(),
+        StringValue(v) => v.as_str().into(),
+        BoolValue(v) => (*v).into(),
+        DurationValue(_) => value.to_string().into(),
+        MapValue(v) => serde_yaml::Value::Mapping(v.clone()),
+        ListValue(v) => serde_yaml::Value::Sequence(v.iter().map(yaml_value).collect()),
+        NodeInputValue(_) => unreachable!("node inputs are no parameters of a config"),
+    }
+}
 
-            Ok(ProcessingGraph { nodes: built_nodes, sinks })
+/// `, did you mean <candidate>?` if one of the candidates is close to `name`
+fn did_you_mean<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> String {
+    closest_match(name, candidates)
+        .map(|candidate| format!(", did you mean {candidate}?"))
+        .unwrap_or_default()
+}
+
+fn check_parameters<IdTy>(
+    node: &ProcessingNodeConfig<IdTy>,
+    descriptor: &ParametersDescriptor,
+) -> Result<()> {
+    // the elements of a list of inputs are checked as the list
+    let input_keys: HashSet<_> = node
+        .inputs
+        .keys()
+        .map(|key| split_list_input_key(key).map_or(key.as_str(), |(list, _)| list))
+        .collect();
+
+    for key in node.parameters.keys().map(String::as_str).chain(input_keys.iter().copied()) {
+        if !descriptor.0.contains_key(key) {
+            let mut known: Vec<_> = descriptor.0.keys().collect();
+            known.sort();
+            return Err(anyhow!(
+                "unknown parameter {key}{}, the known parameters are {known:?}",
+                did_you_mean(key, known.iter().map(|key| key.as_str()))
+            ));
         }
     }
+
+    for (key, ty) in &descriptor.0 {
+        let is_input = ty.is_node_input();
+        let is_list = matches!(ty.parameter_type(), ListParameter(_));
+        if input_keys.contains(key.as_str()) && !is_input {
+            return Err(anyhow!("parameter {key} was given a node input (<name), but is no input"));
+        }
+        if node.parameters.keys().any(|k| k == key) && is_input {
+            return Err(anyhow!("parameter {key} is an input and has to refer to a node (<name)"));
+        }
+        if is_input && is_list && node.inputs.contains_key(key) {
+            return Err(anyhow!("input {key} takes a list of nodes ([<a, <b])"));
+        }
+        if is_input
+            && !is_list
+            && input_keys.contains(key.as_str())
+            && !node.inputs.contains_key(key)
+        {
+            return Err(anyhow!("input {key} takes a single node (<name), not a list"));
+        }
+        // paths are only checked when the nodes are created
+        if let Some((_, value)) = node.parameters.iter().find(|(k, _)| *k == key) {
+            ty.parameter_type()
+                .value_is_of_type(value.clone_for_same_puller())
+                .with_context(|| format!("invalid value for {key}"))?;
+        }
+        if matches!(ty, Mandatory(_))
+            && !input_keys.contains(key.as_str())
+            && !node.parameters.keys().any(|k| k == key)
+        {
+            return Err(anyhow!("the mandatory parameter {key} is missing"));
+        }
+    }
+
+    Ok(())
 }
 
 pub struct ProcessingGraph {
@@ -259,10 +586,15 @@ impl ProcessingGraph {
                             let ctx = ctx.clone();
                             let sink = self.nodes.get(&id).unwrap().assert_sink()?;
                             Ok(async move {
-                                anyhow::Result::<_, anyhow::Error>::Ok(
-                                    sink.run(&*ctx, std::sync::Arc::new(progress_update_cb))
-                                        .await?,
-                                )
+                                let run_control = ctx.run_control().clone();
+                                let progress_update_cb = move |progress: ProgressUpdate| {
+                                    run_control.progress(id, progress);
+                                    progress_update_cb(progress)
+                                };
+                                let result =
+                                    sink.run(&*ctx, std::sync::Arc::new(progress_update_cb)).await;
+                                ctx.run_control().finished(id, &result);
+                                result
                             })
                         })
                         .collect::<Result<futures::stream::FuturesUnordered<_>>>()?
@@ -281,3 +613,128 @@ impl ProcessingGraph {
 
     pub fn get_node(&self, id: NodeID) -> &Node { &self.nodes[&id] }
 }
+
+/// Quotes a string for a dot file
+fn dot_string(string: &str) -> String {
+    let escaped = string.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline_processing::test_util::graph;
+
+    fn check_error(yaml: &str) -> String { format!("{:#}", graph(yaml).validate().unwrap_err()) }
+
+    #[test]
+    fn test_validate() {
+        let valid = "
+            reader: { type: RawBlobReader, file: a.raw12, width: 16, height: 16 }
+            cache: { type: Cache, input: <reader }
+            debayer: { type: CpuDebayer, input: <cache }
+            writer: { type: FfmpegWriter, output: out.mp4, input: <debayer }
+        ";
+        graph(valid).validate().unwrap();
+
+        let error = check_error(&valid.replace("height: 16", "height: 16, widht: 16"));
+        assert!(error.contains("\"reader\"") && error.contains("widht"), "{error}");
+
+        let error = check_error(&valid.replace("<cache", "<cahce"));
+        assert!(
+            error.contains("\"debayer\"") && error.contains("did you mean \"cache\"?"),
+            "{error}"
+        );
+
+        let error = check_error(&valid.replace("output: out.mp4", "path: out.mp4"));
+        assert!(error.contains("\"writer\"") && error.contains("output"), "{error}");
+
+        let error = check_error(&valid.replace("input: <reader", "input: <debayer"));
+        assert!(
+            error.contains("\"cache\" -> \"debayer\" -> \"cache\"")
+                || error.contains("\"debayer\" -> \"cache\" -> \"debayer\""),
+            "{error}"
+        );
+
+        let error = check_error(&valid.replace("CpuDebayer", "CpuDebayr"));
+        assert!(error.contains("did you mean CpuDebayer?"), "{error}");
+
+        graph(&valid.replace("<cache }", "<cache, mode: Edge-Aware }")).validate().unwrap();
+        let error = check_error(&valid.replace("<cache }", "<cache, mode: edge-awre }"));
+        assert!(
+            error.contains("invalid value for mode") && error.contains("did you mean edge-aware?"),
+            "{error}"
+        );
+
+        let error = check_error(&valid.replace("CpuDebayer", "LosslessDecompress"));
+        assert!(error.contains("\"debayer\"") && error.contains("[LosslessCompressed]"), "{error}");
+
+        let mut builder = graph(valid);
+        let config = serde_yaml::from_str::<SerdeNodeConfig>("{ type: Cache, input: <writer }");
+        builder.add("cache2".to_string(), config.unwrap().try_into().unwrap()).unwrap();
+        let error = format!("{:#}", builder.validate().unwrap_err());
+        assert!(error.contains("\"writer\"") && error.contains("sink"), "{error}");
+    }
+
+    #[test]
+    fn test_list_inputs() {
+        let valid = "
+            a: { type: RawBlobReader, file: a.raw12, width: 16, height: 16 }
+            b: { type: RawBlobReader, file: b.raw12, width: 16, height: 16 }
+            c: { type: RawBlobReader, file: c.raw12, width: 16, height: 16 }
+            concat: { type: Concat, inputs: [<b, <a, <c] }
+            debayer: { type: CpuDebayer, input: <concat }
+        ";
+        let builder = graph(valid);
+        builder.validate().unwrap();
+        let yaml: serde_yaml::Mapping = serde_yaml::from_str(&builder.to_yaml().unwrap()).unwrap();
+        assert_eq!(
+            yaml["concat"]["inputs"],
+            serde_yaml::from_str::<serde_yaml::Value>("[<b, <a, <c]").unwrap()
+        );
+
+        let error = check_error(&valid.replace("inputs: [<b, <a, <c]", "inputs: <b"));
+        assert!(error.contains("list of nodes"), "{error}");
+        let error = check_error(&valid.replace("input: <concat", "input: [<concat]"));
+        assert!(error.contains("single node"), "{error}");
+
+        let config: SerdeNodeConfig =
+            serde_yaml::from_str("{ type: Concat, inputs: [<a, 3] }").unwrap();
+        let error = ProcessingNodeConfig::try_from(config).unwrap_err().to_string();
+        assert!(error.contains("inputs") && error.contains("mix"), "{error}");
+    }
+
+    #[test]
+    fn test_parameter_types() {
+        let valid = "
+            tcp: { type: TcpReader, address: 'localhost:1234', width: 16, height: 16, reconnect-interval: 500ms }
+            reader: { type: RawBlobReader, file: a.raw12, width: 16, height: 16 }
+            denoise: { type: RowNoiseRemoval, input: <reader, inline-model: { weights_odd: { offset: 1.0 } } }
+        ";
+        let builder = graph(valid);
+        builder.validate().unwrap();
+        let yaml: serde_yaml::Mapping = serde_yaml::from_str(&builder.to_yaml().unwrap()).unwrap();
+        assert_eq!(
+            yaml["denoise"]["inline-model"]["weights_odd"]["offset"],
+            serde_yaml::Value::from(1.0)
+        );
+
+        let error = check_error(&valid.replace("500ms", "5 parsecs"));
+        assert!(error.contains("reconnect-interval") && error.contains("unknown unit"), "{error}");
+        let error = check_error(&valid.replace("{ weights_odd: { offset: 1.0 } }", "3"));
+        assert!(error.contains("invalid value for inline-model"), "{error}");
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let builder = graph(
+            r#"
+            'reader "a"': { type: RawBlobReader, file: a.raw12, width: 16, height: 16 }
+            'c:\cache': { type: Cache, input: '<reader "a"' }
+            "#,
+        );
+        let dot = builder.to_dot().unwrap();
+        assert!(dot.contains(r#""reader \"a\"" [label="reader \"a\"\nRawBlobReader"];"#), "{dot}");
+        assert!(dot.contains(r#""reader \"a\"" -> "c:\\cache" [label="input"];"#), "{dot}");
+    }
+}
@@ -49,8 +49,14 @@ pub async fn pull_unordered(
                 result?;
             }
         }
+        // stopping the run ends the range, the frames already requested are still
+        // finished
+        if !context.run_control().proceed().await {
+            range.end = range.start;
+        }
         if let Some(frame) = range.next() {
             let input = input.clone_for_same_puller();
+            let run_control = context.run_control().clone();
             let on_payload = on_payload.clone();
             let progress_callback = progress_callback.clone();
             let latest_frame = latest_frame.clone();
@@ -61,16 +67,12 @@ pub async fn pull_unordered(
                     match input.pull(Request::new(output_priority, frame)).await {
                         Ok(pulled) => on_payload(pulled, frame as _)?,
                         Err(e) => {
-                            // TODO(robin): clean up into own trait?
                             eprintln!("error pulling frame {frame}: {e:#}");
-                            if let Some(&EOFError) = e.downcast_ref::<EOFError>() {
+                            if is_eof(&e) {
                                 eprintln!("end of file, exiting");
                                 should_stop_fut.store(true, Ordering::Relaxed);
-                            } else if let Some(e) = e.downcast_ref::<Arc<anyhow::Error>>() {
-                                if let Some(&EOFError) = e.downcast_ref::<EOFError>() {
-                                    eprintln!("end of file, exiting");
-                                    should_stop_fut.store(true, Ordering::Relaxed);
-                                }
+                            } else {
+                                run_control.frame_dropped(input.puller_id(), frame, &e);
                             }
                         }
                     }
@@ -114,6 +116,7 @@ pub fn pull_ordered(
     let mut futures_ordered = FuturesOrdered::new();
 
     let (tx, rx) = flume::bounded(context.num_threads());
+    let puller_id = input.puller_id();
 
     let context = context.clone();
     std::thread::spawn(move || {
@@ -128,21 +131,20 @@ pub fn pull_ordered(
                         match input {
                             (Ok(input), _) => tx.send_async(input).await.unwrap(),
                             (Err(e), frame) => {
-                                // TODO(robin): clean up into own trait?
                                 eprintln!("error pulling frame {frame}: {e:#}");
-                                if let Some(&EOFError) = e.downcast_ref::<EOFError>() {
+                                if is_eof(&e) {
                                     eprintln!("end of file, exiting");
                                     break;
-                                } else if let Some(e) = e.downcast_ref::<Arc<anyhow::Error>>() {
-                                    if let Some(&EOFError) = e.downcast_ref::<EOFError>() {
-                                        eprintln!("end of file, exiting");
-                                        break;
-                                    }
                                 }
+                                context.run_control().frame_dropped(puller_id, frame, &e);
                             }
                         }
                     }
                 }
+                // stopping the run ends the range, the frames already requested are still sent
+                if !context.run_control().proceed().await {
+                    range.end = range.start;
+                }
                 if let Some(frame) = range.next() {
                     let input = input.clone_for_same_puller();
                     let progress_callback = progress_callback.clone();
@@ -165,3 +167,14 @@ pub fn pull_ordered(
 
     rx
 }
+
+// TODO(robin): clean up into own trait?
+fn is_eof(error: &anyhow::Error) -> bool {
+    if let Some(&EOFError) = error.downcast_ref::<EOFError>() {
+        true
+    } else if let Some(error) = error.downcast_ref::<Arc<anyhow::Error>>() {
+        matches!(error.downcast_ref::<EOFError>(), Some(&EOFError))
+    } else {
+        false
+    }
+}
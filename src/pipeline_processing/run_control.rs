@@ -0,0 +1,256 @@
+//! The state of a running graph, which can be paused, resumed and stopped, and
+//! the status of its sinks for the control server.
+
+use crate::{
+    pipeline_processing::node::{NodeID, ProgressUpdate},
+    util::async_notifier::AsyncNotifier,
+};
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// only the last errors of every sink are kept
+const MAX_ERRORS: usize = 10;
+/// the fps are the frames finished in this window
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunState {
+    Running,
+    /// the sinks request no new frames until the run is resumed
+    Paused,
+    /// the sinks request no new frames and finish, which can't be undone
+    Stopped,
+}
+
+/// A node of the graph as it was built
+#[derive(Clone, Debug, Serialize)]
+pub struct GraphNode {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub inputs: BTreeMap<String, String>,
+    pub sink: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SinkStatus {
+    pub name: String,
+    pub latest_frame: u64,
+    pub total_frames: Option<u64>,
+    /// the number of frames the sink pulled, including the dropped ones
+    pub frames: u64,
+    pub fps: f64,
+    /// frames that couldn't be pulled because of an error (other than the end
+    /// of the input)
+    pub dropped_frames: u64,
+    pub errors: Vec<String>,
+    pub finished: bool,
+    /// the error the sink stopped with
+    pub error: Option<String>,
+    #[serde(skip)]
+    recent_frames: VecDeque<Instant>,
+}
+
+impl SinkStatus {
+    fn push_error(&mut self, error: String) {
+        self.errors.push(error);
+        if self.errors.len() > MAX_ERRORS {
+            self.errors.remove(0);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RunStatus {
+    pub state: RunState,
+    pub graph: Vec<GraphNode>,
+    pub sinks: Vec<SinkStatus>,
+}
+
+#[derive(Default)]
+struct Status {
+    graph: Vec<GraphNode>,
+    sinks: HashMap<NodeID, SinkStatus>,
+}
+
+/// Shared by everything that runs with the same `ProcessingContext`. The
+/// pullers wait while the run is paused and end once it is stopped, so the
+/// sinks can finish their files like at the end of their input.
+#[derive(Clone)]
+pub struct RunControl {
+    state: AsyncNotifier<RunState>,
+    status: Arc<Mutex<Status>>,
+}
+
+impl Default for RunControl {
+    fn default() -> Self {
+        Self { state: AsyncNotifier::new(RunState::Running), status: Default::default() }
+    }
+}
+
+impl RunControl {
+    pub fn state(&self) -> RunState { self.state.update(|state| *state) }
+
+    pub fn pause(&self) -> Result<RunState> { self.transition(RunState::Paused) }
+
+    pub fn resume(&self) -> Result<RunState> { self.transition(RunState::Running) }
+
+    pub fn stop(&self) -> Result<RunState> { self.transition(RunState::Stopped) }
+
+    fn transition(&self, to: RunState) -> Result<RunState> {
+        self.state.update(|state| match *state {
+            RunState::Stopped if to != RunState::Stopped => {
+                Err(anyhow!("the run was stopped, it can't be paused or resumed anymore"))
+            }
+            _ => {
+                *state = to;
+                Ok(to)
+            }
+        })
+    }
+
+    /// Waits while the run is paused. Returns false if the run was stopped and
+    /// no more frames should be requested.
+    pub async fn proceed(&self) -> bool {
+        if self.state() == RunState::Paused {
+            self.state.wait(|state| *state != RunState::Paused).await;
+        }
+        self.state() != RunState::Stopped
+    }
+
+    /// Waits until the run is stopped, for sinks that don't pull their frames
+    /// in a loop (like servers) to know when to finish.
+    pub async fn stopped(&self) { self.state.wait(|state| *state == RunState::Stopped).await; }
+
+    /// Sets the nodes of the graph that runs with this context.
+    pub(crate) fn set_graph(&self, graph: Vec<GraphNode>) {
+        let mut status = self.status.lock();
+        status.sinks.clear();
+        status.graph = graph;
+    }
+
+    pub(crate) fn add_sink(&self, id: NodeID, name: &str) {
+        let sink = SinkStatus { name: name.to_string(), ..Default::default() };
+        self.status.lock().sinks.insert(id, sink);
+    }
+
+    pub(crate) fn progress(&self, sink: NodeID, progress: ProgressUpdate) {
+        self.update_sink(sink, |status| {
+            status.latest_frame = progress.latest_frame;
+            status.total_frames = progress.total_frames;
+            status.frames += 1;
+
+            let now = Instant::now();
+            status.recent_frames.push_back(now);
+            while status.recent_frames.front().map_or(false, |t| now - *t > FPS_WINDOW) {
+                status.recent_frames.pop_front();
+            }
+        })
+    }
+
+    pub(crate) fn frame_dropped(&self, sink: NodeID, frame: u64, error: &anyhow::Error) {
+        self.update_sink(sink, |status| {
+            status.dropped_frames += 1;
+            status.push_error(format!("frame {frame}: {error:#}"));
+        })
+    }
+
+    /// Records an error of a sink that goes on running, like a take of a
+    /// `RecordGate` that failed.
+    pub(crate) fn error(&self, sink: NodeID, error: &anyhow::Error) {
+        self.update_sink(sink, |status| status.push_error(format!("{error:#}")))
+    }
+
+    pub(crate) fn finished(&self, sink: NodeID, result: &Result<()>) {
+        self.update_sink(sink, |status| {
+            status.finished = true;
+            status.error = result.as_ref().err().map(|error| format!("{error:#}"));
+        })
+    }
+
+    fn update_sink(&self, sink: NodeID, update: impl FnOnce(&mut SinkStatus)) {
+        if let Some(status) = self.status.lock().sinks.get_mut(&sink) {
+            update(status)
+        }
+    }
+
+    pub fn status(&self) -> RunStatus {
+        let status = self.status.lock();
+        let now = Instant::now();
+        let mut sinks: Vec<_> = status
+            .sinks
+            .values()
+            .map(|sink| SinkStatus {
+                fps: frames_per_second(&sink.recent_frames, now),
+                ..sink.clone()
+            })
+            .collect();
+        sinks.sort_by(|a, b| a.name.cmp(&b.name));
+        RunStatus { state: self.state(), graph: status.graph.clone(), sinks }
+    }
+}
+
+fn frames_per_second(recent_frames: &VecDeque<Instant>, now: Instant) -> f64 {
+    let in_window = recent_frames.iter().filter(|t| now - **t <= FPS_WINDOW).count();
+    in_window as f64 / FPS_WINDOW.as_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_control() {
+        let control = RunControl::default();
+        control.set_graph(vec![]);
+        let sink = NodeID::from(1);
+        control.add_sink(sink, "writer");
+
+        for latest_frame in 0..3 {
+            control.progress(sink, ProgressUpdate { latest_frame, total_frames: Some(10) });
+        }
+        control.frame_dropped(sink, 3, &anyhow!("broken"));
+        control.error(sink, &anyhow!("take 2 failed"));
+        control.progress(NodeID::from(2), ProgressUpdate { latest_frame: 0, total_frames: None });
+
+        let status = control.status();
+        assert_eq!(status.state, RunState::Running);
+        let writer = &status.sinks[0];
+        assert_eq!((writer.frames, writer.latest_frame, writer.dropped_frames), (3, 2, 1));
+        assert_eq!(writer.fps, 3.0);
+        assert_eq!(writer.errors, vec!["frame 3: broken", "take 2 failed"]);
+
+        assert_eq!(control.pause().unwrap(), RunState::Paused);
+        assert_eq!(control.resume().unwrap(), RunState::Running);
+        assert!(futures::executor::block_on(control.proceed()));
+        let stopped = {
+            let control = control.clone();
+            std::thread::spawn(move || futures::executor::block_on(control.stopped()))
+        };
+        assert_eq!(control.stop().unwrap(), RunState::Stopped);
+        stopped.join().unwrap();
+        assert!(control.resume().is_err());
+        assert!(!futures::executor::block_on(control.proceed()));
+
+        control.finished(sink, &Err(anyhow!("disk full")));
+        let writer = &control.status().sinks[0];
+        assert!(writer.finished);
+        assert_eq!(writer.error.as_deref(), Some("disk full"));
+    }
+
+    #[test]
+    fn test_frames_per_second() {
+        let now = Instant::now();
+        let recent: VecDeque<_> =
+            [1500, 900, 500, 100].iter().map(|ms| now - Duration::from_millis(*ms)).collect();
+        assert_eq!(frames_per_second(&recent, now), 3.0);
+        assert_eq!(frames_per_second(&VecDeque::new(), now), 0.0);
+    }
+}
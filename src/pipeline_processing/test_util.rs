@@ -0,0 +1,51 @@
+//! Helpers for the tests of the nodes and the graph.
+
+use crate::pipeline_processing::{
+    parametrizable::prelude::*,
+    processing_context::ProcessingContext,
+    processing_graph::{ProcessingGraphBuilder, SerdeNodeConfig},
+};
+use anyhow::Result;
+use std::{collections::HashMap, thread::JoinHandle};
+
+/// A graph with the nodes of a yaml mapping like in a pipeline file (without
+/// variables, includes and macros)
+pub fn graph(yaml: &str) -> ProcessingGraphBuilder<String> {
+    let nodes: HashMap<String, SerdeNodeConfig> = serde_yaml::from_str(yaml).unwrap();
+    let mut builder = ProcessingGraphBuilder::new();
+    for (name, node) in nodes {
+        builder.add(name, node.try_into().unwrap()).unwrap();
+    }
+    builder
+}
+
+/// Builds the graph and runs it until its inputs end
+pub fn run_graph(yaml: &str) {
+    let context = ProcessingContext::default();
+    graph(yaml).build(&context).unwrap().run(context, |_| {}).unwrap();
+}
+
+/// Builds the graph and runs it on another thread, it can be controlled with
+/// the returned context.
+pub fn spawn_graph(yaml: &str) -> (ProcessingContext, JoinHandle<Result<()>>) {
+    let context = ProcessingContext::default();
+    let graph = graph(yaml).build(&context).unwrap();
+    let run = {
+        let context = context.clone();
+        std::thread::spawn(move || graph.run(context, |_| {}))
+    };
+    (context, run)
+}
+
+/// Creates a node with the given parameters and the defaults for the others,
+/// inputs are given as `NodeInputValue`.
+pub fn create<'a, T: Parameterizable>(
+    context: &ProcessingContext,
+    values: impl IntoIterator<Item = (&'a str, ParameterValue)>,
+) -> T {
+    let descriptor = T::describe_parameters();
+    let values = values.into_iter().map(|(key, value)| (key.to_string(), value)).collect();
+    let parameters =
+        Parameters::new(values).check_values(&descriptor).unwrap().add_defaults(descriptor);
+    T::from_parameters(parameters, &[], context).unwrap()
+}
@@ -1,5 +1,5 @@
 use std::{
-    sync::mpsc::{sync_channel, SyncSender},
+    sync::mpsc::{sync_channel, RecvTimeoutError, SyncSender},
     thread,
     time::{Duration, SystemTime},
 };
@@ -17,8 +17,11 @@ impl FPSReporter {
             let mut time = SystemTime::now();
             let mut frames = 0u128;
             loop {
-                if rx.recv_timeout(Duration::from_millis(100)).is_ok() {
-                    frames += 1;
+                match rx.recv_timeout(Duration::from_millis(100)) {
+                    Ok(()) => frames += 1,
+                    Err(RecvTimeoutError::Timeout) => {}
+                    // the reporter was dropped
+                    Err(RecvTimeoutError::Disconnected) => break,
                 }
                 let current_time = SystemTime::now();
                 let elapsed_ms = current_time.duration_since(time).unwrap().as_millis();
@@ -1,2 +1,3 @@
 pub mod async_notifier;
+pub mod edit_distance;
 pub mod fps_report;